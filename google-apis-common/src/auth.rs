@@ -73,6 +73,7 @@
 //! [`Authenticator`]: yup_oauth2::authenticator::Authenticator
 use std::future::Future;
 use std::pin::Pin;
+use std::time::SystemTime;
 
 type GetTokenOutput<'a> = Pin<
     Box<
@@ -82,11 +83,29 @@ type GetTokenOutput<'a> = Pin<
     >,
 >;
 
+type GetExpiringTokenOutput<'a> = Pin<
+    Box<
+        dyn Future<
+                Output = Result<
+                    Option<(String, Option<SystemTime>)>,
+                    Box<dyn std::error::Error + Send + Sync>,
+                >,
+            > + Send
+            + 'a,
+    >,
+>;
+
 pub trait GetToken: GetTokenClone + Send + Sync {
     /// Called whenever an API call requires authentication via an oauth2 token.
     /// Returns `Ok(None)` if a token is not necessary - otherwise, returns an error
     /// indicating the reason why a token could not be produced.
     fn get_token<'a>(&'a self, _scopes: &'a [&str]) -> GetTokenOutput<'a>;
+
+    /// Like `get_token()`, but also returns when the token expires, if that is known. Caches
+    /// holding on to tokens use it to stop handing them out before they expire.
+    fn get_expiring_token<'a>(&'a self, scopes: &'a [&str]) -> GetExpiringTokenOutput<'a> {
+        Box::pin(async move { Ok(self.get_token(scopes).await?.map(|token| (token, None))) })
+    }
 }
 
 pub trait GetTokenClone {
@@ -127,7 +146,9 @@ impl GetToken for NoToken {
 
 #[cfg(feature = "yup-oauth2")]
 mod yup_oauth2_impl {
-    use super::{GetToken, GetTokenOutput};
+    use super::{GetExpiringTokenOutput, GetToken, GetTokenOutput};
+
+    use std::time::{Duration, SystemTime};
 
     use http::Uri;
     use hyper::client::connect::Connection;
@@ -150,6 +171,16 @@ mod yup_oauth2_impl {
                     .map_err(|e| e.into())
             })
         }
+
+        fn get_expiring_token<'a>(&'a self, scopes: &'a [&str]) -> GetExpiringTokenOutput<'a> {
+            Box::pin(async move {
+                let token = self.token(scopes).await?;
+                let expires_at = token.expiration_time().map(|at| {
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(at.unix_timestamp().max(0) as u64)
+                });
+                Ok(token.token().map(|t| (t.to_owned(), expires_at)))
+            })
+        }
     }
 }
 
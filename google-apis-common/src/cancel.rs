@@ -0,0 +1,45 @@
+//! Cancelling calls cooperatively with a [`CancellationToken`]
+//!
+//! A call given a token with `cancellation_token()` stops as soon as the token is cancelled, be it
+//! while waiting for a response or for the next attempt, and fails with `Error::Cancelled`. The
+//! request in flight is dropped along the way, which closes its connection instead of leaving it
+//! to the pool half-read. Dropping the future of a call has the same effect, so a call made in a
+//! task which is aborted stops just as well.
+use std::future::Future;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// Await `fut` unless `token` is cancelled first, in which case `fut` is dropped and `None` is
+/// returned. Without a token, `fut` is awaited as is.
+pub async fn cancellable<F: Future>(
+    token: Option<&CancellationToken>,
+    fut: F,
+) -> Option<F::Output> {
+    match token {
+        Some(token) => token.run_until_cancelled(fut).await,
+        None => Some(fut.await),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    #[test]
+    fn cancels() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let token = CancellationToken::new();
+        assert_eq!(rt.block_on(cancellable(Some(&token), async { 1 })), Some(1));
+        assert_eq!(rt.block_on(cancellable(None, async { 2 })), Some(2));
+
+        let child = token.child_token();
+        token.cancel();
+        let sleep = async { tokio::time::sleep(Duration::from_secs(60)).await };
+        assert_eq!(rt.block_on(cancellable(Some(&child), sleep)), None);
+    }
+}
@@ -30,10 +30,25 @@ fn snakecase(source: &str) -> String {
     dest
 }
 
+/// Types whose fields can be named in a [`FieldMask`], like the schemas of an API.
+pub trait FieldNames {
+    /// The names of all fields, as the API spells them.
+    const FIELD_NAMES: &'static [&'static str];
+}
+
 /// A `FieldMask` as defined in `https://github.com/protocolbuffers/protobuf/blob/ec1a70913e5793a7d0a7b5fbf7e0e4f75409dd41/src/google/protobuf/field_mask.proto#L180`
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct FieldMask(Vec<String>);
 
+impl FieldMask {
+    /// A mask selecting all fields of `T`. As naming a field selects everything below it, too, it
+    /// lists only the fields of `T` itself, which keeps it short and correct as the API adds fields
+    /// to nested types.
+    pub fn full_for<T: FieldNames>() -> FieldMask {
+        FieldMask(T::FIELD_NAMES.iter().map(|name| snakecase(name)).collect())
+    }
+}
+
 impl Serialize for FieldMask {
     fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
     where
@@ -59,7 +74,7 @@ impl FromStr for FieldMask {
         let mut in_quotes = false;
         let mut prev_ind = 0;
         let mut paths = Vec::new();
-        for (i, c) in s.chars().enumerate() {
+        for (i, c) in s.char_indices() {
             if c == '`' {
                 in_quotes = !in_quotes;
             } else if in_quotes {
@@ -88,7 +103,7 @@ impl Display for FieldMask {
 
 #[cfg(test)]
 mod test {
-    use crate::field_mask::FieldMask;
+    use crate::field_mask::{FieldMask, FieldNames};
     use serde::{Deserialize, Serialize};
 
     #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -117,6 +132,28 @@ mod test {
         );
     }
 
+    #[test]
+    fn multi_byte_paths() {
+        assert_eq!(
+            "grüße.ähnlich,photo".parse::<FieldMask>().unwrap(),
+            FieldMask(vec!["grüße.ähnlich".to_string(), "photo".to_string()])
+        );
+    }
+
+    #[test]
+    fn full_for() {
+        struct Device;
+
+        impl FieldNames for Device {
+            const FIELD_NAMES: &'static [&'static str] = &["deviceId", "cpuInfo", "name"];
+        }
+
+        assert_eq!(
+            FieldMask::full_for::<Device>().to_string(),
+            "deviceId,cpuInfo,name"
+        );
+    }
+
     #[test]
     fn test_empty_wrapper() {
         assert_eq!(
@@ -0,0 +1,253 @@
+//! Building the expressions accepted by the `filter` parameter of list and report methods
+//!
+//! The fields a method can be filtered by are generated as constants of [`FilterField`], typed
+//! after the values they can be compared with. Comparing a field yields a [`Filter`], which can be
+//! combined with others and passed to the `filter()` setter of the call as a string.
+//!
+//! ```ignore
+//! use api::filters::customers_reports_count_installed_apps::{APP_TYPE, TOTAL_INSTALL_COUNT};
+//!
+//! let filter = APP_TYPE.eq("EXTENSION").and(TOTAL_INSTALL_COUNT.gte(100));
+//! assert_eq!(filter.to_string(), r#"app_type = "EXTENSION" AND total_install_count >= 100"#);
+//! hub.customers().reports_count_installed_apps("customers/my_customer").filter(&filter.to_string());
+//! ```
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A value a field can be compared with, as it is written in a filter expression.
+pub trait FilterValue {
+    fn write_literal(&self, out: &mut String);
+}
+
+/// A value with an order, which allows fields of its type to be compared with `<`, `<=`, `>` and
+/// `>=` in addition to `=` and `!=`.
+pub trait FilterOrd: FilterValue {}
+
+impl FilterValue for String {
+    fn write_literal(&self, out: &mut String) {
+        out.push('"');
+        for c in self.chars() {
+            if c == '"' || c == '\\' {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.push('"');
+    }
+}
+
+impl FilterOrd for String {}
+
+impl FilterValue for bool {
+    fn write_literal(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" });
+    }
+}
+
+macro_rules! number_filter_values {
+    ($($t:ty),*) => {
+        $(
+            impl FilterValue for $t {
+                fn write_literal(&self, out: &mut String) {
+                    out.push_str(&self.to_string());
+                }
+            }
+
+            impl FilterOrd for $t {}
+        )*
+    };
+}
+
+number_filter_values!(i32, i64, u32, u64, f32, f64);
+
+/// Timestamps are written as quoted RFC 3339 strings.
+impl FilterValue for chrono::DateTime<chrono::Utc> {
+    fn write_literal(&self, out: &mut String) {
+        out.push('"');
+        out.push_str(&self.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true));
+        out.push('"');
+    }
+}
+
+impl FilterOrd for chrono::DateTime<chrono::Utc> {}
+
+/// Dates are written as `YYYY-MM-DD`.
+impl FilterValue for chrono::NaiveDate {
+    fn write_literal(&self, out: &mut String) {
+        out.push_str(&self.format("%Y-%m-%d").to_string());
+    }
+}
+
+impl FilterOrd for chrono::NaiveDate {}
+
+/// A field a method can be filtered by, which can be compared with values of type `T`.
+#[derive(Debug)]
+pub struct FilterField<T> {
+    name: &'static str,
+    _value: PhantomData<fn(T)>,
+}
+
+impl<T> Clone for FilterField<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for FilterField<T> {}
+
+impl<T: FilterValue> FilterField<T> {
+    pub const fn new(name: &'static str) -> FilterField<T> {
+        FilterField {
+            name,
+            _value: PhantomData,
+        }
+    }
+
+    /// The name of the field as it is written in filter expressions.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn compare(self, operator: &str, value: T) -> Filter {
+        let mut expr = format!("{} {} ", self.name, operator);
+        value.write_literal(&mut expr);
+        Filter {
+            expr,
+            compound: false,
+        }
+    }
+
+    pub fn eq(self, value: impl Into<T>) -> Filter {
+        self.compare("=", value.into())
+    }
+
+    pub fn ne(self, value: impl Into<T>) -> Filter {
+        self.compare("!=", value.into())
+    }
+}
+
+impl<T: FilterOrd> FilterField<T> {
+    pub fn lt(self, value: impl Into<T>) -> Filter {
+        self.compare("<", value.into())
+    }
+
+    pub fn lte(self, value: impl Into<T>) -> Filter {
+        self.compare("<=", value.into())
+    }
+
+    pub fn gt(self, value: impl Into<T>) -> Filter {
+        self.compare(">", value.into())
+    }
+
+    pub fn gte(self, value: impl Into<T>) -> Filter {
+        self.compare(">=", value.into())
+    }
+}
+
+/// A filter expression, which is passed to the `filter()` setter of a call through its string
+/// representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    expr: String,
+    compound: bool,
+}
+
+impl Filter {
+    /// Match only what both filters match.
+    pub fn and(self, other: Filter) -> Filter {
+        Filter {
+            expr: format!("{} AND {}", self.expr, other.expr),
+            compound: true,
+        }
+    }
+
+    /// Match what any of both filters match. Not all methods support this.
+    pub fn or(self, other: Filter) -> Filter {
+        // OR binds tighter than AND, so combined filters keep their meaning in parentheses only
+        Filter {
+            expr: format!("({} OR {})", self.grouped(), other.grouped()),
+            compound: false,
+        }
+    }
+
+    fn grouped(&self) -> String {
+        match self.compound {
+            true => format!("({})", self.expr),
+            false => self.expr.clone(),
+        }
+    }
+}
+
+/// Match what the filter doesn't match.
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        Filter {
+            expr: match self.compound {
+                true => format!("NOT ({})", self.expr),
+                false => format!("NOT {}", self.expr),
+            },
+            compound: false,
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.expr)
+    }
+}
+
+impl From<Filter> for String {
+    fn from(filter: Filter) -> String {
+        filter.expr
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const APP_NAME: FilterField<String> = FilterField::new("app_name");
+    const INSTALLS: FilterField<i64> = FilterField::new("total_install_count");
+    const ENABLED: FilterField<bool> = FilterField::new("enabled");
+    const ACTIVE: FilterField<chrono::NaiveDate> = FilterField::new("last_active_date");
+
+    #[test]
+    fn expressions() {
+        let filter = APP_NAME.eq("say \"hi\"").and(INSTALLS.gte(10)).and(
+            ENABLED
+                .eq(true)
+                .or(ACTIVE.lt(chrono::NaiveDate::from_ymd_opt(2022, 1, 2).unwrap())),
+        );
+        assert_eq!(
+            filter.to_string(),
+            r#"app_name = "say \"hi\"" AND total_install_count >= 10 AND (enabled = true OR last_active_date < 2022-01-02)"#
+        );
+        assert_eq!(
+            (!APP_NAME.ne("a").and(INSTALLS.lt(1))).to_string(),
+            r#"NOT (app_name != "a" AND total_install_count < 1)"#
+        );
+        assert_eq!((!INSTALLS.gt(0)).to_string(), "NOT total_install_count > 0");
+    }
+
+    #[test]
+    fn or_binds_tighter_than_and() {
+        assert_eq!(
+            APP_NAME
+                .eq("a")
+                .and(INSTALLS.gt(1))
+                .or(ENABLED.eq(true))
+                .to_string(),
+            r#"((app_name = "a" AND total_install_count > 1) OR enabled = true)"#
+        );
+        assert_eq!(
+            APP_NAME
+                .eq("a")
+                .or(INSTALLS.gt(1).and(ENABLED.eq(true)))
+                .to_string(),
+            r#"(app_name = "a" OR (total_install_count > 1 AND enabled = true))"#
+        );
+    }
+}
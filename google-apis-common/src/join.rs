@@ -0,0 +1,84 @@
+//! Running several calls concurrently, with a bound on how many are in flight at once
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::task::Poll;
+
+/// A call whose result is stored elsewhere, as made by aggregate call builders.
+pub type BoxedCall<'a, E> = Pin<Box<dyn Future<Output = Result<(), E>> + 'a>>;
+
+/// Drive all `calls` with at most `limit` of them in flight, starting them in order. Returns the
+/// first error, which drops all calls still in flight.
+pub async fn try_join_bounded<E>(calls: Vec<BoxedCall<'_, E>>, limit: usize) -> Result<(), E> {
+    let limit = limit.max(1);
+    let mut pending = calls.into_iter();
+    let mut running = Vec::with_capacity(limit);
+    poll_fn(|cx| loop {
+        while running.len() < limit {
+            match pending.next() {
+                Some(call) => running.push(call),
+                None => break,
+            }
+        }
+        if running.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+        let before = running.len();
+        let mut i = 0;
+        while i < running.len() {
+            match running[i].as_mut().poll(cx) {
+                Poll::Ready(Ok(())) => {
+                    drop(running.remove(i));
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => i += 1,
+            }
+        }
+        if running.len() == before {
+            return Poll::Pending;
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::{Cell, RefCell};
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn bounded_and_failing() {
+        let in_flight = Cell::new(0);
+        let max_in_flight = Cell::new(0);
+        let done = RefCell::new(Vec::new());
+        let calls: Vec<BoxedCall<'_, &str>> = (0..5)
+            .map(|i| {
+                let (in_flight, max_in_flight, done) = (&in_flight, &max_in_flight, &done);
+                Box::pin(async move {
+                    in_flight.set(in_flight.get() + 1);
+                    max_in_flight.set(max_in_flight.get().max(in_flight.get()));
+                    tokio::task::yield_now().await;
+                    in_flight.set(in_flight.get() - 1);
+                    done.borrow_mut().push(i);
+                    Ok(())
+                }) as BoxedCall<'_, &str>
+            })
+            .collect();
+        assert_eq!(block_on(try_join_bounded(calls, 2)), Ok(()));
+        assert_eq!(max_in_flight.get(), 2);
+        assert_eq!(done.borrow().len(), 5);
+
+        let calls: Vec<BoxedCall<'_, &str>> = vec![
+            Box::pin(async { Ok(()) }),
+            Box::pin(async { Err("failed") }),
+        ];
+        assert_eq!(block_on(try_join_bounded(calls, 4)), Err("failed"));
+    }
+}
@@ -0,0 +1,333 @@
+//! Sending large requests without encoding them in memory first
+//!
+//! Usually, the request of a call is encoded into a buffer which is sent as a whole, along with
+//! its length. Call builders of methods taking a request offer `stream_request(true)` to instead
+//! encode it while it is sent, in chunks of [`CHUNK_SIZE`] using chunked transfer encoding, which
+//! keeps memory usage low for requests of many megabytes.
+//!
+//! Either way, `null` values are left out, as the API would take them for values to clear.
+use std::io;
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json as json;
+use serde_json::ser::{CharEscape, CompactFormatter, Formatter};
+
+/// The size of the chunks a streamed request is sent in.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Serialize `value` as compact JSON into `writer`, leaving out all `null` values like
+/// [`crate::remove_json_null_values()`] does, but without building a [`json::Value`] first.
+pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(writer: W, value: &T) -> json::Result<()> {
+    let mut serializer = json::Serializer::with_formatter(writer, SkipNulls::default());
+    value.serialize(&mut serializer)
+}
+
+/// A body which is encoded from `value` while it is sent. Encoding happens on a blocking thread of
+/// the current tokio runtime, and only ever runs ahead of the connection by one chunk.
+///
+/// If `value` fails to encode, the body fails as well, which fails the request.
+pub fn streamed<T: Serialize + Send + Sync + 'static>(value: Arc<T>) -> hyper::Body {
+    let (sender, body) = hyper::Body::channel();
+    let handle = tokio::runtime::Handle::current();
+    tokio::task::spawn_blocking(move || {
+        let mut writer = ChannelWriter {
+            sender,
+            handle,
+            buf: Vec::with_capacity(CHUNK_SIZE),
+        };
+        let encoded = to_writer(&mut writer, &*value)
+            .map_err(io::Error::from)
+            .and_then(|()| io::Write::flush(&mut writer));
+        if encoded.is_err() {
+            writer.sender.abort();
+        }
+    });
+    body
+}
+
+/// Passes on what is written to the body of a request, a chunk at a time.
+struct ChannelWriter {
+    sender: hyper::body::Sender,
+    handle: tokio::runtime::Handle,
+    buf: Vec<u8>,
+}
+
+impl io::Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+        self.handle
+            .block_on(self.sender.send_data(chunk.into()))
+            .map_err(|err| io::Error::new(io::ErrorKind::BrokenPipe, err))
+    }
+}
+
+/// Writes compact JSON without `null` values. The separator and key of each array item and object
+/// entry are held back until the first token of its value shows whether it is `null`.
+#[derive(Default)]
+struct SkipNulls {
+    /// Whether an item was written yet, for each array and object being written.
+    written: Vec<bool>,
+    /// The key of the object entry being written.
+    key: Option<Vec<u8>>,
+    /// The key of the entry, or nothing for an array item, whose value is yet to be written.
+    pending: Option<Vec<u8>>,
+}
+
+impl SkipNulls {
+    /// Write the separator and key held back for the value which is about to be written.
+    fn begin_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if let Some(pending) = self.pending.take() {
+            if let Some(written) = self.written.last_mut() {
+                if *written {
+                    writer.write_all(b",")?;
+                }
+                *written = true;
+            }
+            writer.write_all(&pending)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write a token of a key into the held back key, or of a value after its separator and key.
+macro_rules! token {
+    ($self:ident, $writer:ident, $write:ident($($arg:expr),*)) => {
+        match $self.key.as_mut() {
+            Some(key) => CompactFormatter.$write(key, $($arg),*),
+            None => {
+                $self.begin_value($writer)?;
+                CompactFormatter.$write($writer, $($arg),*)
+            }
+        }
+    };
+}
+
+impl Formatter for SkipNulls {
+    fn write_null<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if self.pending.take().is_some() {
+            return Ok(());
+        }
+        token!(self, writer, write_null())
+    }
+
+    fn write_bool<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: bool) -> io::Result<()> {
+        token!(self, writer, write_bool(value))
+    }
+
+    fn write_i8<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: i8) -> io::Result<()> {
+        token!(self, writer, write_i8(value))
+    }
+
+    fn write_i16<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: i16) -> io::Result<()> {
+        token!(self, writer, write_i16(value))
+    }
+
+    fn write_i32<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: i32) -> io::Result<()> {
+        token!(self, writer, write_i32(value))
+    }
+
+    fn write_i64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: i64) -> io::Result<()> {
+        token!(self, writer, write_i64(value))
+    }
+
+    fn write_i128<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: i128) -> io::Result<()> {
+        token!(self, writer, write_i128(value))
+    }
+
+    fn write_u8<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: u8) -> io::Result<()> {
+        token!(self, writer, write_u8(value))
+    }
+
+    fn write_u16<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: u16) -> io::Result<()> {
+        token!(self, writer, write_u16(value))
+    }
+
+    fn write_u32<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: u32) -> io::Result<()> {
+        token!(self, writer, write_u32(value))
+    }
+
+    fn write_u64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: u64) -> io::Result<()> {
+        token!(self, writer, write_u64(value))
+    }
+
+    fn write_u128<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: u128) -> io::Result<()> {
+        token!(self, writer, write_u128(value))
+    }
+
+    fn write_f32<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f32) -> io::Result<()> {
+        token!(self, writer, write_f32(value))
+    }
+
+    fn write_f64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+        token!(self, writer, write_f64(value))
+    }
+
+    fn write_number_str<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        value: &str,
+    ) -> io::Result<()> {
+        token!(self, writer, write_number_str(value))
+    }
+
+    fn begin_string<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        token!(self, writer, begin_string())
+    }
+
+    fn end_string<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        token!(self, writer, end_string())
+    }
+
+    fn write_string_fragment<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        token!(self, writer, write_string_fragment(fragment))
+    }
+
+    fn write_char_escape<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        char_escape: CharEscape,
+    ) -> io::Result<()> {
+        token!(self, writer, write_char_escape(char_escape))
+    }
+
+    fn write_raw_fragment<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        token!(self, writer, write_raw_fragment(fragment))
+    }
+
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.begin_value(writer)?;
+        self.written.push(false);
+        writer.write_all(b"[")
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.written.pop();
+        writer.write_all(b"]")
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        _writer: &mut W,
+        _first: bool,
+    ) -> io::Result<()> {
+        self.pending = Some(Vec::new());
+        Ok(())
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.begin_value(writer)?;
+        self.written.push(false);
+        writer.write_all(b"{")
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.written.pop();
+        writer.write_all(b"}")
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        _writer: &mut W,
+        _first: bool,
+    ) -> io::Result<()> {
+        self.key = Some(Vec::new());
+        Ok(())
+    }
+
+    fn end_object_key<W: ?Sized + io::Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        let mut key = self.key.take().unwrap_or_default();
+        key.push(b':');
+        self.pending = Some(key);
+        Ok(())
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    fn encode<T: Serialize>(value: &T) -> String {
+        let mut buf = Vec::new();
+        to_writer(&mut buf, value).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn skips_nulls() {
+        assert_eq!(encode(&json!(null)), "null");
+        assert_eq!(encode(&json!([null, null])), "[]");
+        assert_eq!(
+            encode(&json!({"a": null, "b": [null, 1, {"c": null}], "d": {"e": null, "f": "x\n"}})),
+            r#"{"b":[1,{}],"d":{"f":"x\n"}}"#
+        );
+
+        #[derive(Serialize)]
+        struct Instance {
+            name: Option<String>,
+            labels: std::collections::BTreeMap<u32, Option<String>>,
+            size: Option<f64>,
+        }
+        let instance = Instance {
+            name: None,
+            labels: [(1, None), (2, Some("two".to_string()))].into(),
+            size: Some(f64::NAN),
+        };
+        let mut expected = json::to_value(&instance).unwrap();
+        crate::remove_json_null_values(&mut expected);
+        assert_eq!(encode(&instance), r#"{"labels":{"2":"two"}}"#);
+        assert_eq!(
+            json::from_str::<json::Value>(&encode(&instance)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn streams_in_chunks() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let items: Vec<Option<String>> = (0..CHUNK_SIZE / 4)
+            .map(|i| (i % 2 == 0).then(|| i.to_string()))
+            .collect();
+        let expected = encode(&items);
+        let body = runtime.block_on(async {
+            let body = streamed(Arc::new(items));
+            hyper::body::to_bytes(body).await.unwrap()
+        });
+        assert_eq!(body, expected.as_bytes());
+    }
+}
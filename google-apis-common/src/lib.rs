@@ -1,7 +1,20 @@
 pub mod auth;
+pub mod cancel;
 pub mod field_mask;
+pub mod filter;
+pub mod join;
+pub mod json_body;
+pub mod project;
+pub mod rate;
+pub mod report;
+pub mod retry;
+pub mod revision;
 pub mod serde;
+pub mod service;
+pub mod status;
+pub mod trace;
 pub mod url;
+pub mod validate;
 
 use std::error;
 use std::error::Error as StdError;
@@ -26,9 +39,14 @@ use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::time::sleep;
 
 pub use auth::{GetToken, NoToken};
+pub use cancel::CancellationToken;
 pub use chrono;
-pub use field_mask::FieldMask;
+pub use field_mask::{FieldMask, FieldNames};
+pub use filter::{Filter, FilterField};
+pub use report::{PagedReport, PagedResponse};
+pub use revision::RevisionDrift;
 pub use serde_with;
+pub use validate::{Validate, ValidationError};
 #[cfg(feature = "yup-oauth2")]
 pub use yup_oauth2 as oauth2;
 
@@ -41,6 +59,24 @@ pub enum Retry {
     After(Duration),
 }
 
+impl Retry {
+    /// Abort instead of retrying once `retries` reached `max_retries`, if there is a maximum.
+    pub fn within(self, retries: u32, max_retries: Option<u32>) -> Retry {
+        match max_retries {
+            Some(max) if retries >= max => Retry::Abort,
+            _ => self,
+        }
+    }
+
+    /// Decide with `retry` instead, unless this retries already.
+    pub fn or_else<F: FnOnce() -> Retry>(self, retry: F) -> Retry {
+        match self {
+            Retry::Abort => retry(),
+            after => after,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq)]
 pub enum UploadProtocol {
     Simple,
@@ -191,6 +227,24 @@ pub trait Delegate: Send {
     /// It's also useful as you can be sure that a request will definitely be made.
     fn pre_request(&mut self) {}
 
+    /// Called right after `pre_request()` with the URL the request is sent to, including its
+    /// query. Beware that the query contains the API key, if one is used.
+    fn request_url(&mut self, _url: &str) {}
+
+    /// Called whenever the server responded to the main request, before its status is
+    /// inspected. It is called once per attempt, which makes it suitable to log status
+    /// codes, headers and latency alongside `pre_request()`.
+    fn http_response(&mut self, _res: &hyper::Response<hyper::body::Body>) {}
+
+    /// Called with the body of a successful response before it is decoded, which allows to keep
+    /// it as it was sent by the server. Media downloads are not passed on.
+    fn response_body(&mut self, _body: &str) {}
+
+    /// Called after a successful response if the hub was asked to check the API revision, and
+    /// the revision the client was generated from is older than allowed. The call proceeds
+    /// normally, as this is for diagnostic purposes only.
+    fn api_revision_drift(&mut self, _drift: &RevisionDrift) {}
+
     /// Return the size of each chunk of a resumable upload.
     /// Must be a power of two, with 1<<18 being the smallest allowed chunk size.
     /// Will be called once before starting any resumable upload.
@@ -214,7 +268,7 @@ pub trait Delegate: Send {
     /// # Arguments
     ///
     /// * `is_success` - a true value indicates the operation was successful. If false, you should
-    ///                  discard all values stored during `store_upload_url`.
+    ///   discard all values stored during `store_upload_url`.
     fn finished(&mut self, is_success: bool) {
         let _ = is_success;
     }
@@ -227,6 +281,84 @@ pub struct DefaultDelegate;
 
 impl Delegate for DefaultDelegate {}
 
+/// Allows call builders to borrow a delegate, which may be a trait object as well.
+impl<D: Delegate + ?Sized> Delegate for &mut D {
+    fn begin(&mut self, info: MethodInfo) {
+        (**self).begin(info)
+    }
+
+    fn http_error(&mut self, err: &hyper::Error) -> Retry {
+        (**self).http_error(err)
+    }
+
+    fn api_key(&mut self) -> Option<String> {
+        (**self).api_key()
+    }
+
+    fn token(
+        &mut self,
+        e: Box<dyn StdError + Send + Sync>,
+    ) -> std::result::Result<Option<String>, Box<dyn StdError + Send + Sync>> {
+        (**self).token(e)
+    }
+
+    fn upload_url(&mut self) -> Option<String> {
+        (**self).upload_url()
+    }
+
+    fn store_upload_url(&mut self, url: Option<&str>) {
+        (**self).store_upload_url(url)
+    }
+
+    fn response_json_decode_error(
+        &mut self,
+        json_encoded_value: &str,
+        json_decode_error: &json::Error,
+    ) {
+        (**self).response_json_decode_error(json_encoded_value, json_decode_error)
+    }
+
+    fn http_failure(
+        &mut self,
+        res: &hyper::Response<hyper::body::Body>,
+        err: Option<serde_json::Value>,
+    ) -> Retry {
+        (**self).http_failure(res, err)
+    }
+
+    fn pre_request(&mut self) {
+        (**self).pre_request()
+    }
+
+    fn request_url(&mut self, url: &str) {
+        (**self).request_url(url)
+    }
+
+    fn http_response(&mut self, res: &hyper::Response<hyper::body::Body>) {
+        (**self).http_response(res)
+    }
+
+    fn response_body(&mut self, body: &str) {
+        (**self).response_body(body)
+    }
+
+    fn api_revision_drift(&mut self, drift: &RevisionDrift) {
+        (**self).api_revision_drift(drift)
+    }
+
+    fn chunk_size(&mut self) -> u64 {
+        (**self).chunk_size()
+    }
+
+    fn cancel_chunk_upload(&mut self, chunk: &ContentRange) -> bool {
+        (**self).cancel_chunk_upload(chunk)
+    }
+
+    fn finished(&mut self, is_success: bool) {
+        (**self).finished(is_success)
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     /// The http connection failed
@@ -247,7 +379,8 @@ pub enum Error {
     /// We required a Token, but didn't get one from the Authenticator
     MissingToken(Box<dyn StdError + Send + Sync>),
 
-    /// The delgate instructed to cancel the operation
+    /// The delgate instructed to cancel the operation, or the cancellation token of the call was
+    /// cancelled
     Cancelled,
 
     /// An additional, free form field clashed with one of the built-in optional ones
@@ -257,11 +390,25 @@ pub enum Error {
     /// This can happen if the protocol changes in conjunction with strict json decoding.
     JsonDecodeError(String, json::Error),
 
+    /// The request value couldn't be encoded as JSON, for instance as a map in it has keys
+    /// which aren't strings.
+    JsonEncodeError(json::Error),
+
+    /// The request couldn't be built, for instance as a header like the user agent has a value
+    /// which isn't allowed in headers.
+    InvalidRequest(hyper::http::Error),
+
     /// Indicates an HTTP repsonse with a non-success status code
     Failure(hyper::Response<hyper::body::Body>),
 
     /// An IO error occurred while reading a stream into memory
     Io(std::io::Error),
+
+    /// The given quota or user project is neither a valid project ID nor a project number
+    InvalidProject(String),
+
+    /// No response was received within the timeout stored in field `.0`
+    Timeout(Duration),
 }
 
 impl Display for Error {
@@ -286,16 +433,59 @@ impl Display for Error {
             }
             Error::BadRequest(message) => writeln!(f, "Bad Request: {}", message),
             Error::MissingToken(e) => writeln!(f, "Token retrieval failed: {}", e),
-            Error::Cancelled => writeln!(f, "Operation cancelled by delegate"),
+            Error::Cancelled => writeln!(f, "Operation cancelled"),
             Error::FieldClash(field) => writeln!(
                 f,
                 "The custom parameter '{}' is already provided natively by the CallBuilder.",
                 field
             ),
             Error::JsonDecodeError(json_str, err) => writeln!(f, "{}: {}", err, json_str),
+            Error::JsonEncodeError(err) => {
+                writeln!(f, "The request couldn't be encoded as JSON: {}", err)
+            }
+            Error::InvalidRequest(err) => writeln!(f, "The request couldn't be built: {}", err),
+            Error::InvalidProject(project) => writeln!(
+                f,
+                "'{}' is neither a valid project ID nor a project number",
+                project
+            ),
             Error::Failure(response) => {
                 writeln!(f, "Http status indicates failure: {:?}", response)
             }
+            Error::Timeout(timeout) => {
+                writeln!(f, "No response was received within {:?}", timeout)
+            }
+        }
+    }
+}
+
+impl Error {
+    /// The delay the server asked to wait before retrying, if any. It is taken from the
+    /// `Retry-After` header of a failed response, or from the `RetryInfo` details of an error
+    /// which was returned as JSON.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::Failure(response) => retry::retry_after_header(response.headers()),
+            Error::BadRequest(error) => retry::retry_info_delay(error),
+            _ => None,
+        }
+    }
+
+    /// The message to show to users instead of the one meant for developers, if the server
+    /// provided a `LocalizedMessage` along with an error returned as JSON.
+    pub fn localized_message(&self) -> Option<status::LocalizedMessage> {
+        match self {
+            Error::BadRequest(error) => status::localized_message(error),
+            _ => None,
+        }
+    }
+
+    /// The error returned as JSON by the server rendered for people to read, with its code,
+    /// status, message and details, as done by [`status::pretty`].
+    pub fn pretty_status(&self) -> Option<String> {
+        match self {
+            Error::BadRequest(error) => status::pretty(error),
+            _ => None,
         }
     }
 }
@@ -305,6 +495,7 @@ impl error::Error for Error {
         match *self {
             Error::HttpError(ref err) => err.source(),
             Error::JsonDecodeError(_, ref err) => err.source(),
+            Error::JsonEncodeError(ref err) => err.source(),
             _ => None,
         }
     }
@@ -319,6 +510,75 @@ impl From<std::io::Error> for Error {
 /// A universal result type used as return for all calls.
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// The successful outcome of a call: the value decoded from the response body, along with the
+/// HTTP response it was decoded from.
+///
+/// Most callers just need the value, which is obtained with [`CallResponse::into_body()`],
+/// while the status and headers remain accessible if needed.
+#[derive(Debug)]
+pub struct CallResponse<T> {
+    response: hyper::Response<hyper::body::Body>,
+    body: T,
+}
+
+impl<T> CallResponse<T> {
+    pub fn new(response: hyper::Response<hyper::body::Body>, body: T) -> CallResponse<T> {
+        CallResponse { response, body }
+    }
+
+    /// The status code of the response.
+    pub fn status(&self) -> hyper::StatusCode {
+        self.response.status()
+    }
+
+    /// The headers of the response.
+    pub fn headers(&self) -> &hyper::HeaderMap {
+        self.response.headers()
+    }
+
+    /// The decoded value.
+    pub fn body(&self) -> &T {
+        &self.body
+    }
+
+    /// Drop the response and keep the decoded value only.
+    pub fn into_body(self) -> T {
+        self.body
+    }
+
+    /// Obtain the response along with the decoded value, e.g. to read the media of a download.
+    pub fn into_parts(self) -> (hyper::Response<hyper::body::Body>, T) {
+        (self.response, self.body)
+    }
+}
+
+impl<T> From<(hyper::Response<hyper::body::Body>, T)> for CallResponse<T> {
+    fn from((response, body): (hyper::Response<hyper::body::Body>, T)) -> Self {
+        CallResponse::new(response, body)
+    }
+}
+
+impl<T> From<CallResponse<T>> for (hyper::Response<hyper::body::Body>, T) {
+    fn from(res: CallResponse<T>) -> Self {
+        res.into_parts()
+    }
+}
+
+/// Describes a method of an API as found in its discovery document, without making a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MethodDescription {
+    /// The unique identifier of the method, like `storage.objects.get`.
+    pub id: &'static str,
+    /// The HTTP verb of the method, like `GET`.
+    pub http_method: &'static str,
+    /// The URI template of the method, relative to the base URL of the API.
+    pub path: &'static str,
+    /// The names of the parameters which must be given to make the call.
+    pub required_params: &'static [&'static str],
+    /// The OAuth scopes an access token for the call may have, of which one is enough.
+    pub scopes: &'static [&'static str],
+}
+
 /// Contains information about an API request.
 pub struct MethodInfo {
     pub id: &'static str,
@@ -593,6 +853,8 @@ where
     pub reader: &'a mut dyn ReadSeek,
     pub media_type: Mime,
     pub content_length: u64,
+    /// How often a failed request of the upload may be retried at most, if there is a maximum.
+    pub max_retries: Option<u32>,
 }
 impl<'a, A, S> ResumableUploadHelper<'a, A, S>
 where
@@ -605,6 +867,7 @@ where
     async fn query_transfer_status(
         &mut self,
     ) -> std::result::Result<u64, hyper::Result<hyper::Response<hyper::body::Body>>> {
+        let mut retries = 0;
         loop {
             match self
                 .client
@@ -635,7 +898,12 @@ where
                             RangeResponseHeader::from_bytes(hh.as_bytes())
                         }
                         None | Some(_) => {
-                            if let Retry::After(d) = self.delegate.http_failure(&r, None) {
+                            if let Retry::After(d) = self
+                                .delegate
+                                .http_failure(&r, None)
+                                .within(retries, self.max_retries)
+                            {
+                                retries += 1;
                                 sleep(d).await;
                                 continue;
                             }
@@ -645,7 +913,12 @@ where
                     return Ok(h.0.last);
                 }
                 Err(err) => {
-                    if let Retry::After(d) = self.delegate.http_error(&err) {
+                    if let Retry::After(d) = self
+                        .delegate
+                        .http_error(&err)
+                        .within(retries, self.max_retries)
+                    {
+                        retries += 1;
                         sleep(d).await;
                         continue;
                     }
@@ -673,6 +946,7 @@ where
             _ => MIN_CHUNK_SIZE,
         };
 
+        let mut retries = 0;
         loop {
             self.reader.seek(SeekFrom::Start(start)).unwrap();
 
@@ -725,10 +999,15 @@ where
                         hyper::Response::from_parts(res_parts, res_body_string.clone().into());
 
                     if !reconstructed_result.status().is_success() {
-                        if let Retry::After(d) = self.delegate.http_failure(
-                            &reconstructed_result,
-                            json::from_str(&res_body_string).ok(),
-                        ) {
+                        if let Retry::After(d) = self
+                            .delegate
+                            .http_failure(
+                                &reconstructed_result,
+                                json::from_str(&res_body_string).ok(),
+                            )
+                            .within(retries, self.max_retries)
+                        {
+                            retries += 1;
                             sleep(d).await;
                             continue;
                         }
@@ -736,7 +1015,12 @@ where
                     return Some(Ok(reconstructed_result));
                 }
                 Err(err) => {
-                    if let Retry::After(d) = self.delegate.http_error(&err) {
+                    if let Retry::After(d) = self
+                        .delegate
+                        .http_error(&err)
+                        .within(retries, self.max_retries)
+                    {
+                        retries += 1;
                         sleep(d).await;
                         continue;
                     }
@@ -832,11 +1116,34 @@ mod test_api {
     fn dyn_delegate_is_send() {
         fn with_send(_x: impl Send) {}
 
-        let mut dd = DefaultDelegate::default();
+        let mut dd = DefaultDelegate;
         let dlg: &mut dyn Delegate = &mut dd;
         with_send(dlg);
     }
 
+    #[test]
+    fn borrowed_delegate_forwards() {
+        #[derive(Default)]
+        struct Counting(u64);
+
+        impl Delegate for Counting {
+            fn chunk_size(&mut self) -> u64 {
+                self.0 += 1;
+                self.0
+            }
+        }
+
+        fn chunk_size<D: Delegate>(mut dlg: D) -> u64 {
+            dlg.chunk_size()
+        }
+
+        let mut counting = Counting::default();
+        assert_eq!(chunk_size(&mut counting), 1);
+        let dlg: &mut dyn Delegate = &mut counting;
+        assert_eq!(chunk_size(dlg), 2);
+        assert_eq!(chunk_size(DefaultDelegate), 1 << 23);
+    }
+
     #[test]
     fn test_mime() {
         let mime = MultiPartReader::mime_type();
@@ -848,4 +1155,33 @@ mod test_api {
             mime.get_param("boundary").map(|x| x.as_str())
         );
     }
+
+    #[test]
+    fn call_response() {
+        let response = hyper::Response::builder()
+            .status(201)
+            .header("etag", "abc")
+            .body(hyper::body::Body::empty())
+            .unwrap();
+        let res = CallResponse::from((response, 42u32));
+        assert_eq!(res.status(), hyper::StatusCode::CREATED);
+        assert_eq!(res.headers()["etag"], "abc");
+        assert_eq!(*res.body(), 42);
+
+        let (response, body) = res.into_parts();
+        assert_eq!(response.status(), 201);
+        assert_eq!(CallResponse::new(response, body).into_body(), 42);
+    }
+
+    #[test]
+    fn retry_within() {
+        let retry = || Retry::After(Duration::from_secs(1));
+        assert!(matches!(retry().within(3, None), Retry::After(_)));
+        assert!(matches!(retry().within(1, Some(2)), Retry::After(_)));
+        assert!(matches!(retry().within(2, Some(2)), Retry::Abort));
+        assert!(matches!(retry().within(0, Some(0)), Retry::Abort));
+        assert!(matches!(Retry::Abort.within(0, None), Retry::Abort));
+        assert!(matches!(Retry::Abort.or_else(retry), Retry::After(_)));
+        assert!(matches!(retry().or_else(|| Retry::Abort), Retry::After(_)));
+    }
 }
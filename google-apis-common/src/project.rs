@@ -0,0 +1,65 @@
+//! Validation of the projects which are billed for requests
+//!
+//! Two distinct settings name a project to bill, which are easily confused with each other and
+//! with the project owning the resource, as passed to the methods themselves:
+//!
+//! * The *quota project* is sent with each request in the [`QUOTA_PROJECT_HEADER`] header. It is
+//!   the project whose quota is consumed and which is billed for using the API, and it must have
+//!   the API enabled. Otherwise requests fail with `403 SERVICE_DISABLED`.
+//! * The *user project* is sent in the `userProject` query parameter by the methods supporting it.
+//!   It is the project billed for accessing the resource, as for instance required by
+//!   *Requester Pays* storage buckets.
+
+/// The header naming the quota project of a request.
+pub const QUOTA_PROJECT_HEADER: &str = "X-Goog-User-Project";
+
+/// Returns true if `project` is a project number, or a project ID of 6 to 30 lowercase letters,
+/// digits or hyphens which starts with a letter and doesn't end with a hyphen. Project IDs may be
+/// prefixed by the domain they were created in, like `example.com:my-project`.
+pub fn is_valid_project(project: &str) -> bool {
+    if !project.is_empty() && project.bytes().all(|b| b.is_ascii_digit()) {
+        return true;
+    }
+    let id = match project.rsplit_once(':') {
+        Some((domain, id)) if !domain.is_empty() => id,
+        Some(_) => return false,
+        None => project,
+    };
+    (6..=30).contains(&id.len())
+        && id.starts_with(|c: char| c.is_ascii_lowercase())
+        && !id.ends_with('-')
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn project_ids_and_numbers() {
+        for valid in [
+            "my-project",
+            "project-123456",
+            "a23456",
+            "123456789012",
+            "example.com:my-project",
+        ] {
+            assert!(is_valid_project(valid), "{}", valid);
+        }
+        for invalid in [
+            "",
+            "short",
+            "My-Project",
+            "1project",
+            "my-project-",
+            "my_project",
+            "projects/my-project",
+            ":my-project",
+            "a-project-id-which-is-far-too-long",
+        ] {
+            assert!(!is_valid_project(invalid), "{}", invalid);
+        }
+    }
+}
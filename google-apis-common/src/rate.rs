@@ -0,0 +1,76 @@
+//! Spacing out the requests of a hub, to stay within the rate an API allows
+//!
+//! A [`RateLimiter`] set with `rate_limit()` of a hub hands out slots for requests at an even
+//! interval. Each request of its calls, including retries, waits for its slot before it is sent,
+//! which smooths out bursts instead of having them fail with `429 Too Many Requests`. Clones of
+//! the hub share the limiter, and with it the rate.
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Lets requests through at an even interval. Clones share the slots.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    interval: Duration,
+    next: Arc<Mutex<Option<Instant>>>,
+}
+
+impl RateLimiter {
+    /// Let through `requests` per second at most.
+    pub fn per_second(requests: u32) -> RateLimiter {
+        RateLimiter::with_interval(Duration::from_secs(1) / requests.max(1))
+    }
+
+    /// Let through one request per `interval` at most.
+    pub fn with_interval(interval: Duration) -> RateLimiter {
+        RateLimiter {
+            interval,
+            next: Default::default(),
+        }
+    }
+
+    /// The time between two requests.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Wait for the next free slot, which is right away unless a request was let through less than
+    /// an interval ago.
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let slot = next.map_or(now, |next| next.max(now));
+            *next = Some(slot + self.interval);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn spaces_requests() {
+        let limiter = RateLimiter::with_interval(Duration::from_millis(20));
+        assert_eq!(
+            RateLimiter::per_second(4).interval(),
+            Duration::from_millis(250)
+        );
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let elapsed = runtime.block_on(async {
+            let start = Instant::now();
+            for _ in 0..3 {
+                limiter.clone().acquire().await;
+            }
+            start.elapsed()
+        });
+        assert!(elapsed >= Duration::from_millis(40), "{:?}", elapsed);
+    }
+}
@@ -0,0 +1,186 @@
+//! Collecting reports which are returned in pages
+//!
+//! Responses of methods returning the total amount of items along with their pages implement
+//! [`PagedResponse`]. Feeding each page into a [`PagedReport`] keeps track of the progress, and
+//! yields all items once they were fetched.
+//!
+//! ```ignore
+//! let mut report = PagedReport::new();
+//! loop {
+//!     let mut call = hub.customers().reports_count_chrome_versions("my_customer");
+//!     if let Some(token) = report.next_page_token() {
+//!         call = call.page_token(token);
+//!     }
+//!     let more = report.add_page(call.doit().await?.into_body());
+//!     println!("{:.0}%", report.progress() * 100.0);
+//!     if !more {
+//!         break;
+//!     }
+//! }
+//! for version in report {
+//!     println!("{:?}", version);
+//! }
+//! ```
+use std::collections::VecDeque;
+
+/// A response containing one page of a report, along with the total amount of its items.
+pub trait PagedResponse {
+    type Item;
+
+    /// The token to obtain the next page with, if there is one.
+    fn next_page_token(&self) -> Option<&str>;
+
+    /// The total amount of items in all pages, if known.
+    fn total_size(&self) -> Option<u64>;
+
+    /// The items of this page.
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+/// Collects the items of all pages of a report, and iterates them in order.
+pub struct PagedReport<T> {
+    items: VecDeque<T>,
+    total_size: Option<u64>,
+    fetched: u64,
+    next_page_token: Option<String>,
+    complete: bool,
+}
+
+impl<T> Default for PagedReport<T> {
+    fn default() -> Self {
+        PagedReport {
+            items: VecDeque::new(),
+            total_size: None,
+            fetched: 0,
+            next_page_token: None,
+            complete: false,
+        }
+    }
+}
+
+impl<T> PagedReport<T> {
+    pub fn new() -> PagedReport<T> {
+        Default::default()
+    }
+
+    /// Add the items of the given page, and return true if there are more pages to fetch.
+    /// The total size is taken from the first page providing it.
+    pub fn add_page<P: PagedResponse<Item = T>>(&mut self, page: P) -> bool {
+        if self.total_size.is_none() {
+            self.total_size = page.total_size();
+        }
+        self.next_page_token = page
+            .next_page_token()
+            .filter(|t| !t.is_empty())
+            .map(str::to_string);
+        self.complete = self.next_page_token.is_none();
+
+        let items = page.into_items();
+        self.fetched += items.len() as u64;
+        self.items.extend(items);
+        !self.complete
+    }
+
+    /// The token to fetch the next page with, if any.
+    pub fn next_page_token(&self) -> Option<&str> {
+        self.next_page_token.as_deref()
+    }
+
+    /// The total amount of items as reported with the first page.
+    pub fn total_size(&self) -> Option<u64> {
+        self.total_size
+    }
+
+    /// The amount of items fetched so far.
+    pub fn fetched(&self) -> u64 {
+        self.fetched
+    }
+
+    /// Returns true once the last page was added.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// The fraction of items fetched so far, between 0.0 and 1.0. It is 0.0 as long as the total
+    /// size is unknown, and 1.0 once the last page was added.
+    pub fn progress(&self) -> f32 {
+        match self.total_size {
+            _ if self.complete => 1.0,
+            Some(total) if total > 0 => (self.fetched as f32 / total as f32).min(1.0),
+            _ => 0.0,
+        }
+    }
+}
+
+impl<T> Iterator for PagedReport<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.items.pop_front()
+    }
+
+    /// At least the items fetched so far remain, and at most those which were not yielded yet
+    /// according to the total size.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let fetched = self.items.len();
+        if self.complete {
+            return (fetched, Some(fetched));
+        }
+        let yielded = self.fetched - fetched as u64;
+        let remaining = self
+            .total_size
+            .map(|total| (total.saturating_sub(yielded) as usize).max(fetched));
+        (fetched, remaining)
+    }
+}
+
+impl<T> DoubleEndedIterator for PagedReport<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.items.pop_back()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Page(Vec<u32>, Option<&'static str>, Option<u64>);
+
+    impl PagedResponse for Page {
+        type Item = u32;
+
+        fn next_page_token(&self) -> Option<&str> {
+            self.1
+        }
+
+        fn total_size(&self) -> Option<u64> {
+            self.2
+        }
+
+        fn into_items(self) -> Vec<u32> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn progress_and_size_hint() {
+        let mut report = PagedReport::new();
+        assert_eq!(report.progress(), 0.0);
+
+        assert!(report.add_page(Page(vec![1, 2], Some("a"), Some(5))));
+        assert_eq!(report.next_page_token(), Some("a"));
+        assert_eq!(report.total_size(), Some(5));
+        assert_eq!(report.progress(), 0.4);
+        assert_eq!(report.size_hint(), (2, Some(5)));
+        assert_eq!(report.next(), Some(1));
+        assert_eq!(report.size_hint(), (1, Some(4)));
+
+        assert!(!report.add_page(Page(vec![3, 4, 5], Some(""), Some(42))));
+        assert!(report.is_complete());
+        assert_eq!(report.total_size(), Some(5));
+        assert_eq!(report.progress(), 1.0);
+        assert_eq!(report.size_hint(), (4, Some(4)));
+        assert_eq!(report.next_back(), Some(5));
+        assert_eq!(report.collect::<Vec<_>>(), vec![2, 3, 4]);
+    }
+}
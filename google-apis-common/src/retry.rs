@@ -0,0 +1,289 @@
+//! Finding the delay the server asked to wait before retrying a failed request
+//!
+//! The delay is given either by the `Retry-After` header of the response, as a number of seconds
+//! or as a date, or by the `google.rpc.RetryInfo` details of an error returned as JSON.
+//!
+//! Whenever the delegate asks to retry a request, the [`RetryHook`] set with `on_retry()` of a hub
+//! is told about it right before waiting, which is all it takes to log or count retries.
+//!
+//! A [`Backoff`] set with `retry_policy()` of a hub retries requests failing with a connection
+//! error or a transient status whenever the delegate doesn't, waiting as long as the server asks to.
+//! Only requests with an idempotent method are retried that way, as others may have been carried
+//! out already.
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use hyper::header::{HeaderMap, RETRY_AFTER};
+use hyper::{Body, Method, Response, StatusCode};
+use serde_json::Value;
+
+use crate::Retry;
+
+const RETRY_INFO_TYPE: &str = "type.googleapis.com/google.rpc.RetryInfo";
+
+/// The statuses of failed requests which may well succeed when they are retried.
+pub const TRANSIENT_STATUSES: &[StatusCode] = &[
+    StatusCode::REQUEST_TIMEOUT,
+    StatusCode::TOO_MANY_REQUESTS,
+    StatusCode::INTERNAL_SERVER_ERROR,
+    StatusCode::BAD_GATEWAY,
+    StatusCode::SERVICE_UNAVAILABLE,
+    StatusCode::GATEWAY_TIMEOUT,
+];
+
+/// Whether repeating a request with `method` has the same effect as sending it once, so it is safe
+/// to retry even if it may have been carried out already.
+pub fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE
+    )
+}
+
+/// The delay given by the `Retry-After` header, if any. A date in the past is no delay at all.
+pub fn retry_after_header(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// The `retryDelay` of the `RetryInfo` details of an error response, if any.
+pub fn retry_info_delay(error: &Value) -> Option<Duration> {
+    error
+        .pointer("/error/details")?
+        .as_array()?
+        .iter()
+        .filter(|detail| detail.get("@type").and_then(Value::as_str) == Some(RETRY_INFO_TYPE))
+        .find_map(|detail| detail.get("retryDelay")?.as_str())
+        .and_then(|delay| crate::serde::duration::duration_from_str(delay).ok())
+        .and_then(|delay| delay.to_std().ok())
+}
+
+/// Called with the number of the attempt which failed, starting at 1, why it failed, and how long
+/// to wait before the next attempt is made.
+pub type RetryHook = Arc<dyn Fn(u32, &RetryReason<'_>, Duration) + Send + Sync>;
+
+/// Why a request is retried.
+#[derive(Debug)]
+pub enum RetryReason<'a> {
+    /// The request failed before a response arrived, like when the connection was reset.
+    Http(&'a hyper::Error),
+    /// The server answered with an error status, along with the error it returned as JSON, if any.
+    Failure {
+        status: StatusCode,
+        error: Option<&'a Value>,
+    },
+}
+
+impl fmt::Display for RetryReason<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryReason::Http(err) => write!(f, "{}", err),
+            RetryReason::Failure { status, error } => {
+                write!(f, "{}", status)?;
+                match error.and_then(|e| e.pointer("/error/message")?.as_str()) {
+                    Some(message) => write!(f, ": {}", message),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Retrying requests with exponential backoff, for all calls of a hub it is set for with
+/// `retry_policy()`.
+///
+/// Requests failing with a connection error or one of the [`TRANSIENT_STATUSES`] are retried up to
+/// `max_retries` times, as long as their method [`is_idempotent`]. Requests with another method,
+/// like creating a resource, are only retried after `429 Too Many Requests` or
+/// `503 Service Unavailable` with a `Retry-After` header, as the server didn't carry them out
+/// then. Each retry waits as long as the server asks to, with the `Retry-After`
+/// header or the `RetryInfo` details of the error. Otherwise, it waits twice as long as the one
+/// before, starting at `initial` and capped at `max`, minus a random part of up to half of it, so
+/// clients which failed at once don't all retry at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backoff {
+    /// The longest wait before the first retry.
+    pub initial: Duration,
+    /// The longest wait before any retry, unless the server asks for a longer one.
+    pub max: Duration,
+    /// How often a request is retried at most.
+    pub max_retries: u32,
+}
+
+impl Default for Backoff {
+    /// Waits of up to 1, 2, 4, 8 and 16 seconds for at most 5 retries.
+    fn default() -> Backoff {
+        Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(32),
+            max_retries: 5,
+        }
+    }
+}
+
+impl Backoff {
+    /// Whether to retry after `attempt`, starting at 1, of a request with `method` failed before a
+    /// response arrived.
+    pub fn http_error(&self, method: &Method, attempt: u32) -> Retry {
+        if !is_idempotent(method) {
+            return Retry::Abort;
+        }
+        self.retry(attempt, None)
+    }
+
+    /// Whether to retry after `attempt`, starting at 1, of a request with `method` failed with the
+    /// status of `res`, along with the `error` the server returned as JSON, if any.
+    pub fn http_failure(
+        &self,
+        method: &Method,
+        attempt: u32,
+        res: &Response<Body>,
+        error: Option<&Value>,
+    ) -> Retry {
+        let status = res.status();
+        if !TRANSIENT_STATUSES.contains(&status) {
+            return Retry::Abort;
+        }
+        let retry_after = retry_after_header(res.headers());
+        if !is_idempotent(method)
+            && !(retry_after.is_some()
+                && (status == StatusCode::TOO_MANY_REQUESTS
+                    || status == StatusCode::SERVICE_UNAVAILABLE))
+        {
+            return Retry::Abort;
+        }
+        self.retry(
+            attempt,
+            retry_after.or_else(|| error.and_then(retry_info_delay)),
+        )
+    }
+
+    fn retry(&self, attempt: u32, delay: Option<Duration>) -> Retry {
+        if attempt > self.max_retries {
+            return Retry::Abort;
+        }
+        Retry::After(delay.unwrap_or_else(|| self.delay(attempt, jitter())))
+    }
+
+    /// The wait after `attempt` failed, leaving out the part `jitter` of half of it.
+    fn delay(&self, attempt: u32, jitter: f64) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self.initial.saturating_mul(1 << exponent).min(self.max);
+        delay.mul_f64(1.0 - jitter / 2.0)
+    }
+}
+
+/// A random number from 0 up to 1, as hashers are keyed randomly. It is plenty for jitter.
+fn jitter() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn header_and_retry_info() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after_header(&headers), None);
+        headers.insert(RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(retry_after_header(&headers), Some(Duration::from_secs(120)));
+        headers.insert(
+            RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after_header(&headers), Some(Duration::ZERO));
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(retry_after_header(&headers), None);
+
+        let error = json!({"error": {"code": 429, "details": [
+            {"@type": "type.googleapis.com/google.rpc.ErrorInfo", "reason": "RATE_LIMIT_EXCEEDED"},
+            {"@type": RETRY_INFO_TYPE, "retryDelay": "1.500s"},
+        ]}});
+        assert_eq!(retry_info_delay(&error), Some(Duration::from_millis(1500)));
+        assert_eq!(retry_info_delay(&json!({"error": {"code": 400}})), None);
+    }
+
+    #[test]
+    fn backoff() {
+        let backoff = Backoff::default();
+        assert_eq!(backoff.delay(1, 0.0), Duration::from_secs(1));
+        assert_eq!(backoff.delay(3, 0.0), Duration::from_secs(4));
+        assert_eq!(backoff.delay(3, 1.0), Duration::from_secs(2));
+        assert_eq!(backoff.delay(40, 0.0), Duration::from_secs(32));
+        assert!((0.0..1.0).contains(&jitter()));
+        assert!(matches!(
+            backoff.http_error(&Method::GET, 5),
+            Retry::After(_)
+        ));
+        assert!(matches!(backoff.http_error(&Method::GET, 6), Retry::Abort));
+        assert!(matches!(backoff.http_error(&Method::POST, 1), Retry::Abort));
+
+        let failure = |method: Method, status: u16, retry_after: Option<&str>| {
+            let mut res = Response::builder().status(status);
+            if let Some(value) = retry_after {
+                res = res.header(RETRY_AFTER, value);
+            }
+            backoff.http_failure(&method, 1, &res.body(Body::empty()).unwrap(), None)
+        };
+        assert!(matches!(failure(Method::GET, 404, None), Retry::Abort));
+        assert!(matches!(
+            failure(Method::GET, 503, None),
+            Retry::After(d) if d <= Duration::from_secs(1)
+        ));
+        assert!(matches!(
+            failure(Method::GET, 429, Some("7")),
+            Retry::After(d) if d == Duration::from_secs(7)
+        ));
+        assert!(matches!(
+            failure(Method::DELETE, 500, None),
+            Retry::After(_)
+        ));
+        assert!(matches!(
+            failure(Method::POST, 500, Some("7")),
+            Retry::Abort
+        ));
+        assert!(matches!(failure(Method::POST, 503, None), Retry::Abort));
+        assert!(matches!(
+            failure(Method::POST, 503, Some("7")),
+            Retry::After(d) if d == Duration::from_secs(7)
+        ));
+        assert!(matches!(
+            failure(Method::PATCH, 429, Some("1")),
+            Retry::After(_)
+        ));
+    }
+
+    #[test]
+    fn reasons() {
+        let error = json!({"error": {"code": 503, "message": "The service is unavailable."}});
+        let reason = RetryReason::Failure {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            error: Some(&error),
+        };
+        assert_eq!(
+            reason.to_string(),
+            "503 Service Unavailable: The service is unavailable."
+        );
+        let reason = RetryReason::Failure {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            error: None,
+        };
+        assert_eq!(reason.to_string(), "429 Too Many Requests");
+    }
+}
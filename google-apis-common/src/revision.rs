@@ -0,0 +1,67 @@
+//! Detecting clients generated from an outdated revision of an API
+//!
+//! Each crate is generated from a particular revision of the API's discovery document, which it
+//! provides as `API_REVISION`. Fields which were added to the API later are silently missing from
+//! the generated types. If enabled on the hub, the revision is compared to the date the server
+//! responded at, and the delegate is told about the drift if the revision is older than allowed.
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use hyper::header::{HeaderMap, DATE};
+
+/// The revision a client was generated from, and how much older it is than the server's response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevisionDrift {
+    /// The revision of the API the client was generated from, like `20230522`.
+    pub revision: String,
+    /// The time between the revision and the date of the server response.
+    pub age: chrono::Duration,
+}
+
+/// Returns the drift if `revision`, formatted as `YYYYMMDD`, is older than `max_age` compared to
+/// the `Date` header of the response, or the local clock if there is none. Revisions which can't
+/// be parsed are never considered to be outdated.
+pub fn revision_drift(
+    revision: &str,
+    headers: &HeaderMap,
+    max_age: chrono::Duration,
+) -> Option<RevisionDrift> {
+    let released = Utc.from_utc_datetime(
+        &NaiveDate::parse_from_str(revision, "%Y%m%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)?,
+    );
+    let now = headers
+        .get(DATE)
+        .and_then(|date| date.to_str().ok())
+        .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+        .map(|date| date.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+    let age = now - released;
+    (age > max_age).then(|| RevisionDrift {
+        revision: revision.to_string(),
+        age,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn drift_against_date_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(DATE, "Sat, 01 Jul 2023 12:00:00 GMT".parse().unwrap());
+
+        let drift = revision_drift("20230101", &headers, chrono::Duration::days(90)).unwrap();
+        assert_eq!(drift.revision, "20230101");
+        assert_eq!(drift.age.num_days(), 181);
+
+        assert_eq!(
+            revision_drift("20230601", &headers, chrono::Duration::days(90)),
+            None
+        );
+        assert_eq!(
+            revision_drift("00000000", &headers, chrono::Duration::days(90)),
+            None
+        );
+    }
+}
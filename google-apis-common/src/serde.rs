@@ -1,6 +1,7 @@
 pub mod duration {
     use serde::{Deserialize, Deserializer};
     use serde_with::{DeserializeAs, SerializeAs};
+    use std::borrow::Cow;
     use std::fmt::Formatter;
     use std::str::FromStr;
 
@@ -9,7 +10,7 @@ pub mod duration {
     const MAX_SECONDS: i64 = 315576000000i64;
 
     #[derive(Debug)]
-    enum ParseDurationError {
+    pub(crate) enum ParseDurationError {
         MissingSecondSuffix,
         NanosTooSmall,
         ParseIntError(std::num::ParseIntError),
@@ -53,7 +54,7 @@ pub mod duration {
 
     impl std::error::Error for ParseDurationError {}
 
-    fn duration_from_str(s: &str) -> Result<Duration, ParseDurationError> {
+    pub(crate) fn duration_from_str(s: &str) -> Result<Duration, ParseDurationError> {
         // TODO: Test strings like -.s, -0.0s
         let value = match s.strip_suffix('s') {
             None => return Err(ParseDurationError::MissingSecondSuffix),
@@ -130,13 +131,15 @@ pub mod duration {
         where
             D: Deserializer<'de>,
         {
-            let s = Deserialize::deserialize(deserializer)?;
-            duration_from_str(s).map_err(serde::de::Error::custom)
+            let s: Cow<str> = Deserialize::deserialize(deserializer)?;
+            duration_from_str(&s).map_err(serde::de::Error::custom)
         }
     }
 }
 
 pub mod urlsafe_base64 {
+    use std::borrow::Cow;
+
     use serde::{Deserialize, Deserializer, Serializer};
     use serde_with::{DeserializeAs, SerializeAs};
 
@@ -160,19 +163,71 @@ pub mod urlsafe_base64 {
         where
             D: Deserializer<'de>,
         {
-            let s: &str = Deserialize::deserialize(deserializer)?;
-            base64::decode_config(s, base64::URL_SAFE).map_err(serde::de::Error::custom)
+            let s: Cow<str> = Deserialize::deserialize(deserializer)?;
+            base64::decode_config(&*s, base64::URL_SAFE).map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+/// 32 bit integers are numbers in JSON, but some endpoints and transcoders send them as strings.
+/// Both are accepted, while numbers are always written.
+pub mod number_or_string {
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde_with::{DeserializeAs, SerializeAs};
+
+    pub struct Wrapper;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString<T> {
+        Number(T),
+        String(String),
+    }
+
+    impl<T: Serialize> SerializeAs<T> for Wrapper {
+        fn serialize_as<S>(value: &T, s: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            value.serialize(s)
+        }
+    }
+
+    impl<'de, T> DeserializeAs<'de, T> for Wrapper
+    where
+        T: Deserialize<'de> + FromStr,
+        T::Err: Display,
+    {
+        fn deserialize_as<D>(deserializer: D) -> Result<T, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            match NumberOrString::deserialize(deserializer)? {
+                NumberOrString::Number(n) => Ok(n),
+                NumberOrString::String(s) => s.trim().parse().map_err(serde::de::Error::custom),
+            }
         }
     }
 }
 
+/// The envelope of requests and responses of APIs using the legacy `dataWrapper` convention, like
+/// `{"data": {...}}`. Call builders of such APIs wrap their request and unwrap their response with
+/// it, so the schemas describe the bare object.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DataWrapper<T> {
+    pub data: T,
+}
+
 pub fn datetime_to_string(datetime: &chrono::DateTime<chrono::offset::Utc>) -> String {
     datetime.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
 }
 
 #[cfg(test)]
 mod test {
-    use super::{duration, urlsafe_base64};
+    use super::{duration, number_or_string, urlsafe_base64, DataWrapper};
     use serde::{Deserialize, Serialize};
     use serde_with::{serde_as, DisplayFromStr};
 
@@ -219,6 +274,17 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_de_from_value() {
+        // Values which own their strings, like those assembled from command line arguments.
+        let wrapper: DurationWrapper =
+            serde_json::from_value(serde_json::json!({"duration": "5400s"})).unwrap();
+        assert_eq!(Some(5400), wrapper.duration.map(|d| d.num_seconds()));
+        let wrapper: Base64Wrapper =
+            serde_json::from_value(serde_json::json!({"bytes": "aGk="})).unwrap();
+        assert_eq!(Some(b"hi".to_vec()), wrapper.bytes);
+    }
+
     #[test]
     fn test_duration_de_failure_cases() {
         let durations = ["1.-3s", "1.1111111111s", "1.2"];
@@ -297,6 +363,33 @@ mod test {
         );
     }
 
+    #[serde_as]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct I32Wrapper {
+        #[serde_as(as = "Option<number_or_string::Wrapper>")]
+        num: Option<i32>,
+        #[serde_as(as = "Vec<number_or_string::Wrapper>")]
+        nums: Vec<u32>,
+    }
+
+    #[test]
+    fn i32_from_number_or_string() {
+        let wrapper = I32Wrapper {
+            num: Some(-42),
+            nums: vec![1, 2],
+        };
+        assert_eq!(
+            wrapper,
+            serde_json::from_str(r#"{"num": "-42", "nums": [1, "2"]}"#).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&wrapper).unwrap(),
+            r#"{"num":-42,"nums":[1,2]}"#
+        );
+        assert!(serde_json::from_str::<I32Wrapper>(r#"{"num": "x", "nums": []}"#).is_err());
+        assert!(serde_json::from_str::<I32Wrapper>(r#"{"num": 1.5, "nums": []}"#).is_err());
+    }
+
     #[test]
     fn test_empty_wrapper() {
         assert_eq!(
@@ -312,4 +405,19 @@ mod test {
             serde_json::from_str("{}").unwrap()
         );
     }
+
+    #[test]
+    fn data_wrapper_roundtrip() {
+        let wrapped: DataWrapper<I64Wrapper> =
+            serde_json::from_str(r#"{"data": {"num": "42"}}"#).unwrap();
+        assert_eq!(wrapped.data, I64Wrapper { num: Some(42) });
+        assert_eq!(
+            serde_json::to_string(&DataWrapper {
+                data: &wrapped.data
+            })
+            .unwrap(),
+            r#"{"data":{"num":"42"}}"#
+        );
+        assert!(serde_json::from_str::<DataWrapper<I64Wrapper>>(r#"{"num": "42"}"#).is_err());
+    }
 }
@@ -0,0 +1,90 @@
+//! Making calls through a `tower_service::Service`, to wrap them in middleware
+//!
+//! Call builders which are ready to be made implement [`ServiceRequest`], and [`CallService`] is
+//! a service taking them as requests. This allows to stack layers of the tower ecosystem around
+//! all calls in the same way, like timeouts, rate limits or load shedding.
+//!
+//! ```ignore
+//! use tower::{Service, ServiceBuilder, ServiceExt};
+//!
+//! let mut service = ServiceBuilder::new()
+//!     .rate_limit(10, Duration::from_secs(1))
+//!     .service(client::service::CallService);
+//! let call = hub.customers().telemetry_devices_get("customers/my_customer/telemetry/devices/abc");
+//! let (_, device) = service.ready().await?.call(call).await?.into_parts();
+//! ```
+//!
+//! Layers which need to clone requests, like retries, can't be used as call builders are not
+//! `Clone`.
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::{Error, Result};
+
+/// The future of a call made through a service.
+pub type CallFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// A call builder which can be made without further arguments, as done by its `doit()` method.
+pub trait ServiceRequest {
+    /// What the call returns on success, usually a [`crate::CallResponse`].
+    type Response;
+    /// The future making the call.
+    type Future: Future<Output = Result<Self::Response>>;
+
+    /// Make the call, just like `doit()`.
+    fn into_call(self) -> Self::Future;
+}
+
+/// A service making each call it is given, and ready to do so at all times.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CallService;
+
+impl<R: ServiceRequest> tower_service::Service<R> for CallService {
+    type Response = R::Response;
+    type Error = Error;
+    type Future = R::Future;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        request.into_call()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::future::poll_fn;
+
+    use tower_service::Service;
+
+    struct Answer(u32);
+
+    impl ServiceRequest for Answer {
+        type Response = u32;
+        type Future = CallFuture<'static, u32>;
+
+        fn into_call(self) -> Self::Future {
+            Box::pin(async move { Ok(self.0) })
+        }
+    }
+
+    #[test]
+    fn calls() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut service = CallService;
+        runtime.block_on(async {
+            assert!(matches!(
+                poll_fn(|cx| Service::<Answer>::poll_ready(&mut service, cx)).await,
+                Ok(())
+            ));
+            assert!(matches!(service.call(Answer(42)).await, Ok(42)));
+        });
+    }
+}
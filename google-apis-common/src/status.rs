@@ -0,0 +1,220 @@
+//! Details of the `google.rpc.Status` of errors returned as JSON
+//!
+//! Besides the `message` meant for developers, the details of an error may hold a
+//! `google.rpc.LocalizedMessage`, which is meant to be shown to the user.
+//!
+//! [`pretty`] renders the code, status, message and details of an error for people to read.
+//!
+//! Errors only come with their details in the format selected by [`ErrorFormat::V2`], which hubs
+//! ask for by default through the `$.xgafv` parameter.
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const LOCALIZED_MESSAGE_TYPE: &str = "type.googleapis.com/google.rpc.LocalizedMessage";
+
+/// The format of errors returned as JSON, as selected by the `$.xgafv` parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ErrorFormat {
+    /// The legacy format, with a list of `errors` instead of a `status` and `details`.
+    V1,
+    /// The format of a `google.rpc.Status`, with its `status` and `details`.
+    #[default]
+    V2,
+}
+
+impl ErrorFormat {
+    /// The name of the parameter selecting the format.
+    pub const PARAM: &'static str = "$.xgafv";
+
+    /// The value of the parameter selecting this format.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorFormat::V1 => "1",
+            ErrorFormat::V2 => "2",
+        }
+    }
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    /// Parse either the name of the format, like `v2`, or its parameter value, like `2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "v1" | "1" => Ok(ErrorFormat::V1),
+            "v2" | "2" => Ok(ErrorFormat::V2),
+            _ => Err("expected 'v1' or 'v2'".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ErrorFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorFormat::V1 => "v1",
+            ErrorFormat::V2 => "v2",
+        })
+    }
+}
+
+/// A message which is safe to show to the user, in the given locale.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct LocalizedMessage {
+    /// The locale of the message, like `en-US` or `fr-CH`.
+    pub locale: String,
+    /// The localized message.
+    pub message: String,
+}
+
+/// The first `LocalizedMessage` of the details of an error response, if any.
+pub fn localized_message(error: &Value) -> Option<LocalizedMessage> {
+    error
+        .pointer("/error/details")?
+        .as_array()?
+        .iter()
+        .filter(|detail| {
+            detail.get("@type").and_then(Value::as_str) == Some(LOCALIZED_MESSAGE_TYPE)
+        })
+        .find_map(|detail| LocalizedMessage::deserialize(detail).ok())
+        .filter(|localized| !localized.message.is_empty())
+}
+
+/// Renders the `google.rpc.Status` of an error response for people to read, as
+/// `ERROR <code> (<status>): <message>` followed by its details. Each detail is headed by the name
+/// of its type, like `ErrorInfo`, with its fields indented below, while errors in the legacy format
+/// list their `errors` instead. Returns `None` unless `error` is an error response.
+pub fn pretty(error: &Value) -> Option<String> {
+    let status = error.get("error")?.as_object()?;
+    let mut out = String::from("ERROR");
+    if let Some(code) = status.get("code").and_then(Value::as_i64) {
+        write!(out, " {}", code).ok();
+    }
+    if let Some(name) = status.get("status").and_then(Value::as_str) {
+        write!(out, " ({})", name).ok();
+    }
+    let message = status.get("message").and_then(Value::as_str);
+    write!(out, ": {}", message.unwrap_or("no message provided")).ok();
+    let list = |key| {
+        status
+            .get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+    };
+    for detail in list("details") {
+        let mut detail = detail.clone();
+        let type_url = detail.as_object_mut().and_then(|d| d.remove("@type"));
+        let name = type_url
+            .as_ref()
+            .and_then(Value::as_str)
+            .and_then(|t| t.rsplit('.').next())
+            .unwrap_or("detail");
+        write!(out, "\n  {}:", name).ok();
+        write_value(&mut out, &detail, 4);
+    }
+    for legacy in list("errors") {
+        out.push_str("\n  error:");
+        write_value(&mut out, legacy, 4);
+    }
+    Some(out)
+}
+
+/// Appends `value` to a line ending in a key, with the fields of objects and the items of lists on
+/// lines of their own, indented by `indent`.
+fn write_value(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                write!(out, "\n{:indent$}{}:", "", key, indent = indent).ok();
+                write_value(out, value, indent + 2);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                write!(out, "\n{:indent$}-", "", indent = indent).ok();
+                write_value(out, item, indent + 2);
+            }
+        }
+        Value::String(s) => {
+            write!(out, " {}", s).ok();
+        }
+        scalar => {
+            write!(out, " {}", scalar).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn localized() {
+        let error = json!({"error": {"code": 403, "message": "Caller lacks permission", "details": [
+            {"@type": "type.googleapis.com/google.rpc.ErrorInfo", "reason": "ACCESS_DENIED"},
+            {"@type": LOCALIZED_MESSAGE_TYPE, "locale": "de-DE", "message": "Zugriff verweigert"},
+        ]}});
+        assert_eq!(
+            localized_message(&error),
+            Some(LocalizedMessage {
+                locale: "de-DE".to_string(),
+                message: "Zugriff verweigert".to_string(),
+            })
+        );
+        assert_eq!(localized_message(&json!({"error": {"code": 400}})), None);
+    }
+
+    #[test]
+    fn pretty_status() {
+        let error = json!({"error": {"code": 403, "message": "Caller lacks permission",
+            "status": "PERMISSION_DENIED", "details": [
+            {"@type": "type.googleapis.com/google.rpc.ErrorInfo", "reason": "ACCESS_DENIED",
+             "metadata": {"service": "pubsub.googleapis.com"}},
+            {"@type": "type.googleapis.com/google.rpc.BadRequest", "fieldViolations": [
+                {"field": "name", "description": "too long"}]},
+            {"@type": LOCALIZED_MESSAGE_TYPE, "locale": "de-DE", "message": "Zugriff verweigert"},
+        ]}});
+        assert_eq!(
+            pretty(&error).unwrap(),
+            "ERROR 403 (PERMISSION_DENIED): Caller lacks permission
+  ErrorInfo:
+    metadata:
+      service: pubsub.googleapis.com
+    reason: ACCESS_DENIED
+  BadRequest:
+    fieldViolations:
+      -
+        description: too long
+        field: name
+  LocalizedMessage:
+    locale: de-DE
+    message: Zugriff verweigert"
+        );
+
+        let legacy = json!({"error": {"code": 404, "message": "Not Found", "errors": [
+            {"domain": "global", "reason": "notFound", "message": "Not Found"}]}});
+        assert_eq!(
+            pretty(&legacy).unwrap(),
+            "ERROR 404: Not Found
+  error:
+    domain: global
+    message: Not Found
+    reason: notFound"
+        );
+        assert_eq!(pretty(&json!({"error": "invalid_grant"})), None);
+    }
+
+    #[test]
+    fn error_formats() {
+        assert_eq!(ErrorFormat::default().as_str(), "2");
+        assert_eq!("v1".parse::<ErrorFormat>(), Ok(ErrorFormat::V1));
+        assert_eq!("2".parse::<ErrorFormat>(), Ok(ErrorFormat::V2));
+        assert!("3".parse::<ErrorFormat>().is_err());
+        assert_eq!(ErrorFormat::V1.to_string(), "v1");
+    }
+}
@@ -0,0 +1,31 @@
+//! Emitting `tracing` events about the requests of calls, once enabled with `tracing(true)` of a hub
+//!
+//! All events have the target `google_apis`, the id of the method as `method`, like
+//! `chromemanagement.customers.apps.get`, and the number of the attempt as `attempt`, starting at 1:
+//!
+//! * a `DEBUG` event for each response, with its `status`,
+//! * a `WARN` event for each request which failed before a response arrived, with the `error`,
+//! * an `INFO` event for each retry, with the `delay_ms` until the next attempt.
+//!
+//! They are only seen once the application installs a subscriber, like the one of the
+//! `tracing-subscriber` crate.
+use std::time::Duration;
+
+use hyper::StatusCode;
+
+pub use tracing;
+
+/// A response with `status` arrived for `attempt` of a call of `method`.
+pub fn response(method: &str, attempt: u32, status: StatusCode) {
+    tracing::debug!(target: "google_apis", method, attempt, status = status.as_u16(), "response");
+}
+
+/// `attempt` of a call of `method` failed before a response arrived.
+pub fn http_error(method: &str, attempt: u32, error: &hyper::Error) {
+    tracing::warn!(target: "google_apis", method, attempt, %error, "request failed");
+}
+
+/// `attempt` of a call of `method` is retried after `delay`.
+pub fn retry(method: &str, attempt: u32, delay: Duration) {
+    tracing::info!(target: "google_apis", method, attempt, delay_ms = delay.as_millis() as u64, "retrying");
+}
@@ -69,3 +69,31 @@ impl<'a> Params<'a> {
         Url::parse_with_params(url, &self.params).unwrap()
     }
 }
+
+/// Make sure the given base url ends with exactly one slash, as relative paths are appended to it.
+pub fn with_trailing_slash(url: String) -> String {
+    let trimmed = url.trim_end_matches('/');
+    if trimmed.len() + 1 == url.len() {
+        return url;
+    }
+    format!("{}/", trimmed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trailing_slash() {
+        for url in [
+            "https://example.com/api",
+            "https://example.com/api/",
+            "https://example.com/api//",
+        ] {
+            assert_eq!(
+                with_trailing_slash(url.to_string()),
+                "https://example.com/api/"
+            );
+        }
+    }
+}
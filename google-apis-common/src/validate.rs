@@ -0,0 +1,483 @@
+//! Checking calls against the constraints documented by the API before making them
+//!
+//! Discovery documents note the values an enumeration allows, patterns strings have to match and
+//! ranges numbers have to be in. The `validate()` method of each call builder checks its parameters
+//! and request against them, which reports mistakes with the path of the offending value instead
+//! of a generic `400 Bad Request` from the server.
+//!
+//! ```ignore
+//! let call = hub.projects().locations_instances_create(instance, "projects/my-project");
+//! if let Err(errors) = call.validate() {
+//!     for error in errors {
+//!         eprintln!("{}", error);
+//!     }
+//! }
+//! ```
+//!
+//! Patterns are matched as a whole with the `regex` crate, whose run time is linear in the length
+//! of the value. Patterns using syntax it doesn't support, like look-around, aren't checked.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+use regex::Regex;
+
+/// A value which doesn't satisfy a constraint documented by the API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The path to the value, like `name` or `items[0].kind`.
+    pub field: String,
+    /// How the value violates the constraint.
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Implemented by all schemas with fields the API documents constraints for, directly or below
+/// nested schemas.
+pub trait Validate {
+    /// Push a [`ValidationError`] for each value at or below `path` which violates a constraint
+    /// onto `errors`.
+    fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>);
+
+    /// Check all values against their constraints, returning the violations if there are any.
+    fn validate(&self) -> std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        self.validate_at("", &mut errors);
+        into_result(errors)
+    }
+}
+
+impl<T: Validate> Validate for Option<T> {
+    fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>) {
+        if let Some(value) = self {
+            value.validate_at(path, errors);
+        }
+    }
+}
+
+impl<T: Validate> Validate for Box<T> {
+    fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>) {
+        (**self).validate_at(path, errors);
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>) {
+        for (i, value) in self.iter().enumerate() {
+            value.validate_at(&item_path(path, i), errors);
+        }
+    }
+}
+
+impl<T: Validate> Validate for HashMap<String, T> {
+    fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>) {
+        for (key, value) in self {
+            value.validate_at(&field_path(path, key), errors);
+        }
+    }
+}
+
+/// A value of type `T`, or a container of such values, which a constraint on `T` applies to
+/// each of.
+pub trait Values<T: ?Sized> {
+    /// Call `f` with the path and the value of each contained value.
+    fn for_each_value(&self, path: &str, f: &mut dyn FnMut(&str, &T));
+}
+
+impl Values<str> for String {
+    fn for_each_value(&self, path: &str, f: &mut dyn FnMut(&str, &str)) {
+        f(path, self)
+    }
+}
+
+macro_rules! number_values {
+    ($($t:ty),*) => {
+        $(
+            impl Values<$t> for $t {
+                fn for_each_value(&self, path: &str, f: &mut dyn FnMut(&str, &$t)) {
+                    f(path, self)
+                }
+            }
+        )*
+    };
+}
+
+number_values!(i32, i64, u32, u64, f32, f64);
+
+impl<T: ?Sized, V: Values<T>> Values<T> for Option<V> {
+    fn for_each_value(&self, path: &str, f: &mut dyn FnMut(&str, &T)) {
+        if let Some(value) = self {
+            value.for_each_value(path, f);
+        }
+    }
+}
+
+impl<T: ?Sized, V: Values<T>> Values<T> for Vec<V> {
+    fn for_each_value(&self, path: &str, f: &mut dyn FnMut(&str, &T)) {
+        for (i, value) in self.iter().enumerate() {
+            value.for_each_value(&item_path(path, i), f);
+        }
+    }
+}
+
+impl<T: ?Sized, V: Values<T>> Values<T> for HashMap<String, V> {
+    fn for_each_value(&self, path: &str, f: &mut dyn FnMut(&str, &T)) {
+        for (key, value) in self {
+            value.for_each_value(&field_path(path, key), f);
+        }
+    }
+}
+
+/// The path of the field `name` of the object at `path`.
+pub fn field_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn item_path(path: &str, index: usize) -> String {
+    format!("{}[{}]", path, index)
+}
+
+/// `Ok(())` if there are no `errors`, or `Err(errors)` otherwise.
+pub fn into_result(errors: Vec<ValidationError>) -> std::result::Result<(), Vec<ValidationError>> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Check that all strings of `value` are one of `allowed`.
+pub fn check_enum<V: Values<str> + ?Sized>(
+    path: &str,
+    value: &V,
+    allowed: &[&str],
+    errors: &mut Vec<ValidationError>,
+) {
+    value.for_each_value(path, &mut |path, v| {
+        if !allowed.contains(&v) {
+            errors.push(ValidationError {
+                field: path.to_string(),
+                message: format!("'{}' is not one of {}", v, allowed.join(", ")),
+            });
+        }
+    });
+}
+
+/// Check that all strings of `value` match `pattern` as a whole. Nothing is checked if the
+/// pattern uses syntax which isn't supported.
+pub fn check_pattern<V: Values<str> + ?Sized>(
+    path: &str,
+    value: &V,
+    pattern: &str,
+    errors: &mut Vec<ValidationError>,
+) {
+    let regex = match compiled(pattern) {
+        Some(regex) => regex,
+        None => return,
+    };
+    value.for_each_value(path, &mut |path, v| {
+        if !regex.is_match(v) {
+            errors.push(ValidationError {
+                field: path.to_string(),
+                message: format!("'{}' doesn't match the pattern '{}'", v, pattern),
+            });
+        }
+    });
+}
+
+/// Check that all numbers of `value` are within `min` and `max`, where given.
+pub fn check_range<T, V>(
+    path: &str,
+    value: &V,
+    min: Option<T>,
+    max: Option<T>,
+    errors: &mut Vec<ValidationError>,
+) where
+    T: PartialOrd + fmt::Display,
+    V: Values<T> + ?Sized,
+{
+    value.for_each_value(path, &mut |path, v| {
+        let message = match (&min, &max) {
+            (Some(min), _) if v < min => format!("{} is less than the minimum of {}", v, min),
+            (_, Some(max)) if v > max => format!("{} is greater than the maximum of {}", v, max),
+            _ => return,
+        };
+        errors.push(ValidationError {
+            field: path.to_string(),
+            message,
+        });
+    });
+}
+
+/// Check that all strings of `value` only order by the `allowed` fields. Each string is a comma
+/// separated list of fields, each optionally followed by `asc` or `desc`, like
+/// `name, create_time desc`. Fields are compared ignoring case, and a close enough allowed field is
+/// suggested for an unknown one.
+pub fn check_order_by<V: Values<str> + ?Sized>(
+    path: &str,
+    value: &V,
+    allowed: &[&str],
+    errors: &mut Vec<ValidationError>,
+) {
+    value.for_each_value(path, &mut |path, v| {
+        for order in v.split(',') {
+            let words: Vec<&str> = order.split_whitespace().collect();
+            let message = match words[..] {
+                [] => continue,
+                [field, ..] if !allowed.iter().any(|a| a.eq_ignore_ascii_case(field)) => {
+                    let suggestion = allowed
+                        .iter()
+                        .map(|a| (strsim::jaro_winkler(field, a), a))
+                        .filter(|(confidence, _)| *confidence > 0.8)
+                        .max_by(|a, b| a.0.total_cmp(&b.0))
+                        .map(|(_, a)| format!(" (did you mean '{}'?)", a))
+                        .unwrap_or_default();
+                    format!(
+                        "results can't be ordered by '{}'{}, but only by {}",
+                        field,
+                        suggestion,
+                        allowed.join(", ")
+                    )
+                }
+                [_] => continue,
+                [_, direction]
+                    if direction.eq_ignore_ascii_case("asc")
+                        || direction.eq_ignore_ascii_case("desc") =>
+                {
+                    continue
+                }
+                _ => format!(
+                    "'{}' is not a field optionally followed by asc or desc",
+                    order.trim()
+                ),
+            };
+            errors.push(ValidationError {
+                field: path.to_string(),
+                message,
+            });
+        }
+    });
+}
+
+/// Check that at least one of `scopes` is one of the scopes a method accepts, as its access token
+/// only needs one of them. Nothing is checked if there are no `scopes`, as the default scope of the
+/// method is used then.
+pub fn check_scopes<I, S>(
+    path: &str,
+    scopes: I,
+    allowed: &[&str],
+    errors: &mut Vec<ValidationError>,
+) where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let scopes: Vec<S> = scopes.into_iter().collect();
+    if scopes.is_empty() || allowed.is_empty() {
+        return;
+    }
+    if scopes.iter().any(|s| allowed.contains(&s.as_ref())) {
+        return;
+    }
+    let given: Vec<String> = scopes.iter().map(|s| format!("'{}'", s.as_ref())).collect();
+    errors.push(ValidationError {
+        field: path.to_string(),
+        message: format!(
+            "the method can't be authorized with {}, but needs one of {}",
+            given.join(", "),
+            allowed.join(", ")
+        ),
+    });
+}
+
+/// The compiled form of `pattern`, anchored to match values as a whole, or `None` if it isn't a
+/// regular expression the `regex` crate supports. Patterns are compiled once, and kept for
+/// further calls.
+fn compiled(pattern: &str) -> Option<Regex> {
+    static PATTERNS: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+    let mut patterns = PATTERNS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    patterns
+        .entry(pattern.to_string())
+        .or_insert_with(|| Regex::new(&format!("^(?:{})$", pattern)).ok())
+        .clone()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn matches(pattern: &str, value: &str) -> bool {
+        compiled(pattern)
+            .expect("supported pattern")
+            .is_match(value)
+    }
+
+    #[test]
+    fn patterns() {
+        let name = "[a-z](?:[-a-z0-9]{0,61}[a-z0-9])?";
+        assert!(matches(name, "my-instance-1"));
+        assert!(matches(name, "a"));
+        assert!(!matches(name, "my-instance-"));
+        assert!(!matches(name, "1-instance"));
+        assert!(!matches(name, &"a".repeat(64)));
+
+        let parent = "^projects/[^/]+/locations/[^/]+$";
+        assert!(matches(parent, "projects/p/locations/us-central1"));
+        assert!(!matches(parent, "projects/p/locations/"));
+        assert!(!matches(parent, "projects/p/locations/a/b"));
+
+        let ip = r"[0-9]{1,3}(?:\.[0-9]{1,3}){3}/[0-9]{1,2}";
+        assert!(matches(ip, "10.0.0.0/8"));
+        assert!(!matches(ip, "10.0.0/8"));
+
+        assert!(matches("[a-zA-Z0-9-_]{1,128}", "A-b_9"));
+        assert!(matches(r"[a-z]+|[1-9]\d{0,19}", "1234"));
+        assert!(matches("[0-9]{0,20}?", ""));
+        assert!(matches(".?", ""));
+        assert!(!matches(".?", "ab"));
+
+        assert!(!matches("(a|a)*b", &"a".repeat(64)));
+        assert!(matches("(a|a)*b", "aab"));
+
+        assert!(compiled("(?=a)b").is_none());
+        assert!(compiled("a(b").is_none());
+        assert!(compiled("*a").is_none());
+    }
+
+    #[derive(Default)]
+    struct Instance {
+        name: Option<String>,
+        tier: Option<String>,
+        tags: Option<Vec<String>>,
+        children: Option<Vec<Instance>>,
+    }
+
+    impl Validate for Instance {
+        fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>) {
+            check_pattern(&field_path(path, "name"), &self.name, "[a-z]+", errors);
+            check_enum(
+                &field_path(path, "tier"),
+                &self.tier,
+                &["BASIC", "PREMIUM"],
+                errors,
+            );
+            check_enum(&field_path(path, "tags"), &self.tags, &["a", "b"], errors);
+            self.children
+                .validate_at(&field_path(path, "children"), errors);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let instance = Instance {
+            name: Some("ok".into()),
+            tier: Some("BASIC".into()),
+            tags: Some(vec!["a".into(), "c".into()]),
+            children: Some(vec![Instance {
+                name: Some("Not-OK".into()),
+                ..Default::default()
+            }]),
+        };
+        let errors = instance.validate().unwrap_err();
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "tags[1]: 'c' is not one of a, b",
+                "children[0].name: 'Not-OK' doesn't match the pattern '[a-z]+'",
+            ]
+        );
+        assert!(Instance::default().validate().is_ok());
+
+        let mut errors = Vec::new();
+        check_range("pageSize", &Some(0u32), Some(1), Some(500), &mut errors);
+        check_range("pageSize", &Some(100u32), Some(1), Some(500), &mut errors);
+        check_range("port", &vec![80, 70000], None, Some(65535), &mut errors);
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "pageSize: 0 is less than the minimum of 1",
+                "port[1]: 70000 is greater than the maximum of 65535",
+            ]
+        );
+    }
+
+    #[test]
+    fn order_by() {
+        let allowed = &["request_count", "latest_request_time"];
+        let mut errors = Vec::new();
+        check_order_by(
+            "orderBy",
+            &"request_count".to_string(),
+            allowed,
+            &mut errors,
+        );
+        check_order_by(
+            "orderBy",
+            &Some("latest_request_time desc, Request_Count ASC".to_string()),
+            allowed,
+            &mut errors,
+        );
+        check_order_by("orderBy", &"".to_string(), allowed, &mut errors);
+        assert!(errors.is_empty());
+
+        check_order_by("orderBy", &"request_cont".to_string(), allowed, &mut errors);
+        check_order_by("orderBy", &"name".to_string(), allowed, &mut errors);
+        check_order_by(
+            "orderBy",
+            &"request_count down".to_string(),
+            allowed,
+            &mut errors,
+        );
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "orderBy: results can't be ordered by 'request_cont' (did you mean \
+                 'request_count'?), but only by request_count, latest_request_time",
+                "orderBy: results can't be ordered by 'name', but only by request_count, \
+                 latest_request_time",
+                "orderBy: 'request_count down' is not a field optionally followed by asc or desc",
+            ]
+        );
+    }
+
+    #[test]
+    fn scopes() {
+        let allowed = &["https://x/auth/a", "https://x/auth/a.readonly"];
+        let mut errors = Vec::new();
+        check_scopes("scope", ["https://x/auth/a.readonly"], allowed, &mut errors);
+        check_scopes(
+            "scope",
+            ["https://x/auth/b", "https://x/auth/a"],
+            allowed,
+            &mut errors,
+        );
+        check_scopes("scope", Vec::<String>::new(), allowed, &mut errors);
+        assert!(errors.is_empty());
+
+        check_scopes("scope", ["https://x/auth/b"], allowed, &mut errors);
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "scope: the method can't be authorized with 'https://x/auth/b', but needs one of \
+                 https://x/auth/a, https://x/auth/a.readonly"
+            ]
+        );
+    }
+}
@@ -0,0 +1,364 @@
+//! Defaults for the global flags, read from a TOML file
+//!
+//! The file given with `--config-file`, or `<program>-config.toml` in the configuration directory
+//! if it exists, holds one key per global flag, named like its long form. Flags given on the
+//! command line always win over those of the file.
+//!
+//! ```toml
+//! # use the same account and scope each time
+//! profile = "work"
+//! scope = ["https://www.googleapis.com/auth/cloud-platform"]
+//! all-pages = true
+//! concurrency = 8
+//! ```
+//!
+//! Only top-level keys are used, with strings, numbers, booleans and arrays of them as values.
+//! Arrays set flags which may be given multiple times, and booleans set flags which take no value.
+//!
+//! The file is found and merged before the command line is parsed, so the global flags given on
+//! it are picked out by [`GivenFlags::scan`] beforehand.
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+
+use serde::Deserialize;
+
+use crate::{CLIError, ConfigurationError};
+
+/// A global flag, as (long form, argument name, takes a value, short form).
+pub type ConfigFlag = (&'static str, &'static str, bool, Option<char>);
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(flatten)]
+    flags: BTreeMap<String, toml::Value>,
+}
+
+/// The configuration file which is read if none is given explicitly.
+pub fn default_config_file_path(config_dir: &str, program_name: &str) -> String {
+    format!("{}/{}-config.toml", config_dir, program_name)
+}
+
+/// The global flags given on the command line, along with their values, and the command following
+/// them.
+#[derive(Debug, Default, PartialEq)]
+pub struct GivenFlags {
+    flags: Vec<(&'static str, Option<String>)>,
+    command: Vec<String>,
+}
+
+impl GivenFlags {
+    /// Pick out the `flags` given in `args` before the subcommand, which is where global flags go.
+    /// Anything which isn't one of `flags` is left for the parser of the command line to report.
+    pub fn scan(args: &[OsString], flags: &[ConfigFlag]) -> GivenFlags {
+        let mut given = Vec::new();
+        let mut command = Vec::new();
+        let mut args = args.iter().skip(1).map(|a| a.to_string_lossy());
+        while let Some(arg) = args.next() {
+            if arg == "--" || !arg.starts_with('-') || arg == "-" {
+                if arg != "--" {
+                    command.push(arg.into_owned());
+                }
+                command.extend(args.map(|a| a.into_owned()));
+                break;
+            }
+            if let Some(long) = arg.strip_prefix("--") {
+                let (long, value) = match long.split_once('=') {
+                    Some((long, value)) => (long, Some(value.to_string())),
+                    None => (long, None),
+                };
+                if let Some(&(long, _, takes_value, _)) = flags.iter().find(|f| f.0 == long) {
+                    let value = match (takes_value, value) {
+                        (true, None) => args.next().map(|v| v.into_owned()),
+                        (_, value) => value,
+                    };
+                    given.push((long, value));
+                }
+            } else {
+                for short in arg.chars().skip(1) {
+                    if let Some(flag) = flags.iter().find(|f| f.3 == Some(short)) {
+                        given.push((flag.0, None));
+                    }
+                }
+            }
+        }
+        GivenFlags {
+            flags: given,
+            command,
+        }
+    }
+
+    /// The arguments following the global flags, starting with the subcommand.
+    pub fn command(&self) -> &[String] {
+        &self.command
+    }
+
+    /// Returns true if the flag with the long form `long` was given.
+    pub fn contains(&self, long: &str) -> bool {
+        self.flags.iter().any(|(l, _)| *l == long)
+    }
+
+    /// The last value given to the flag with the long form `long`, if any.
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.flags
+            .iter()
+            .rev()
+            .find(|(l, _)| *l == long)
+            .and_then(|(_, v)| v.as_deref())
+    }
+}
+
+/// Returns `args` with the flags set in the configuration file inserted after the program name,
+/// skipping those which were `given` on the command line. The file is `config_file` if set, and
+/// `default_config_file` otherwise, which may not exist. The flag `config_file_flag` itself can't
+/// be set in the file.
+pub fn args_with_config_file(
+    args: Vec<OsString>,
+    given: &GivenFlags,
+    config_file: Option<&str>,
+    default_config_file: Option<String>,
+    config_file_flag: &str,
+    flags: &[ConfigFlag],
+) -> Result<Vec<OsString>, CLIError> {
+    let (path, text) = match (config_file, default_config_file) {
+        (Some(path), _) => (path.to_string(), fs::read_to_string(path)),
+        (None, Some(path)) => match fs::read_to_string(&path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(args),
+            text => (path, text),
+        },
+        (None, None) => return Ok(args),
+    };
+    let config_err =
+        |msg: String| CLIError::Configuration(ConfigurationError::ConfigFile((path.clone(), msg)));
+    let text =
+        text.map_err(|err| CLIError::Configuration(ConfigurationError::Io((path.clone(), err))))?;
+    let config: ConfigFile = toml::from_str(&text).map_err(|err| config_err(err.to_string()))?;
+
+    let mut defaults = Vec::new();
+    for (key, value) in config.flags {
+        let &(long, _, takes_value, _) = flags
+            .iter()
+            .find(|f| f.0 == key && f.0 != config_file_flag)
+            .ok_or_else(|| config_err(format!("'{}' is not a known flag", key)))?;
+        if given.contains(long) {
+            continue;
+        }
+        match (value, takes_value) {
+            (toml::Value::Boolean(set), false) => {
+                if set {
+                    defaults.push(OsString::from(format!("--{}", long)));
+                }
+            }
+            (toml::Value::Array(values), true) => {
+                for value in values {
+                    let value = scalar(value).ok_or_else(|| {
+                        config_err(format!("'{}' must hold strings or numbers", key))
+                    })?;
+                    defaults.push(OsString::from(format!("--{}", long)));
+                    defaults.push(OsString::from(value));
+                }
+            }
+            (value, true) => {
+                let value =
+                    scalar(value).ok_or_else(|| config_err(format!("'{}' needs a value", key)))?;
+                defaults.push(OsString::from(format!("--{}", long)));
+                defaults.push(OsString::from(value));
+            }
+            (_, false) => return Err(config_err(format!("'{}' must be true or false", key))),
+        }
+    }
+    let mut args = args.into_iter();
+    Ok(args
+        .next()
+        .into_iter()
+        .chain(defaults)
+        .chain(args)
+        .collect())
+}
+
+/// The value of a flag set to `value`, which is a string, a number or a boolean.
+fn scalar(value: toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value),
+        toml::Value::Integer(value) => Some(value.to_string()),
+        toml::Value::Float(value) => Some(value.to_string()),
+        toml::Value::Boolean(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use clap::{App, Arg, SubCommand};
+
+    const FLAGS: &[ConfigFlag] = &[
+        ("scope", "url", true, None),
+        ("profile", "name", true, None),
+        ("all-pages", "all-pages", false, None),
+        ("verbose", "verbose", false, Some('v')),
+        ("concurrency", "limit", true, None),
+        ("config-file", "path", true, None),
+    ];
+
+    fn os_args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    fn config_file(name: &str, text: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "google-clis-common-config-{}-{}.toml",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, text).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn scan() {
+        let given = GivenFlags::scan(
+            &os_args(&[
+                "program",
+                "--profile",
+                "home",
+                "-v",
+                "--config-file=a.toml",
+                "cmd",
+                "--all-pages",
+            ]),
+            FLAGS,
+        );
+        assert!(given.contains("profile") && given.contains("verbose"));
+        assert!(!given.contains("all-pages"));
+        assert_eq!(given.value("profile"), Some("home"));
+        assert_eq!(given.value("config-file"), Some("a.toml"));
+        assert_eq!(given.command(), ["cmd", "--all-pages"]);
+    }
+
+    #[test]
+    fn toml_values() {
+        let path = config_file(
+            "values",
+            "# comment\nprofile = 'work \"a\"' # trailing\nscope = [\"a\", \"b\",]\n\
+             concurrency = 1_000\n\"all-pages\" = true\n",
+        );
+        let args = args_with_config_file(
+            os_args(&["program"]),
+            &GivenFlags::default(),
+            Some(&path),
+            None,
+            "config-file",
+            FLAGS,
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            os_args(&[
+                "program",
+                "--all-pages",
+                "--concurrency",
+                "1000",
+                "--profile",
+                "work \"a\"",
+                "--scope",
+                "a",
+                "--scope",
+                "b"
+            ])
+        );
+
+        for text in [
+            "[table]\nprofile = \"a\"",
+            "profile = { name = \"a\" }",
+            "profile = work",
+            "all-pages = \"yes\"",
+            "config-file = \"other.toml\"",
+            "unknown = 1",
+        ] {
+            fs::write(&path, text).unwrap();
+            let args = os_args(&["program"]);
+            let given = GivenFlags::default();
+            assert!(
+                args_with_config_file(args, &given, Some(&path), None, "config-file", FLAGS)
+                    .is_err(),
+                "{}",
+                text
+            );
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn command_line_wins() {
+        let path = config_file(
+            "wins",
+            "profile = \"work\"\nall-pages = true\nscope = [\"a\", \"b\"]\nverbose = true\n",
+        );
+        let args = os_args(&["program", "--profile", "home", "-v", "cmd"]);
+        let given = GivenFlags::scan(&args, FLAGS);
+        let args =
+            args_with_config_file(args, &given, Some(&path), None, "config-file", FLAGS).unwrap();
+        assert_eq!(
+            args,
+            os_args(&[
+                "program",
+                "--all-pages",
+                "--scope",
+                "a",
+                "--scope",
+                "b",
+                "--profile",
+                "home",
+                "-v",
+                "cmd"
+            ])
+        );
+        let matches = App::new("program")
+            .arg(
+                Arg::with_name("url")
+                    .long("scope")
+                    .takes_value(true)
+                    .multiple(true),
+            )
+            .arg(Arg::with_name("name").long("profile").takes_value(true))
+            .arg(Arg::with_name("all-pages").long("all-pages"))
+            .arg(
+                Arg::with_name("verbose")
+                    .long("verbose")
+                    .short("v")
+                    .multiple(true),
+            )
+            .subcommand(SubCommand::with_name("cmd"))
+            .get_matches_from(args);
+        assert_eq!(matches.value_of("name"), Some("home"));
+        assert_eq!(matches.occurrences_of("verbose"), 1);
+
+        let missing = Some(format!("{}.missing", path));
+        let given = GivenFlags::default();
+        assert_eq!(
+            args_with_config_file(
+                os_args(&["program"]),
+                &given,
+                None,
+                missing.clone(),
+                "config-file",
+                FLAGS
+            )
+            .unwrap(),
+            os_args(&["program"])
+        );
+        assert!(args_with_config_file(
+            vec![],
+            &given,
+            missing.as_deref(),
+            None,
+            "config-file",
+            FLAGS
+        )
+        .is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
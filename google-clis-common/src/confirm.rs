@@ -0,0 +1,58 @@
+//! Asking before destructive calls, like deletes, unless `--assume-yes` is given
+//!
+//! The question is only asked if both standard input and standard error are terminals, so
+//! scripts and pipelines are never stopped by it.
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Returns true if the call described by `question` should be made. Without `assume_yes`, and
+/// only when running in a terminal, the user is asked first.
+pub fn confirm(question: &str, assume_yes: bool) -> bool {
+    if assume_yes || !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return true;
+    }
+    ask(question, &mut io::stdin().lock(), &mut io::stderr()).unwrap_or(false)
+}
+
+/// Ask `question` until it is answered. Only 'y' and 'yes' agree, and an empty answer or the end
+/// of input decline.
+fn ask(question: &str, input: &mut dyn BufRead, output: &mut dyn Write) -> io::Result<bool> {
+    loop {
+        write!(output, "{} [y/N] ", question)?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            return Ok(false);
+        }
+        match line.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(true),
+            "" | "n" | "no" => return Ok(false),
+            answer => writeln!(output, "Please answer yes or no, not '{}'.", answer)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn answer(input: &str) -> (bool, String) {
+        let mut output = Vec::new();
+        let agreed = ask("Delete it?", &mut input.as_bytes(), &mut output).unwrap();
+        (agreed, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn answers() {
+        assert_eq!(answer("y\n"), (true, "Delete it? [y/N] ".to_string()));
+        assert!(answer("YES\n").0);
+        assert!(!answer("\n").0);
+        assert!(!answer("no\n").0);
+        assert!(!answer("").0);
+
+        let (agreed, output) = answer("maybe\nyes\n");
+        assert!(agreed);
+        assert!(output.contains("not 'maybe'"));
+        assert!(confirm("Delete it?", true));
+    }
+}
@@ -0,0 +1,182 @@
+//! Choosing the address family to connect with, with `--address-family`
+//!
+//! By default, all addresses a name resolves to are tried, and if the first one doesn't connect
+//! quickly, the addresses of the other family are tried alongside it ("Happy Eyeballs"). In
+//! networks where IPv6 is configured but broken, or the other way around, this can still cost
+//! seconds per connection. The [`Resolver`] thus allows to only use the addresses of one family,
+//! or to try those of one family first.
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+
+/// The addresses to connect to, as chosen by `--address-family`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressFamily {
+    /// All addresses, in the order they were resolved.
+    #[default]
+    Any,
+    /// Only IPv4 addresses.
+    Ipv4,
+    /// Only IPv6 addresses.
+    Ipv6,
+    /// All addresses, IPv4 addresses first.
+    PreferIpv4,
+    /// All addresses, IPv6 addresses first.
+    PreferIpv6,
+}
+
+impl AddressFamily {
+    /// The addresses of `addrs` which may be connected to, in the order to try them in.
+    pub fn select<I: IntoIterator<Item = SocketAddr>>(self, addrs: I) -> Vec<SocketAddr> {
+        let mut addrs: Vec<SocketAddr> = addrs.into_iter().collect();
+        match self {
+            AddressFamily::Any => {}
+            AddressFamily::Ipv4 => addrs.retain(SocketAddr::is_ipv4),
+            AddressFamily::Ipv6 => addrs.retain(SocketAddr::is_ipv6),
+            AddressFamily::PreferIpv4 => addrs.sort_by_key(SocketAddr::is_ipv6),
+            AddressFamily::PreferIpv6 => addrs.sort_by_key(SocketAddr::is_ipv4),
+        }
+        addrs
+    }
+}
+
+impl FromStr for AddressFamily {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(AddressFamily::Any),
+            "ipv4" => Ok(AddressFamily::Ipv4),
+            "ipv6" => Ok(AddressFamily::Ipv6),
+            "prefer-ipv4" => Ok(AddressFamily::PreferIpv4),
+            "prefer-ipv6" => Ok(AddressFamily::PreferIpv6),
+            _ => Err("expected 'any', 'ipv4', 'ipv6', 'prefer-ipv4' or 'prefer-ipv6'".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for AddressFamily {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            AddressFamily::Any => "any",
+            AddressFamily::Ipv4 => "ipv4",
+            AddressFamily::Ipv6 => "ipv6",
+            AddressFamily::PreferIpv4 => "prefer-ipv4",
+            AddressFamily::PreferIpv6 => "prefer-ipv6",
+        })
+    }
+}
+
+/// Wraps a resolver, like hyper's `GaiResolver`, to only pass on the addresses of the chosen
+/// family. Resolving fails if none of them is left.
+#[derive(Debug, Clone)]
+pub struct Resolver<S> {
+    inner: S,
+    family: AddressFamily,
+}
+
+impl<S> Resolver<S> {
+    pub fn new(inner: S, family: AddressFamily) -> Resolver<S> {
+        Resolver { inner, family }
+    }
+}
+
+impl<S, R> tower_service::Service<R> for Resolver<S>
+where
+    S: tower_service::Service<R>,
+    S::Response: IntoIterator<Item = SocketAddr>,
+    S::Future: Send + 'static,
+    S::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = Box<dyn StdError + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, name: R) -> Self::Future {
+        let future = self.inner.call(name);
+        let family = self.family;
+        Box::pin(async move {
+            let addrs = family.select(future.await.map_err(Into::into)?);
+            if addrs.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("the host has no address of the '{}' address family", family),
+                )
+                .into());
+            }
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use tower_service::Service;
+
+    struct Static;
+
+    impl Service<&'static str> for Static {
+        type Response = Vec<SocketAddr>;
+        type Error = io::Error;
+        type Future = std::future::Ready<Result<Vec<SocketAddr>, io::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, addrs: &'static str) -> Self::Future {
+            std::future::ready(Ok(addrs
+                .split(' ')
+                .map(|addr| addr.parse().unwrap())
+                .collect()))
+        }
+    }
+
+    #[tokio::test]
+    async fn selects_addresses() {
+        let addrs = "[::1]:443 127.0.0.1:443 [::2]:443 127.0.0.2:443";
+        let resolve = |family: &str| {
+            let family = family.parse().unwrap();
+            async move {
+                Resolver::new(Static, family)
+                    .call(addrs)
+                    .await
+                    .map(|addrs| addrs.map(|a| a.to_string()).collect::<Vec<_>>().join(" "))
+                    .map_err(|err| err.to_string())
+            }
+        };
+
+        assert_eq!(resolve("any").await.unwrap(), addrs);
+        assert_eq!(
+            resolve("ipv4").await.unwrap(),
+            "127.0.0.1:443 127.0.0.2:443"
+        );
+        assert_eq!(resolve("ipv6").await.unwrap(), "[::1]:443 [::2]:443");
+        assert_eq!(
+            resolve("prefer-ipv4").await.unwrap(),
+            "127.0.0.1:443 127.0.0.2:443 [::1]:443 [::2]:443"
+        );
+        assert_eq!(
+            resolve("prefer-ipv6").await.unwrap(),
+            "[::1]:443 [::2]:443 127.0.0.1:443 127.0.0.2:443"
+        );
+
+        let err = Resolver::new(Static, AddressFamily::Ipv4)
+            .call("[::1]:443")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("'ipv4'"));
+        assert!("ipv5".parse::<AddressFamily>().is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Fan out get-style calls over many names
+//!
+//! Instead of a single name, `@<file>` reads one name per line from the given file, or from
+//! standard input if it is `@-`. The call is then made once per name, with up to `--concurrency`
+//! calls in flight, and each result is written as a line of JSON in the order of the names.
+//!
+//! With `--on-error continue`, the default, a failed call is written as a line holding the name
+//! and the error, and the remaining calls are made regardless. With `--on-error stop`, no further
+//! calls are made after the first failure.
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::io::{self, BufRead, BufReader};
+use std::str::FromStr;
+
+use futures::stream::{self, StreamExt};
+use serde_json::{json, Value};
+
+use crate::{CLIError, InputError, InvalidOptionsError};
+
+const FAN_OUT_PREFIX: char = '@';
+
+/// What to do once one of many calls failed, as chosen by `--on-error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnError {
+    /// Record the failure and make the remaining calls.
+    #[default]
+    Continue,
+    /// Make no further calls.
+    Stop,
+}
+
+impl FromStr for OnError {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "continue" => Ok(OnError::Continue),
+            "stop" => Ok(OnError::Stop),
+            _ => Err("expected 'continue' or 'stop'".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for OnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            OnError::Continue => "continue",
+            OnError::Stop => "stop",
+        })
+    }
+}
+
+/// Returns the names to fan out the call to if `arg` is of the form `@<file>`, or `None` if it is
+/// a plain name. Empty lines are skipped.
+///
+/// As the dry-run must not consume standard input, it only validates the argument and returns
+/// a single empty name to check the remaining arguments with.
+pub fn names_from_arg(
+    arg: &str,
+    dry_run: bool,
+    err: &mut InvalidOptionsError,
+) -> Option<Vec<String>> {
+    let path = arg.strip_prefix(FAN_OUT_PREFIX)?;
+    let names = if dry_run {
+        match path {
+            "-" => Ok(()),
+            _ => fs::metadata(path).map(|_| ()),
+        }
+        .map(|_| vec![String::new()])
+    } else {
+        match path {
+            "-" => read_names(io::stdin().lock()),
+            _ => fs::File::open(path).and_then(|f| read_names(BufReader::new(f))),
+        }
+    };
+    match names {
+        Ok(names) => Some(names),
+        Err(io_err) => {
+            err.issues
+                .push(CLIError::Input(InputError::Io((path.to_string(), io_err))));
+            Some(Vec::new())
+        }
+    }
+}
+
+fn read_names<R: BufRead>(reader: R) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let name = line.trim();
+        if !name.is_empty() {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// The line written in place of the result of the call for `name` which failed with `err`.
+pub fn error_line(name: &str, err: &dyn fmt::Display) -> Value {
+    json!({ "name": name, "error": err.to_string() })
+}
+
+/// Drive all `calls` with at most `limit` of them in flight, and pass each output to `handle`
+/// in the order of the calls. Once `handle` returns false, the calls still in flight are dropped
+/// and no further calls are made.
+pub async fn for_each_buffered<F, O>(calls: Vec<F>, limit: usize, mut handle: impl FnMut(O) -> bool)
+where
+    F: Future<Output = O>,
+{
+    let mut outputs = stream::iter(calls).buffered(limit.max(1));
+    while let Some(output) = outputs.next().await {
+        if !handle(output) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    #[test]
+    fn names() {
+        let mut err = InvalidOptionsError::new();
+        assert_eq!(names_from_arg("plain", false, &mut err), None);
+        assert_eq!(
+            names_from_arg("@-", true, &mut err),
+            Some(vec![String::new()])
+        );
+        assert_eq!(
+            names_from_arg("@/does/not/exist", true, &mut err),
+            Some(vec![])
+        );
+        assert_eq!(err.issues.len(), 1);
+
+        let names = read_names("a\n\n  b  \nc".as_bytes()).unwrap();
+        assert_eq!(names, vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn outputs_keep_order() {
+        let calls = (0..5u64)
+            .map(|i| async move {
+                tokio::time::sleep(Duration::from_millis(10 * (5 - i))).await;
+                i
+            })
+            .collect();
+        let mut outputs = Vec::new();
+        for_each_buffered(calls, 3, |i| {
+            outputs.push(i);
+            true
+        })
+        .await;
+        assert_eq!(outputs, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn stop_after_failure() {
+        let calls = (0..5u64).map(|i| async move { i }).collect();
+        let mut outputs = Vec::new();
+        for_each_buffered(calls, 2, |i| {
+            outputs.push(i);
+            i != 1
+        })
+        .await;
+        assert_eq!(outputs, vec![0, 1]);
+
+        assert_eq!("stop".parse(), Ok(OnError::Stop));
+        assert!("abort".parse::<OnError>().is_err());
+        assert_eq!(
+            error_line("a", &"not found"),
+            json!({"name": "a", "error": "not found"})
+        );
+    }
+}
@@ -0,0 +1,462 @@
+//! Filtering the items of list results, with `--where <expression>`
+//!
+//! The expression is evaluated for each item of a page, like the `devices` of a list of devices,
+//! and only the items it holds for are kept. Other fields of the page are left alone. Fields of
+//! the item are referred to by dotted paths, with array elements picked by their index, like
+//! `cpuInfo[0].maxClockSpeed`. They are compared with `==`, `!=`, `<`, `<=`, `>` and `>=` to other
+//! fields or to strings, numbers, `true`, `false` and `null`, and comparisons are combined with
+//! `&&`, `||`, `!` and parentheses. A path on its own holds if the field is set and neither `null`
+//! nor `false`.
+//!
+//! As 64 bit integers are strings in the JSON of Google APIs, strings are compared to numbers by
+//! their numeric value, which is exact if both are integers. Missing fields are `null`, and
+//! comparing the order of values of different types never holds.
+//!
+//! Unlike the `filter` parameter of some methods, this works with every list method and any
+//! field, but all items are still transferred.
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Path(Vec<Segment>),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, Op, Operand),
+    Test(Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(serde_json::Number),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Dot,
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+}
+
+/// A parsed `--where` expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterExpr(Expr);
+
+impl FilterExpr {
+    /// Parse `expression`, or describe where it is malformed.
+    pub fn parse(expression: &str) -> Result<FilterExpr, String> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(FilterExpr(expr)),
+            Some((at, _)) => Err(format!("unexpected input at position {}", at)),
+        }
+    }
+
+    /// Returns true if the expression holds for `item`.
+    pub fn matches(&self, item: &Value) -> bool {
+        eval(&self.0, item)
+    }
+
+    /// Keep only the items of the `items_field` of `page` the expression holds for, or those of
+    /// all arrays of `page` if its items field isn't known.
+    pub fn retain_items(&self, page: &mut Value, items_field: Option<&str>) {
+        for items in page_items(page, items_field) {
+            items.retain(|item| self.matches(item));
+        }
+    }
+}
+
+/// The `items_field` of `page`, or all of its arrays if the items field isn't known.
+pub(crate) fn page_items<'a>(
+    page: &'a mut Value,
+    items_field: Option<&str>,
+) -> Vec<&'a mut Vec<Value>> {
+    match (page, items_field) {
+        (Value::Object(object), Some(field)) => object
+            .get_mut(field)
+            .and_then(Value::as_array_mut)
+            .into_iter()
+            .collect(),
+        (Value::Object(object), None) => object
+            .values_mut()
+            .filter_map(Value::as_array_mut)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, String> {
+    let chars: Vec<(usize, char)> = input.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (at, c) = chars[i];
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('=', Some('=')) => (Token::Op(Op::Eq), 2),
+            ('!', Some('=')) => (Token::Op(Op::Ne), 2),
+            ('<', Some('=')) => (Token::Op(Op::Le), 2),
+            ('>', Some('=')) => (Token::Op(Op::Ge), 2),
+            ('<', _) => (Token::Op(Op::Lt), 1),
+            ('>', _) => (Token::Op(Op::Gt), 1),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('!', _) => (Token::Not, 1),
+            ('.', _) => (Token::Dot, 1),
+            ('(', _) => (Token::Open, 1),
+            (')', _) => (Token::Close, 1),
+            ('[', _) => (Token::OpenBracket, 1),
+            (']', _) => (Token::CloseBracket, 1),
+            ('"', _) | ('\'', _) => {
+                let mut value = String::new();
+                let mut end = i + 1;
+                loop {
+                    match chars.get(end).map(|&(_, c)| c) {
+                        None => return Err(format!("the string at position {} isn't closed", at)),
+                        Some(q) if q == c => break,
+                        Some('\\') if end + 1 < chars.len() => {
+                            value.push(chars[end + 1].1);
+                            end += 2;
+                        }
+                        Some(other) => {
+                            value.push(other);
+                            end += 1;
+                        }
+                    }
+                }
+                (Token::Str(value), end + 1 - i)
+            }
+            (c, _) if c.is_ascii_digit() || c == '-' => {
+                // a sign is part of the number at its start, and right after its exponent
+                let is_sign = |n: usize, c: char| {
+                    (n == 0 && c == '-')
+                        || (n > 0
+                            && (c == '-' || c == '+')
+                            && matches!(chars[i + n - 1].1, 'e' | 'E'))
+                };
+                let len = chars[i..]
+                    .iter()
+                    .enumerate()
+                    .take_while(|&(n, &(_, c))| {
+                        c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E') || is_sign(n, c)
+                    })
+                    .count();
+                let text: String = chars[i..i + len].iter().map(|&(_, c)| c).collect();
+                // integers are kept as they are, rather than rounded to the nearest f64
+                let number = text
+                    .parse::<serde_json::Number>()
+                    .ok()
+                    .or_else(|| text.parse().ok().and_then(serde_json::Number::from_f64));
+                match number {
+                    Some(number) => (Token::Num(number), len),
+                    None => return Err(format!("'{}' at position {} is no number", text, at)),
+                }
+            }
+            (c, _) if c.is_alphabetic() || c == '_' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|&&(_, c)| c.is_alphanumeric() || c == '_')
+                    .count();
+                let ident: String = chars[i..i + len].iter().map(|&(_, c)| c).collect();
+                (Token::Ident(ident), len)
+            }
+            (c, _) => return Err(format!("unexpected '{}' at position {}", c, at)),
+        };
+        tokens.push((at, token));
+        i += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn error(&self, expected: &str) -> String {
+        match self.tokens.get(self.pos) {
+            Some((at, _)) => format!("expected {} at position {}", expected, at),
+            None => format!("expected {} at the end", expected),
+        }
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.eat(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.eat(&Token::Open) {
+            let expr = self.or()?;
+            if !self.eat(&Token::Close) {
+                return Err(self.error("')'"));
+            }
+            return Ok(expr);
+        }
+        let left = self.operand()?;
+        match self.peek() {
+            Some(&Token::Op(op)) => {
+                self.pos += 1;
+                Ok(Expr::Compare(left, op, self.operand()?))
+            }
+            _ => Ok(Expr::Test(left)),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        let token = match self.tokens.get(self.pos) {
+            Some((_, token)) => token.clone(),
+            None => return Err(self.error("a field or a value")),
+        };
+        self.pos += 1;
+        let first = match token {
+            Token::Str(s) => return Ok(Operand::Literal(Value::String(s))),
+            Token::Num(n) => return Ok(Operand::Literal(Value::Number(n))),
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => return Ok(Operand::Literal(Value::Bool(true))),
+                "false" => return Ok(Operand::Literal(Value::Bool(false))),
+                "null" => return Ok(Operand::Literal(Value::Null)),
+                _ => ident,
+            },
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("a field or a value"));
+            }
+        };
+        let mut path = vec![Segment::Field(first)];
+        loop {
+            if self.eat(&Token::Dot) {
+                match self.tokens.get(self.pos) {
+                    Some((_, Token::Ident(ident))) => {
+                        path.push(Segment::Field(ident.clone()));
+                        self.pos += 1;
+                    }
+                    _ => return Err(self.error("a field name")),
+                }
+            } else if self.eat(&Token::OpenBracket) {
+                match self.tokens.get(self.pos) {
+                    Some((_, Token::Num(n))) if n.as_u64().is_some() => {
+                        path.push(Segment::Index(n.as_u64().unwrap_or_default() as usize));
+                        self.pos += 1;
+                    }
+                    _ => return Err(self.error("an index")),
+                }
+                if !self.eat(&Token::CloseBracket) {
+                    return Err(self.error("']'"));
+                }
+            } else {
+                return Ok(Operand::Path(path));
+            }
+        }
+    }
+}
+
+fn resolve<'a>(operand: &'a Operand, item: &'a Value) -> Option<&'a Value> {
+    match operand {
+        Operand::Literal(value) => Some(value),
+        Operand::Path(path) => path.iter().try_fold(item, |value, segment| match segment {
+            Segment::Field(name) => value.get(name),
+            Segment::Index(index) => value.get(index),
+        }),
+    }
+}
+
+/// The value of integers, and of strings holding an integer, like 64 bit integers.
+fn as_integer(value: &Value) -> Option<i128> {
+    match value {
+        Value::Number(n) => n
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| n.as_u64().map(i128::from)),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// The value of numbers, and of strings holding a number.
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Number(_), _) | (_, Value::Number(_)) => match (as_integer(a), as_integer(b)) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => as_number(a)?.partial_cmp(&as_number(b)?),
+        },
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn eval(expr: &Expr, item: &Value) -> bool {
+    match expr {
+        Expr::Or(a, b) => eval(a, item) || eval(b, item),
+        Expr::And(a, b) => eval(a, item) && eval(b, item),
+        Expr::Not(expr) => !eval(expr, item),
+        Expr::Test(operand) => !matches!(
+            resolve(operand, item),
+            None | Some(Value::Null) | Some(Value::Bool(false))
+        ),
+        Expr::Compare(a, op, b) => {
+            let (a, b) = (
+                resolve(a, item).unwrap_or(&Value::Null),
+                resolve(b, item).unwrap_or(&Value::Null),
+            );
+            let equal = a == b || compare(a, b) == Some(Ordering::Equal);
+            match op {
+                Op::Eq => equal,
+                Op::Ne => !equal,
+                Op::Lt => compare(a, b) == Some(Ordering::Less),
+                Op::Le => matches!(compare(a, b), Some(Ordering::Less | Ordering::Equal)),
+                Op::Gt => compare(a, b) == Some(Ordering::Greater),
+                Op::Ge => matches!(compare(a, b), Some(Ordering::Greater | Ordering::Equal)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    fn holds(expression: &str, item: Value) -> bool {
+        FilterExpr::parse(expression).unwrap().matches(&item)
+    }
+
+    #[test]
+    fn evaluates() {
+        let device = json!({
+            "deviceId": "a",
+            "cpuInfo": [{"maxClockSpeed": 2400000}],
+            "totalRamBytes": "8589934592",
+            "osUpdateStatus": {"state": "UPDATED"},
+            "managed": true,
+        });
+        assert!(holds("cpuInfo[0].maxClockSpeed > 2000000", device.clone()));
+        assert!(!holds("cpuInfo[1].maxClockSpeed > 2000000", device.clone()));
+        assert!(holds("totalRamBytes >= 8e9 && managed", device.clone()));
+        assert!(holds("osUpdateStatus.state == 'UPDATED'", device.clone()));
+        assert!(holds("osUpdateStatus.state != \"FAILED\"", device.clone()));
+        assert!(holds(
+            "!(deviceId < 'a') && missing == null",
+            device.clone()
+        ));
+        assert!(holds("deviceId == 'b' || !missing", device.clone()));
+        assert!(!holds("deviceId > 1", device.clone()));
+        assert!(holds("cpuInfo[0].maxClockSpeed <= -1 || true", device));
+
+        let item = json!({"id": "9007199254740993", "size": 9007199254740993u64});
+        assert!(holds("id > 9007199254740992", item.clone()));
+        assert!(!holds("id == 9007199254740992", item.clone()));
+        assert!(holds(
+            "size == id && size != '9007199254740992'",
+            item.clone()
+        ));
+        assert!(holds("size > 9.0e15", item));
+    }
+
+    #[test]
+    fn reports_malformed_expressions() {
+        for (expression, error) in [
+            ("a >", "expected a field or a value at the end"),
+            ("a > > 1", "expected a field or a value at position 4"),
+            ("(a", "expected ')' at the end"),
+            ("a[x]", "expected an index at position 2"),
+            ("a == 'b", "the string at position 5 isn't closed"),
+            ("a = 1", "unexpected '=' at position 2"),
+            ("a b", "unexpected input at position 2"),
+        ] {
+            assert_eq!(FilterExpr::parse(expression), Err(error.to_string()));
+        }
+    }
+
+    #[test]
+    fn retains_items() {
+        let mut page = json!({
+            "devices": [{"id": 1}, {"id": 2}, {"id": 3}],
+            "unreachable": ["a"],
+            "totalSize": 3,
+        });
+        let filter = FilterExpr::parse("id && id != 2").unwrap();
+        filter.retain_items(&mut page, Some("devices"));
+        assert_eq!(
+            page,
+            json!({"devices": [{"id": 1}, {"id": 3}], "unreachable": ["a"], "totalSize": 3})
+        );
+        filter.retain_items(&mut page, None);
+        assert_eq!(
+            page,
+            json!({"devices": [{"id": 1}, {"id": 3}], "unreachable": [], "totalSize": 3})
+        );
+    }
+}
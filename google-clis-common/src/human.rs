@@ -0,0 +1,101 @@
+//! A readable summary of a single resource, as written with `--human`
+//!
+//! Instead of the whole JSON document, each field at the top level of the resource is written on
+//! a line of its own, labelled with its name. Fields which usually identify a resource, like
+//! `name` or `displayName`, come first. Nested objects and lists are summarised by their size.
+//!
+//! ```text
+//! Name:          customers/my_customer/telemetry/devices/abc
+//! Display name:  Lobby kiosk
+//! Cpu info:      2 items
+//! ```
+use std::io::{self, Write};
+
+use serde_json::Value;
+
+const KEY_FIELDS: &[&str] = &["name", "displayName", "title", "id", "state", "status"];
+
+/// Write the summary of `value`, which is expected to have its null values removed already.
+pub fn write_summary(value: &Value, out: &mut dyn Write) -> io::Result<()> {
+    let object = match value {
+        Value::Object(object) => object,
+        other => {
+            writeln!(out, "{}", summarize(other))?;
+            return out.flush();
+        }
+    };
+
+    let mut fields: Vec<(String, String)> = KEY_FIELDS
+        .iter()
+        .filter_map(|&key| object.get(key).map(|v| (label(key), summarize(v))))
+        .collect();
+    fields.extend(
+        object
+            .iter()
+            .filter(|(key, value)| !KEY_FIELDS.contains(&key.as_str()) && !value.is_null())
+            .map(|(key, value)| (label(key), summarize(value))),
+    );
+
+    let width = fields.iter().map(|(l, _)| l.len()).max().unwrap_or(0) + 1;
+    for (label, value) in fields {
+        writeln!(out, "{:width$}  {}", format!("{}:", label), value)?;
+    }
+    out.flush()
+}
+
+/// `displayName` becomes `Display name`.
+fn label(key: &str) -> String {
+    let mut label = String::with_capacity(key.len() + 2);
+    for (i, c) in key.chars().enumerate() {
+        if i == 0 {
+            label.extend(c.to_uppercase());
+        } else if c.is_uppercase() {
+            label.push(' ');
+            label.extend(c.to_lowercase());
+        } else if c == '_' {
+            label.push(' ');
+        } else {
+            label.push(c);
+        }
+    }
+    label
+}
+
+fn summarize(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) if items.len() == 1 => "1 item".to_string(),
+        Value::Array(items) => format!("{} items", items.len()),
+        Value::Object(fields) if fields.len() == 1 => "1 field".to_string(),
+        Value::Object(fields) => format!("{} fields", fields.len()),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn key_fields_first() {
+        let value = json!({
+            "cpuInfo": [{"model": "a"}, {"model": "b"}],
+            "displayName": "Lobby kiosk",
+            "memoryInfo": {"totalRamBytes": "4096"},
+            "name": "devices/abc",
+            "serial_number": 42,
+        });
+        let mut out = Vec::new();
+        write_summary(&value, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Name:           devices/abc\n\
+             Display name:   Lobby kiosk\n\
+             Cpu info:       2 items\n\
+             Memory info:    1 field\n\
+             Serial number:  42\n"
+        );
+    }
+}
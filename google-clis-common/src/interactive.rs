@@ -0,0 +1,238 @@
+//! Asking for the fields of a request one after the other, with `--interactive`
+//!
+//! Each field of the request which isn't set with `-r` already is asked for on standard error,
+//! along with its type and the values it allows if it is an enumeration. An empty answer skips the
+//! field, while lists and maps take one entry per answer until an empty one. The answers are
+//! handled exactly like `-r` arguments setting the field, so they are checked in the same way.
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    parse_kv_arg, well_known, CLIError, ComplexType, FieldCursor, InputError, InvalidOptionsError,
+    JsonType,
+};
+
+/// A field of the request which can be asked for.
+pub struct Field {
+    /// The path of the field, as given with `-r`.
+    pub path: &'static str,
+    pub jtype: JsonType,
+    pub ctype: ComplexType,
+    /// The values the field allows if it is an enumeration, or none if it allows any.
+    pub choices: &'static [&'static str],
+}
+
+/// Ask for each of `fields` not set by `kvargs` yet, and return `-r` arguments setting the fields
+/// to the answers. Failing to read an answer is an issue of `err`.
+pub fn ask_fields(
+    fields: &[Field],
+    kvargs: &[String],
+    err: &mut InvalidOptionsError,
+) -> Vec<String> {
+    let given = given_fields(kvargs);
+    match ask(fields, &given, &mut io::stdin().lock(), &mut io::stderr()) {
+        Ok(answers) => answers,
+        Err(io_err) => {
+            err.issues
+                .push(CLIError::Input(InputError::Io(("-".to_string(), io_err))));
+            Vec::new()
+        }
+    }
+}
+
+/// The paths of the fields set by `kvargs`, with the cursor moving like when they are applied.
+fn given_fields(kvargs: &[String]) -> Vec<String> {
+    let mut err = InvalidOptionsError::new();
+    let mut cursor = FieldCursor::default();
+    let mut given = Vec::new();
+    for kvarg in kvargs {
+        let (key, value) = parse_kv_arg(kvarg, &mut err, false);
+        let mut temp_cursor = cursor.clone();
+        if temp_cursor.set(key).is_err() {
+            continue;
+        }
+        match value {
+            Some(_) => given.push(temp_cursor.to_string()),
+            None => cursor = temp_cursor,
+        }
+    }
+    given
+}
+
+/// Ask for the fields not in `given` until the end of `input`.
+fn ask(
+    fields: &[Field],
+    given: &[String],
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> io::Result<Vec<String>> {
+    let mut kvargs = Vec::new();
+    writeln!(
+        output,
+        "Enter the value of each field, or nothing to skip it."
+    )?;
+    for field in fields.iter().filter(|f| !given.iter().any(|g| g == f.path)) {
+        loop {
+            write!(output, "{} ({}): ", field.path, hint(field))?;
+            output.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                return Ok(kvargs);
+            }
+            let answer = line.trim();
+            if answer.is_empty() {
+                break;
+            }
+            match check(field, answer) {
+                Ok(()) => kvargs.push(format!(".{}={}", field.path, answer)),
+                Err(msg) => {
+                    writeln!(output, "{}", msg)?;
+                    continue;
+                }
+            }
+            if let ComplexType::Pod = field.ctype {
+                break;
+            }
+        }
+    }
+    Ok(kvargs)
+}
+
+/// The type of `field` and how to answer, like `list of integer, an entry per answer`.
+fn hint(field: &Field) -> String {
+    let jtype = type_name(&field.jtype);
+    let mut hint = match field.ctype {
+        ComplexType::Pod => jtype.to_string(),
+        ComplexType::Vec => format!("list of {}, an entry per answer", jtype),
+        ComplexType::Map => format!("map of {}, a key=value per answer", jtype),
+    };
+    if !field.choices.is_empty() {
+        hint.push_str(&format!(", one of {}", field.choices.join(", ")));
+    }
+    hint
+}
+
+fn type_name(jtype: &JsonType) -> &'static str {
+    match jtype {
+        JsonType::Boolean => "boolean",
+        JsonType::Int => "integer",
+        JsonType::Uint => "unsigned integer",
+        JsonType::Float => "number",
+        JsonType::String => "string",
+        JsonType::Date => "date, like 2024-01-15",
+        JsonType::Duration => "duration, like 1h30m",
+    }
+}
+
+/// Returns a message asking for another answer if `answer` isn't a valid value of `field`.
+fn check(field: &Field, answer: &str) -> Result<(), String> {
+    let value = match field.ctype {
+        ComplexType::Map => match answer.split_once('=') {
+            Some((key, value)) if !key.is_empty() => value,
+            _ => return Err(format!("Please answer with key=value, not '{}'.", answer)),
+        },
+        _ => answer,
+    };
+    let valid = match field.jtype {
+        JsonType::Boolean => value.parse::<bool>().is_ok(),
+        JsonType::Int => value.parse::<i64>().is_ok(),
+        JsonType::Uint => value.parse::<u64>().is_ok(),
+        JsonType::Float => value.parse::<f64>().is_ok(),
+        JsonType::String => true,
+        JsonType::Date => well_known::parse_date(value).is_ok(),
+        JsonType::Duration => well_known::parse_duration(value).is_ok(),
+    };
+    if !valid {
+        return Err(format!(
+            "'{}' is not a valid {}.",
+            value,
+            type_name(&field.jtype)
+        ));
+    }
+    if !field.choices.is_empty() && !field.choices.contains(&value) {
+        return Err(format!(
+            "Please answer one of {}, not '{}'.",
+            field.choices.join(", "),
+            value
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIELDS: &[Field] = &[
+        Field {
+            path: "display-name",
+            jtype: JsonType::String,
+            ctype: ComplexType::Pod,
+            choices: &[],
+        },
+        Field {
+            path: "filter.calendar-period",
+            jtype: JsonType::String,
+            ctype: ComplexType::Pod,
+            choices: &["MONTH", "QUARTER"],
+        },
+        Field {
+            path: "filter.projects",
+            jtype: JsonType::String,
+            ctype: ComplexType::Vec,
+            choices: &[],
+        },
+        Field {
+            path: "filter.labels",
+            jtype: JsonType::Int,
+            ctype: ComplexType::Map,
+            choices: &[],
+        },
+    ];
+
+    fn answer(given: &[&str], input: &str) -> (Vec<String>, String) {
+        let given: Vec<String> = given.iter().map(|s| s.to_string()).collect();
+        let mut output = Vec::new();
+        let kvargs = ask(
+            FIELDS,
+            &given_fields(&given),
+            &mut input.as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        (kvargs, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn answers() {
+        let (kvargs, output) = answer(&[], "budget\nYEAR\nMONTH\na\nb\n\nx=1\nx\n\n");
+        assert_eq!(
+            kvargs,
+            vec![
+                ".display-name=budget",
+                ".filter.calendar-period=MONTH",
+                ".filter.projects=a",
+                ".filter.projects=b",
+                ".filter.labels=x=1",
+            ]
+        );
+        assert!(output.contains("filter.calendar-period (string, one of MONTH, QUARTER): "));
+        assert!(output.contains("not 'YEAR'"));
+        assert!(output.contains("filter.labels (map of integer, a key=value per answer): "));
+        assert!(output.contains("Please answer with key=value, not 'x'."));
+
+        let (kvargs, output) = answer(&["filter", "calendar-period=MONTH"], "\n\n\n");
+        assert!(kvargs.is_empty());
+        assert!(!output.contains("calendar-period"));
+        assert!(output.contains("filter.projects"));
+    }
+
+    #[test]
+    fn checks_types() {
+        assert!(check(&FIELDS[3], "x=1").is_ok());
+        assert_eq!(
+            check(&FIELDS[3], "x=one"),
+            Err("'one' is not a valid integer.".to_string())
+        );
+    }
+}
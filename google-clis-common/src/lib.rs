@@ -1,20 +1,47 @@
+pub mod config_file;
+pub mod confirm;
+pub mod dns;
+pub mod fan_out;
+pub mod filter_expr;
+pub mod human;
+pub mod interactive;
+pub mod output_format;
+pub mod paging;
+pub mod profile;
+pub mod projection;
+pub mod record;
+pub mod replay;
+pub mod report;
+pub mod serve;
+pub mod timing;
+pub mod token_cache;
+pub mod verbose;
+pub mod well_known;
+
 use mime::Mime;
 use yup_oauth2::{ApplicationSecret, ConsoleApplicationSecret};
 use serde_json as json;
 use serde_json::value::Value;
 use clap::arg_enum;
+use google_apis_common::{ReadSeek, Validate, ValidationError};
 
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::env;
 use std::fmt;
 use std::fs;
 use std::io;
-use std::io::{stdout, Write};
+use std::io::{stdout, Seek, Write};
 use std::path::Path;
 use std::str::FromStr;
 use std::string::ToString;
 
 use std::default::Default;
 
+pub use paging::Pager;
+pub use token_cache::{CachedAuth, TokenCache};
+pub use verbose::VerboseDelegate;
+
 const FIELD_SEP: char = '.';
 
 pub enum ComplexType {
@@ -36,6 +63,10 @@ pub enum JsonType {
     Uint,
     Float,
     String,
+    /// A `google.type.Date`, given like `2024-01-15`.
+    Date,
+    /// A duration string, given like `1h30m` or `5400s`.
+    Duration,
 }
 
 pub struct JsonTypeInfo {
@@ -77,6 +108,15 @@ pub fn remove_json_null_values(value: &mut Value) {
     }
 }
 
+/// The JSON representation of `value`, like a response, without its `null` values, to write it as
+/// output. Values which JSON can't represent, like maps with keys which aren't strings, are an
+/// error of kind `io::ErrorKind::InvalidData` instead of a panic.
+pub fn to_output_value<T: serde::Serialize>(value: &T) -> io::Result<Value> {
+    let mut value = json::to_value(value)?;
+    remove_json_null_values(&mut value);
+    Ok(value)
+}
+
 fn did_you_mean<'a>(v: &str, possible_values: &[&'a str]) -> Option<&'a str> {
     let mut candidate: Option<(f64, &str)> = None;
     for pv in possible_values {
@@ -125,9 +165,9 @@ impl AsRef<str> for CallType {
 #[derive(Clone, Default)]
 pub struct FieldCursor(Vec<String>);
 
-impl ToString for FieldCursor {
-    fn to_string(&self) -> String {
-        self.0.join(".")
+impl fmt::Display for FieldCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0.join("."))
     }
 }
 
@@ -291,6 +331,11 @@ impl FieldCursor {
                                     .expect("valid f64"),
                             ),
                             JsonType::String => Value::String(value.to_owned()),
+                            JsonType::Date => well_known::parse_date(value)
+                                .unwrap_or_else(|e| parse_error(value, err, field, "date", e)),
+                            JsonType::Duration => well_known::parse_duration(value)
+                                .map(Value::String)
+                                .unwrap_or_else(|e| parse_error(value, err, field, "duration", e)),
                         }
                     };
 
@@ -362,6 +407,141 @@ pub fn parse_kv_arg<'a>(
     }
 }
 
+/// Expand references to environment variables in the value of the key-value pair `kv`. `${VAR}` is
+/// replaced with the value of `VAR`, which must be set, while `${VAR:-default}` falls back to
+/// `default` if `VAR` is unset or empty. `$$` stands for a single `$`, and any other `$` is kept
+/// as is. Issues are pushed onto `err`, in which case the value is returned unchanged.
+pub fn expand_env_vars<'a>(
+    value: &'a str,
+    kv: &str,
+    err: &mut InvalidOptionsError,
+) -> Cow<'a, str> {
+    match expand_vars(value, |name| env::var(name).ok()) {
+        Ok(expanded) => expanded,
+        Err(cause) => {
+            err.issues
+                .push(CLIError::Environment(kv.to_string(), cause));
+            Cow::Borrowed(value)
+        }
+    }
+}
+
+fn expand_vars(
+    value: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Cow<'_, str>, String> {
+    if !value.contains('$') {
+        return Ok(Cow::Borrowed(value));
+    }
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let end = after
+                .find('}')
+                .ok_or_else(|| "a '${' lacks its closing '}'".to_string())?;
+            let (name, default) = match after[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&after[..end], None),
+            };
+            if name.is_empty() {
+                return Err("a '${}' lacks the name of the variable".to_string());
+            }
+            match (lookup(name), default) {
+                (Some(v), Some(default)) if v.is_empty() => expanded.push_str(default),
+                (Some(v), _) => expanded.push_str(&v),
+                (None, Some(default)) => expanded.push_str(default),
+                (None, None) => {
+                    return Err(format!(
+                        "the environment variable '{}' is not set, use '${{{}:-<default>}}' to fall back to a default",
+                        name, name
+                    ))
+                }
+            }
+            rest = &after[end + 1..];
+        } else {
+            expanded.push('$');
+        }
+    }
+    expanded.push_str(rest);
+    Ok(Cow::Owned(expanded))
+}
+
+/// Notices parameters which take a single value but are given more than once with `-p`, in
+/// which case all but the last value are ignored. This is a warning on standard error, or an
+/// error if `strict` is set.
+///
+/// As each command is validated by a dry-run before it is made, warnings are only written during
+/// the dry-run, to write them once.
+#[derive(Debug, Default)]
+pub struct DuplicateParams {
+    seen: HashSet<String>,
+    strict: bool,
+    warn: bool,
+}
+
+impl DuplicateParams {
+    pub fn new(strict: bool, dry_run: bool) -> DuplicateParams {
+        DuplicateParams {
+            seen: HashSet::new(),
+            strict,
+            warn: dry_run && !strict,
+        }
+    }
+
+    /// Record that the single-valued parameter `key` was given.
+    pub fn check(&mut self, key: &str, err: &mut InvalidOptionsError) {
+        if self.seen.insert(key.to_string()) {
+            return;
+        }
+        if self.strict {
+            err.issues
+                .push(CLIError::Field(FieldError::Duplicate(key.to_string())));
+        } else if self.warn {
+            writeln!(
+                io::stderr(),
+                "Warning: the parameter '{}' was given more than once, only its last value is used.",
+                key
+            )
+            .ok();
+        }
+    }
+}
+
+/// Push each constraint documented by the API which `call` violates onto `err`, as done with
+/// `--validate-only`.
+pub fn check_constraints<V: Validate + ?Sized>(call: &V, err: &mut InvalidOptionsError) {
+    if let Err(errors) = call.validate() {
+        err.issues
+            .extend(errors.into_iter().map(CLIError::Constraint));
+    }
+}
+
+/// Push an error onto `err` if the method accepts none of the `--scope`s, as it only needs one of
+/// `allowed`, and warn about each scope it doesn't accept otherwise.
+pub fn check_scopes(scopes: &[&str], allowed: &[&str], err: &mut InvalidOptionsError) {
+    let mut errors = Vec::new();
+    google_apis_common::validate::check_scopes("--scope", scopes, allowed, &mut errors);
+    if !errors.is_empty() {
+        err.issues
+            .extend(errors.into_iter().map(CLIError::Constraint));
+        return;
+    }
+    for scope in scopes.iter().filter(|s| !allowed.contains(s)) {
+        writeln!(
+            io::stderr(),
+            "Warning: the method doesn't accept the scope '{}', which is only requested for the access token.",
+            scope
+        )
+        .ok();
+    }
+}
+
 pub fn calltype_from_str(
     name: &str,
     valid_protocols: Vec<String>,
@@ -379,9 +559,22 @@ pub fn calltype_from_str(
     })
 }
 
-pub fn input_file_from_opts(file_path: &str, err: &mut InvalidOptionsError) -> Option<fs::File> {
-    match fs::File::open(file_path) {
-        Ok(f) => Some(f),
+/// Open the file to upload, or standard input if `file_path` is `-`. As uploads need to seek, standard
+/// input is spooled to a temporary file first, which is removed once it is closed.
+///
+/// As the dry-run must not consume standard input, it yields empty input in place of it.
+pub fn input_file_from_opts(
+    file_path: &str,
+    dry_run: bool,
+    err: &mut InvalidOptionsError,
+) -> Option<Box<dyn ReadSeek>> {
+    let input: Result<Box<dyn ReadSeek>, io::Error> = match file_path {
+        "-" if dry_run => Ok(Box::new(io::Cursor::new(Vec::new()))),
+        "-" => spool_stdin().map(|f| Box::new(f) as Box<dyn ReadSeek>),
+        _ => fs::File::open(file_path).map(|f| Box::new(f) as Box<dyn ReadSeek>),
+    };
+    match input {
+        Ok(input) => Some(input),
         Err(io_err) => {
             err.issues.push(CLIError::Input(InputError::Io((
                 file_path.to_string(),
@@ -392,7 +585,29 @@ pub fn input_file_from_opts(file_path: &str, err: &mut InvalidOptionsError) -> O
     }
 }
 
-pub fn input_mime_from_opts(mime: &str, err: &mut InvalidOptionsError) -> Option<Mime> {
+fn spool_stdin() -> io::Result<fs::File> {
+    spool(&mut io::stdin().lock())
+}
+
+/// Copy `input` to an anonymous temporary file, positioned at its start.
+fn spool(input: &mut dyn io::Read) -> io::Result<fs::File> {
+    let mut file = tempfile::tempfile()?;
+    io::copy(input, &mut file)?;
+    file.seek(io::SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+/// The mime type of the file to upload, which is the explicitly given one if any. Otherwise it is
+/// guessed from the extension of `file_path`, and falls back to `application/octet-stream`.
+pub fn input_mime_from_opts(
+    mime: Option<&str>,
+    file_path: &str,
+    err: &mut InvalidOptionsError,
+) -> Option<Mime> {
+    let mime = match mime {
+        Some(mime) => mime,
+        None => return Some(mime_from_extension(file_path)),
+    };
     match mime.parse() {
         Ok(m) => Some(m),
         Err(_) => {
@@ -403,6 +618,26 @@ pub fn input_mime_from_opts(mime: &str, err: &mut InvalidOptionsError) -> Option
     }
 }
 
+fn mime_from_extension(file_path: &str) -> Mime {
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("json") => mime::APPLICATION_JSON,
+        Some("txt") => mime::TEXT_PLAIN,
+        Some("csv") => mime::TEXT_CSV,
+        Some("html" | "htm") => mime::TEXT_HTML,
+        Some("xml") => mime::TEXT_XML,
+        Some("pdf") => mime::APPLICATION_PDF,
+        Some("png") => mime::IMAGE_PNG,
+        Some("jpg" | "jpeg") => mime::IMAGE_JPEG,
+        Some("gif") => mime::IMAGE_GIF,
+        Some("svg") => mime::IMAGE_SVG,
+        _ => mime::APPLICATION_OCTET_STREAM,
+    }
+}
+
 pub fn writer_from_opts(arg: Option<&str>) -> Result<Box<dyn Write>, io::Error> {
     let f = arg.unwrap_or("-");
     match f {
@@ -443,6 +678,22 @@ where
     }
 }
 
+fn parse_error(
+    value: &str,
+    err: &mut InvalidOptionsError,
+    arg_name: &str,
+    arg_type: &str,
+    cause: String,
+) -> Value {
+    err.issues.push(CLIError::ParseError(
+        arg_name.to_owned(),
+        arg_type.to_owned(),
+        value.to_string(),
+        cause,
+    ));
+    Value::Null
+}
+
 #[derive(Debug)]
 pub enum ApplicationSecretError {
     DecoderError((String, json::Error)),
@@ -473,6 +724,7 @@ pub enum ConfigurationError {
     HomeExpansionFailed(String),
     Secret(ApplicationSecretError),
     Io((String, io::Error)),
+    ConfigFile((String, String)),
 }
 
 impl fmt::Display for ConfigurationError {
@@ -495,6 +747,9 @@ impl fmt::Display for ConfigurationError {
                 "IO operation failed on path '{}' with error: {}.",
                 path, err
             ),
+            ConfigurationError::ConfigFile((ref path, ref err)) => {
+                writeln!(f, "The configuration file '{}' is invalid: {}.", path, err)
+            }
         }
     }
 }
@@ -570,6 +825,12 @@ pub enum CLIError {
     Field(FieldError),
     MissingCommandError,
     MissingMethodError(String),
+    OptionRequires(String, String),
+    OptionConflicts(String, String),
+    InvalidProfile(String),
+    RequestValue(String),
+    Environment(String, String),
+    Constraint(ValidationError),
 }
 
 impl fmt::Display for CLIError {
@@ -610,6 +871,34 @@ impl fmt::Display for CLIError {
                 "Please specify the method to call on the '{}' command.",
                 cmd
             ),
+            CLIError::RequestValue(ref err) => {
+                writeln!(f, "The request value could not be assembled: {}.", err)
+            }
+            CLIError::OptionRequires(ref flag, ref required) => writeln!(
+                f,
+                "Option '--{}' requires '--{}' to be set as well.",
+                flag, required
+            ),
+            CLIError::OptionConflicts(ref flag, ref other) => writeln!(
+                f,
+                "Option '--{}' can't be combined with '--{}'.",
+                flag, other
+            ),
+            CLIError::InvalidProfile(ref name) => writeln!(
+                f,
+                "'{}' is not a valid profile name, which may only contain letters, digits, '_' and '-'.",
+                name
+            ),
+            CLIError::Environment(ref kv, ref cause) => {
+                writeln!(f, "'{}' could not be expanded: {}.", kv, cause)
+            }
+            CLIError::Constraint(ref err) => {
+                writeln!(
+                    f,
+                    "The value of '{}' is invalid: {}.",
+                    err.field, err.message
+                )
+            }
         }
     }
 }
@@ -716,12 +1005,10 @@ pub fn application_secret_from_directory(
                         Err(cfe) => cfe,
                         Ok(mut f) => {
                             // Assure we convert 'ugly' json string into pretty one
-                            let console_secret: ConsoleApplicationSecret =
-                                json::from_str(json_console_secret).unwrap();
-                            match json::to_writer_pretty(&mut f, &console_secret) {
-                                Err(serde_err) => {
-                                    panic!("Unexpected serde error: {:#?}", serde_err)
-                                }
+                            match json::from_str::<ConsoleApplicationSecret>(json_console_secret)
+                                .and_then(|secret| json::to_writer_pretty(&mut f, &secret))
+                            {
+                                Err(serde_err) => serde_err.into(),
                                 Ok(_) => continue,
                             }
                         }
@@ -756,6 +1043,133 @@ mod test_cli {
     use super::*;
 
     use std::default::Default;
+    use std::io::Seek;
+
+    #[test]
+    fn output_value() {
+        let value = to_output_value(&json::json!({"a": null, "b": [1, null]})).unwrap();
+        assert_eq!(value, json::json!({"b": [1]}));
+
+        let mut map = std::collections::HashMap::new();
+        map.insert(vec![1], "not a string key");
+        let err = to_output_value(&map).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn upload_mime() {
+        let mut err = InvalidOptionsError::new();
+        assert_eq!(
+            input_mime_from_opts(None, "data/report.JSON", &mut err),
+            Some(mime::APPLICATION_JSON)
+        );
+        assert_eq!(
+            input_mime_from_opts(None, "-", &mut err),
+            Some(mime::APPLICATION_OCTET_STREAM)
+        );
+        assert_eq!(
+            input_mime_from_opts(Some("text/csv"), "report.json", &mut err),
+            Some(mime::TEXT_CSV)
+        );
+        assert!(err.issues.is_empty());
+        assert_eq!(input_mime_from_opts(Some("csv"), "-", &mut err), None);
+        assert_eq!(err.issues.len(), 1);
+
+        let mut input = input_file_from_opts("-", true, &mut err).unwrap();
+        assert_eq!(input.seek(io::SeekFrom::End(0)).unwrap(), 0);
+
+        let mut spooled = spool(&mut &b"piped data"[..]).unwrap();
+        let mut data = String::new();
+        io::Read::read_to_string(&mut spooled, &mut data).unwrap();
+        assert_eq!(data, "piped data");
+        assert_eq!(spooled.seek(io::SeekFrom::Start(6)).unwrap(), 6);
+    }
+
+    #[test]
+    fn env_vars() {
+        let lookup = |name: &str| match name {
+            "TEAM" => Some("infra".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        assert_eq!(expand_vars("plain", lookup).unwrap(), "plain");
+        assert_eq!(
+            expand_vars("${TEAM}-budget", lookup).unwrap(),
+            "infra-budget"
+        );
+        assert_eq!(
+            expand_vars("${MISSING:-x}/${EMPTY:-y}/${EMPTY}", lookup).unwrap(),
+            "x/y/"
+        );
+        assert_eq!(
+            expand_vars("$$5 or $5 or $${TEAM}", lookup).unwrap(),
+            "$5 or $5 or ${TEAM}"
+        );
+        assert!(expand_vars("${MISSING}", lookup).is_err());
+        assert!(expand_vars("${TEAM", lookup).is_err());
+        assert!(expand_vars("${}", lookup).is_err());
+
+        let mut err = InvalidOptionsError::new();
+        assert_eq!(expand_env_vars("${}", "a=${}", &mut err), "${}");
+        assert_eq!(err.issues.len(), 1);
+    }
+
+    #[test]
+    fn duplicate_params() {
+        let mut err = InvalidOptionsError::new();
+        let mut params = DuplicateParams::new(false, false);
+        params.check("page-size", &mut err);
+        params.check("page-size", &mut err);
+        assert!(err.issues.is_empty());
+
+        let mut params = DuplicateParams::new(true, true);
+        params.check("page-size", &mut err);
+        params.check("filter", &mut err);
+        assert!(err.issues.is_empty());
+        params.check("page-size", &mut err);
+        assert_eq!(err.issues.len(), 1);
+    }
+
+    #[test]
+    fn constraints() {
+        struct Call(&'static str);
+
+        impl Validate for Call {
+            fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>) {
+                google_apis_common::validate::check_enum(
+                    &google_apis_common::validate::field_path(path, "view"),
+                    &self.0.to_string(),
+                    &["BASIC", "FULL"],
+                    errors,
+                );
+            }
+        }
+
+        let mut err = InvalidOptionsError::new();
+        check_constraints(&Call("FULL"), &mut err);
+        assert!(err.issues.is_empty());
+        check_constraints(&Call("ALL"), &mut err);
+        assert_eq!(
+            err.issues[0].to_string(),
+            "The value of 'view' is invalid: 'ALL' is not one of BASIC, FULL.\n"
+        );
+    }
+
+    #[test]
+    fn scopes() {
+        let allowed = &["https://x/auth/a", "https://x/auth/a.readonly"];
+        let mut err = InvalidOptionsError::new();
+        check_scopes(&[], allowed, &mut err);
+        check_scopes(&["https://x/auth/a.readonly"], allowed, &mut err);
+        check_scopes(&["https://x/auth/a", "https://x/auth/b"], allowed, &mut err);
+        assert!(err.issues.is_empty());
+        check_scopes(&["https://x/auth/b"], allowed, &mut err);
+        assert_eq!(
+            err.issues[0].to_string(),
+            "The value of '--scope' is invalid: the method can't be authorized with \
+             'https://x/auth/b', but needs one of https://x/auth/a, https://x/auth/a.readonly.\n"
+        );
+    }
 
     #[test]
     fn cursor() {
@@ -793,4 +1207,29 @@ mod test_cli {
         assert_eq!(c.num_fields(), 3);
         assert_eq!(c.to_string(), "one.beer.one");
     }
+
+    #[test]
+    fn well_known_values() {
+        let mut object = Value::Object(Default::default());
+        let mut err = InvalidOptionsError::new();
+        let set = |path: &'static str, value: &str, jtype, object: &mut Value, err: &mut _| {
+            let cursor = FieldCursor::from(path);
+            let type_info = JsonTypeInfo {
+                jtype,
+                ctype: ComplexType::Pod,
+            };
+            cursor.set_json_value(object, value, type_info, err, &cursor);
+        };
+
+        set("period.startDate", "2024-01-15", JsonType::Date, &mut object, &mut err);
+        set("period.ttl", "1h30m", JsonType::Duration, &mut object, &mut err);
+        assert!(err.issues.is_empty());
+        assert_eq!(
+            object,
+            json::json!({"period": {"startDate": {"year": 2024, "month": 1, "day": 15}, "ttl": "5400s"}})
+        );
+
+        set("endDate", "2024-02-30", JsonType::Date, &mut object, &mut err);
+        assert_eq!(err.issues.len(), 1);
+    }
 }
@@ -0,0 +1,115 @@
+//! Writing results for readers which consume them while they arrive, with `--output-format`
+//!
+//! By default, each result is written as a pretty-printed JSON document. With `ndjson-stream`,
+//! each item is written as a line of compact JSON instead, and the output is flushed right after,
+//! so a reader tailing it sees the item immediately. The items of paged results are the entries of
+//! their arrays, as with `--stream-output`, while any other result is an item of its own.
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde_json as json;
+use serde_json::Value;
+
+/// The format to write results in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Each result as a pretty-printed document.
+    #[default]
+    Json,
+    /// Each item as a line of compact JSON, flushed right after it was written.
+    NdjsonStream,
+}
+
+impl OutputFormat {
+    /// Write `value`, a result which isn't part of a list, and flush `out`.
+    pub fn write(self, value: &Value, out: &mut dyn Write) -> io::Result<()> {
+        match self {
+            OutputFormat::Json => {
+                json::to_writer_pretty(&mut *out, value)?;
+                out.flush()
+            }
+            OutputFormat::NdjsonStream => write_line(value, out),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "ndjson-stream" => Ok(OutputFormat::NdjsonStream),
+            _ => Err("expected 'json' or 'ndjson-stream'".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            OutputFormat::Json => "json",
+            OutputFormat::NdjsonStream => "ndjson-stream",
+        })
+    }
+}
+
+/// Write `value` as a line of compact JSON, and flush `out`.
+pub fn write_line(value: &Value, out: &mut dyn Write) -> io::Result<()> {
+    json::to_writer(&mut *out, value)?;
+    writeln!(out)?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    /// Counts the flushes, to check that every line is flushed.
+    #[derive(Default)]
+    struct Flushes {
+        written: Vec<u8>,
+        lines_at_flush: Vec<usize>,
+    }
+
+    impl Write for Flushes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            let lines = self.written.iter().filter(|&&b| b == b'\n').count();
+            self.lines_at_flush.push(lines);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writes_lines() {
+        let mut out = Flushes::default();
+        let value = json!({"name": "a", "labels": {"b": 1}});
+        OutputFormat::NdjsonStream.write(&value, &mut out).unwrap();
+        write_line(&json!("c"), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out.written).unwrap(),
+            "{\"labels\":{\"b\":1},\"name\":\"a\"}\n\"c\"\n"
+        );
+        assert_eq!(out.lines_at_flush, vec![1, 2]);
+
+        let mut out = Vec::new();
+        OutputFormat::Json
+            .write(&json!({"a": 1}), &mut out)
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "{\n  \"a\": 1\n}");
+
+        assert_eq!(
+            "ndjson-stream".parse::<OutputFormat>(),
+            Ok(OutputFormat::NdjsonStream)
+        );
+        assert!("ndjson".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,1000 @@
+//! Support for `--all-pages`, including resumable exports through `--state-file` and `--resume`
+//!
+//! Without a state file, all pages are fetched and merged into a single document, which is
+//! written once the last page arrived. Pages are merged by the `merge()` method generated for
+//! their type, as given to [`Pager::with_merge`]. With a state file, each page is written as a
+//! single line of JSON as soon as it arrived, and the `nextPageToken` needed to fetch the next
+//! page is persisted right after. An interrupted export can then be continued with `--resume`,
+//! which appends to the previous output instead of starting over.
+//!
+//! As the token is persisted after the page was written, a crash in between causes the last
+//! page to be fetched and written once more when resuming, but never causes a page to be lost.
+//!
+//! With `--stream-output`, the items of each page, like the `devices` of a list of devices, are
+//! written as lines of JSON as soon as the page arrived, so memory use is bounded by the size of a
+//! page rather than by the whole export.
+//!
+//! With `--continue-from`, the page following a response written by an earlier call is fetched,
+//! using the `nextPageToken` of that response. That way, pages are fetched one call at a time
+//! without copying the token from one call to the next.
+//!
+//! With `--max-pages`, no more than the given amount of pages is fetched, and the pages fetched
+//! until then are written as if they were all. With `--state-file`, the state is kept in that case,
+//! so the export can be continued with `--resume`.
+//!
+//! With `--output-split-pages`, each page is written to a file of its own in the given directory,
+//! named after its number like `page-0001.json`. The pages are written as returned by the API,
+//! including their `nextPageToken`, and are neither merged nor written to the output.
+//!
+//! With `--sort-by`, the items of the merged pages are sorted by the value of the given field,
+//! like `deviceId`, to make the output the same across runs no matter the order the API returned
+//! the items in. Items without the field come last, and the sort is stable.
+//!
+//! With `--where`, only the items of each page a [`FilterExpr`] holds for are kept, before the
+//! page is written or merged.
+//!
+//! A page size above the maximum documented for the method is warned about by [`check_page_size`],
+//! as the API would silently return smaller pages than asked for.
+//!
+//! With `--output-format ndjson-stream`, the items of each page are written as with
+//! `--stream-output`, whether or not all pages are fetched.
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use google_apis_common::ValidationError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json as json;
+use serde_json::Value;
+
+use crate::filter_expr::{page_items, FilterExpr};
+use crate::output_format::{self, OutputFormat};
+use crate::projection::Projection;
+use crate::report::ReportWriter;
+use crate::{to_output_value, writer_from_opts, CLIError, InputError, InvalidOptionsError};
+
+const NEXT_PAGE_TOKEN: &str = "nextPageToken";
+
+/// Merges a page into the pages merged before it, usually through [`merge_as`].
+pub type MergeFn = fn(&mut Value, Value) -> io::Result<()>;
+
+/// Drives the page loop of a list method and writes its output.
+#[derive(Debug, Default)]
+pub struct Pager {
+    all_pages: bool,
+    resume: bool,
+    state_file: Option<String>,
+    token: Option<String>,
+    pages: u64,
+    merged: Option<Value>,
+    report: ReportWriter,
+    stream_output: bool,
+    max_pages: Option<u64>,
+    fetched: u64,
+    truncated: bool,
+    projection: Projection,
+    pages_dir: Option<PathBuf>,
+    sort_key: Option<Vec<String>>,
+    filter: Option<FilterExpr>,
+    output_format: OutputFormat,
+    items_field: Option<&'static str>,
+    merge: Option<MergeFn>,
+}
+
+impl Pager {
+    /// Create a new pager from the respective command-line flags. Any issue with the given
+    /// combination of flags or with reading the state file is pushed onto `err`.
+    pub fn new(
+        all_pages: bool,
+        state_file: Option<&str>,
+        resume: bool,
+        err: &mut InvalidOptionsError,
+    ) -> Pager {
+        let mut pager = Pager {
+            all_pages,
+            resume,
+            state_file: state_file.map(str::to_string),
+            ..Default::default()
+        };
+        if resume && state_file.is_none() {
+            err.issues.push(CLIError::OptionRequires(
+                "resume".to_string(),
+                "state-file".to_string(),
+            ));
+        }
+        if state_file.is_some() && !all_pages {
+            err.issues.push(CLIError::OptionRequires(
+                "state-file".to_string(),
+                "all-pages".to_string(),
+            ));
+        }
+        if let (true, Some(path)) = (resume, state_file) {
+            match read_state(path) {
+                Ok((token, pages)) => {
+                    pager.token = Some(token);
+                    pager.pages = pages;
+                }
+                Err(io_err) => err
+                    .issues
+                    .push(CLIError::Input(InputError::Io((path.to_string(), io_err)))),
+            }
+        }
+        pager
+    }
+
+    /// Start with the page following the response an earlier call wrote to `path`, as given by its
+    /// `nextPageToken`. This conflicts with resuming, which starts with the page of the state
+    /// file, and with a page token given as parameter, as told by `page_token_given`. Conflicts are
+    /// pushed onto `err` just like failing to read the token.
+    pub fn with_continue_from(
+        mut self,
+        path: Option<&str>,
+        page_token_given: bool,
+        err: &mut InvalidOptionsError,
+    ) -> Pager {
+        let path = match path {
+            Some(path) => path,
+            None => return self,
+        };
+        if self.resume {
+            err.issues.push(CLIError::OptionConflicts(
+                "continue-from".to_string(),
+                "resume".to_string(),
+            ));
+            return self;
+        }
+        if page_token_given {
+            err.issues.push(CLIError::OptionConflicts(
+                "continue-from".to_string(),
+                "page-token".to_string(),
+            ));
+            return self;
+        }
+        match read_next_page_token(path) {
+            Ok(token) => self.token = Some(token),
+            Err(io_err) => err
+                .issues
+                .push(CLIError::Input(InputError::Io((path.to_string(), io_err)))),
+        }
+        self
+    }
+
+    /// The field holding the items of each page, like `devices`, if the pages have one. Only its
+    /// items are written one by one, while pages without it are written as a whole.
+    pub fn with_items_field(mut self, items_field: Option<&'static str>) -> Pager {
+        self.items_field = items_field;
+        self
+    }
+
+    /// Merge pages with `merge`, like the `merge()` method generated for the type of the pages,
+    /// instead of appending all of their arrays.
+    pub fn with_merge(mut self, merge: MergeFn) -> Pager {
+        self.merge = Some(merge);
+        self
+    }
+
+    /// Write the items of each page as lines of JSON as soon as the page arrived, instead of
+    /// merging all pages. Requires all pages to be fetched, which is pushed onto `err` otherwise.
+    pub fn with_stream_output(
+        mut self,
+        stream_output: bool,
+        err: &mut InvalidOptionsError,
+    ) -> Pager {
+        if stream_output && !self.all_pages {
+            err.issues.push(CLIError::OptionRequires(
+                "stream-output".to_string(),
+                "all-pages".to_string(),
+            ));
+        }
+        self.stream_output = stream_output;
+        self
+    }
+
+    /// Write the items of each page as lines of JSON, flushing the output after each, if `format`
+    /// is `ndjson-stream`. Unlike `--stream-output`, this doesn't require all pages to be fetched.
+    pub fn with_output_format(mut self, format: OutputFormat) -> Pager {
+        self.output_format = format;
+        self
+    }
+
+    /// Fetch at most `max_pages` pages in this run, as given on the command line. Requires all
+    /// pages to be fetched and a positive number of pages, which is pushed onto `err` otherwise.
+    pub fn with_max_pages(
+        mut self,
+        max_pages: Option<&str>,
+        err: &mut InvalidOptionsError,
+    ) -> Pager {
+        let max_pages = match max_pages {
+            None => None,
+            Some(_) if !self.all_pages => {
+                err.issues.push(CLIError::OptionRequires(
+                    "max-pages".to_string(),
+                    "all-pages".to_string(),
+                ));
+                None
+            }
+            Some(value) => match value.parse::<u64>() {
+                Ok(0) => {
+                    err.issues.push(CLIError::ParseError(
+                        "--max-pages".to_string(),
+                        "integer".to_string(),
+                        value.to_string(),
+                        "at least one page is fetched".to_string(),
+                    ));
+                    None
+                }
+                Ok(max_pages) => Some(max_pages),
+                Err(perr) => {
+                    err.issues.push(CLIError::ParseError(
+                        "--max-pages".to_string(),
+                        "integer".to_string(),
+                        value.to_string(),
+                        perr.to_string(),
+                    ));
+                    None
+                }
+            },
+        };
+        self.max_pages = max_pages;
+        self
+    }
+
+    /// Write each page as is to a file of its own in `pages_dir`, instead of writing it to the
+    /// output. Requires all pages to be fetched, which is pushed onto `err` otherwise.
+    pub fn with_pages_dir(
+        mut self,
+        pages_dir: Option<&str>,
+        err: &mut InvalidOptionsError,
+    ) -> Pager {
+        if pages_dir.is_some() && !self.all_pages {
+            err.issues.push(CLIError::OptionRequires(
+                "output-split-pages".to_string(),
+                "all-pages".to_string(),
+            ));
+        }
+        self.pages_dir = pages_dir.map(PathBuf::from);
+        self
+    }
+
+    /// Sort the items of the merged pages by the value of the field at the dotted path `sort_key`.
+    /// Requires all pages to be fetched and merged, which is pushed onto `err` otherwise, so it
+    /// must be set after the other options.
+    pub fn with_sort_key(mut self, sort_key: Option<&str>, err: &mut InvalidOptionsError) -> Pager {
+        let sort_key = match sort_key {
+            Some(key) => key,
+            None => return self,
+        };
+        if !self.all_pages {
+            err.issues.push(CLIError::OptionRequires(
+                "sort-by".to_string(),
+                "all-pages".to_string(),
+            ));
+        }
+        for (set, flag) in [
+            (self.stream_output, "stream-output"),
+            (
+                self.output_format == OutputFormat::NdjsonStream,
+                "output-format",
+            ),
+            (self.state_file.is_some(), "state-file"),
+            (self.pages_dir.is_some(), "output-split-pages"),
+        ] {
+            if set {
+                err.issues.push(CLIError::OptionConflicts(
+                    "sort-by".to_string(),
+                    flag.to_string(),
+                ));
+            }
+        }
+        let fields: Vec<String> = sort_key.split('.').map(str::to_string).collect();
+        if fields.iter().any(String::is_empty) {
+            err.issues.push(CLIError::ParseError(
+                "--sort-by".to_string(),
+                "field path".to_string(),
+                sort_key.to_string(),
+                "it has an empty field name".to_string(),
+            ));
+        }
+        self.sort_key = Some(fields);
+        self
+    }
+
+    /// Keep only the items of each page the expression `filter` holds for. An expression which
+    /// can't be parsed is pushed onto `err`.
+    pub fn with_filter(mut self, filter: Option<&str>, err: &mut InvalidOptionsError) -> Pager {
+        if let Some(expression) = filter {
+            match FilterExpr::parse(expression) {
+                Ok(filter) => self.filter = Some(filter),
+                Err(desc) => err.issues.push(CLIError::ParseError(
+                    "--where".to_string(),
+                    "expression".to_string(),
+                    expression.to_string(),
+                    desc,
+                )),
+            }
+        }
+        self
+    }
+
+    /// Write single pages and merged pages using the given writer. Pages written as soon as they
+    /// arrived are always written as lines of JSON.
+    pub fn with_report(mut self, report: ReportWriter) -> Pager {
+        self.report = report;
+        self
+    }
+
+    /// Prune each page with the given projection before it is written or merged. The token of the
+    /// next page is taken before, so it is never dropped by it.
+    pub fn with_projection(mut self, projection: Projection) -> Pager {
+        self.projection = projection;
+        self
+    }
+
+    /// The token of the page to fetch next, if any.
+    pub fn page_token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// The amount of pages written so far, including those of a previous run when resuming.
+    pub fn pages(&self) -> u64 {
+        self.pages
+    }
+
+    /// Returns true if fetching pages stopped at `--max-pages` even though there were more.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Open the output, which is appended to when resuming and truncated otherwise.
+    pub fn writer(&self, out: Option<&str>) -> Result<Box<dyn Write>, io::Error> {
+        match out {
+            Some(path) if self.resume && path != "-" => Ok(Box::new(
+                fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?,
+            )),
+            _ => writer_from_opts(out),
+        }
+    }
+
+    /// Handle a page as returned by the API, and return true if another page should be fetched.
+    pub fn add_page(&mut self, mut page: Value, out: &mut dyn Write) -> io::Result<bool> {
+        let stream_items = self.stream_output || self.output_format == OutputFormat::NdjsonStream;
+        if !self.all_pages {
+            self.filter_items(&mut page);
+            self.projection.apply(&mut page);
+            if stream_items {
+                write_items(&page, self.items_field, out)?;
+            } else {
+                self.report.write(&page, out)?;
+            }
+            return Ok(false);
+        }
+
+        if let Some(dir) = &self.pages_dir {
+            write_page_file(dir, self.pages + 1, &page)?;
+        }
+        self.token = match page.as_object_mut().and_then(|o| o.remove(NEXT_PAGE_TOKEN)) {
+            Some(Value::String(token)) if !token.is_empty() => Some(token),
+            _ => None,
+        };
+        self.pages += 1;
+        self.fetched += 1;
+        self.truncated = self.token.is_some() && self.max_pages == Some(self.fetched);
+        self.filter_items(&mut page);
+        self.projection.apply(&mut page);
+
+        if self.pages_dir.is_some() {
+            if let (Some(path), Some(token)) = (&self.state_file, &self.token) {
+                write_state(path, token, self.pages)?;
+            }
+        } else if stream_items || self.state_file.is_some() {
+            if stream_items {
+                write_items(&page, self.items_field, out)?;
+            } else {
+                json::to_writer(&mut *out, &page)?;
+                writeln!(out)?;
+            }
+            out.flush()?;
+            if let (Some(path), Some(token)) = (&self.state_file, &self.token) {
+                write_state(path, token, self.pages)?;
+            }
+        } else {
+            match self.merged {
+                Some(ref mut merged) => match self.merge {
+                    Some(merge) => merge(merged, page)?,
+                    None => merge_page(merged, page),
+                },
+                None => self.merged = Some(page),
+            }
+        }
+        Ok(self.token.is_some() && !self.truncated)
+    }
+
+    /// Write the pages merged so far, if any, after fetching a page failed. The state file is kept
+    /// so the export can still be resumed.
+    pub fn finish_partial(mut self, out: &mut dyn Write) -> io::Result<()> {
+        match self.sorted_merged() {
+            Some(merged) => self.report.write(&merged, out),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the merged pages, if any, and remove the state file as the export is complete. It is
+    /// kept if the export was truncated, to allow resuming it.
+    pub fn finish(mut self, out: &mut dyn Write) -> io::Result<()> {
+        if let Some(merged) = self.sorted_merged() {
+            self.report.write(&merged, out)?;
+        }
+        if self.truncated {
+            return Ok(());
+        }
+        if let Some(path) = self.state_file {
+            match fs::remove_file(path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn filter_items(&self, page: &mut Value) {
+        if let Some(filter) = &self.filter {
+            filter.retain_items(page, self.items_field);
+        }
+    }
+
+    /// Take the merged pages, with their items sorted if a sort key was given.
+    fn sorted_merged(&mut self) -> Option<Value> {
+        let mut merged = self.merged.take()?;
+        if let Some(key) = &self.sort_key {
+            for items in page_items(&mut merged, self.items_field) {
+                items.sort_by(|a, b| compare_fields(field(a, key), field(b, key)));
+            }
+        }
+        Some(merged)
+    }
+}
+
+/// Warn if the page size given for the parameter `key` exceeds the `max`imum documented for the
+/// method, as the API would silently return smaller pages than asked for. With `strict`, this is an
+/// error instead. The warning is only printed if `dry_run`, as the options are checked twice.
+pub fn check_page_size(
+    key: &str,
+    size: i64,
+    max: i64,
+    strict: bool,
+    dry_run: bool,
+    err: &mut InvalidOptionsError,
+) {
+    if size <= max {
+        return;
+    }
+    if strict {
+        err.issues.push(CLIError::Constraint(ValidationError {
+            field: key.to_string(),
+            message: format!("must be at most {}, the documented maximum page size", max),
+        }));
+    } else if dry_run {
+        writeln!(
+            io::stderr(),
+            "Warning: the page size {} exceeds the documented maximum of {}, \
+             pages will likely hold fewer items.",
+            size,
+            max
+        )
+        .ok();
+    }
+}
+
+/// The value of the field at `path` below `value`, if there is one.
+fn field<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, name| value.get(name))
+}
+
+/// Order strings and numbers by their value, and anything else by its JSON. Missing fields and
+/// nulls come last.
+fn compare_fields(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    match (a.filter(|v| !v.is_null()), b.filter(|v| !v.is_null())) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+        (Some(Value::Number(a)), Some(Value::Number(b))) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Some(a), Some(b)) => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+/// Append all arrays of `page` to the ones in `into`, while any other value of `page` replaces
+/// the previous one.
+fn merge_page(into: &mut Value, page: Value) {
+    if let (Value::Object(into), Value::Object(page)) = (into, page) {
+        for (key, value) in page {
+            match (into.get_mut(&key), value) {
+                (Some(Value::Array(items)), Value::Array(more)) => items.extend(more),
+                (_, value) => {
+                    into.insert(key, value);
+                }
+            }
+        }
+    }
+}
+
+/// Merge `page` into `merged` with the `merge` function of their type `T`, like the `merge()`
+/// method generated for paged responses.
+pub fn merge_as<T: Serialize + DeserializeOwned>(
+    merged: &mut Value,
+    page: Value,
+    merge: fn(&mut T, T),
+) -> io::Result<()> {
+    let mut pages: T = json::from_value(merged.take())?;
+    merge(&mut pages, json::from_value(page)?);
+    *merged = to_output_value(&pages)?;
+    Ok(())
+}
+
+/// Write each item of the `items_field` of `page` as a line of JSON, or the page itself if it has
+/// no such field, flushing `out` after each line.
+fn write_items(page: &Value, items_field: Option<&str>, out: &mut dyn Write) -> io::Result<()> {
+    match items_field
+        .and_then(|field| page.get(field))
+        .and_then(Value::as_array)
+    {
+        Some(items) => {
+            for item in items {
+                output_format::write_line(item, out)?;
+            }
+            Ok(())
+        }
+        None => output_format::write_line(page, out),
+    }
+}
+
+/// Write `page` to `page-<number>.json` in `dir`, which is created if needed.
+fn write_page_file(dir: &Path, number: u64, page: &Value) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(
+        dir.join(format!("page-{:04}.json", number)),
+        json::to_vec_pretty(page)?,
+    )
+}
+
+fn read_state(path: &str) -> io::Result<(String, u64)> {
+    let state: Value = json::from_slice(&fs::read(path)?)?;
+    match state.get(NEXT_PAGE_TOKEN).and_then(Value::as_str) {
+        Some(token) => Ok((
+            token.to_string(),
+            state.get("pages").and_then(Value::as_u64).unwrap_or(0),
+        )),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the state file has no '{}'", NEXT_PAGE_TOKEN),
+        )),
+    }
+}
+
+/// The token of the page following the response written to `path`.
+fn read_next_page_token(path: &str) -> io::Result<String> {
+    let response: Value = json::from_slice(&fs::read(path)?)?;
+    match response.get(NEXT_PAGE_TOKEN).and_then(Value::as_str) {
+        Some(token) if !token.is_empty() => Ok(token.to_string()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "the response has no '{}', as it is the last page",
+                NEXT_PAGE_TOKEN
+            ),
+        )),
+    }
+}
+
+/// Write the state to a temporary file first and move it into place, so the state file is
+/// never seen half-written.
+fn write_state(path: &str, token: &str, pages: u64) -> io::Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    fs::write(
+        &tmp_path,
+        json::to_vec(&json::json!({ NEXT_PAGE_TOKEN: token, "pages": pages }))?,
+    )?;
+    fs::rename(tmp_path, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    fn state_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "google-clis-common-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn single_page() {
+        let mut err = InvalidOptionsError::new();
+        let mut pager = Pager::new(false, None, false, &mut err);
+        let mut out = Vec::new();
+        let more = pager
+            .add_page(json!({"items": [1], "nextPageToken": "a"}), &mut out)
+            .unwrap();
+        assert!(!more);
+        assert!(err.issues.is_empty());
+        let value: Value = json::from_slice(&out).unwrap();
+        assert_eq!(value, json!({"items": [1], "nextPageToken": "a"}));
+    }
+
+    #[test]
+    fn continue_from() {
+        let path = state_path("continue-from");
+        fs::write(&path, r#"{"items": [1], "nextPageToken": "t2"}"#).unwrap();
+        let mut err = InvalidOptionsError::new();
+        let pager = Pager::new(false, None, false, &mut err).with_continue_from(
+            Some(&path),
+            false,
+            &mut err,
+        );
+        assert!(err.issues.is_empty());
+        assert_eq!(pager.page_token(), Some("t2"));
+
+        fs::write(&path, r#"{"items": [2]}"#).unwrap();
+        let pager = Pager::new(false, None, false, &mut err).with_continue_from(
+            Some(&path),
+            false,
+            &mut err,
+        );
+        assert_eq!(pager.page_token(), None);
+        assert_eq!(err.issues.len(), 1);
+        fs::remove_file(&path).unwrap();
+
+        let mut err = InvalidOptionsError::new();
+        Pager::new(true, Some(&path), true, &mut err).with_continue_from(
+            Some(&path),
+            false,
+            &mut err,
+        );
+        assert!(err.issues.iter().any(|e| matches!(
+            e,
+            CLIError::OptionConflicts(a, b) if a == "continue-from" && b == "resume"
+        )));
+
+        let mut err = InvalidOptionsError::new();
+        let pager = Pager::new(false, None, false, &mut err).with_continue_from(
+            Some(&path),
+            true,
+            &mut err,
+        );
+        assert_eq!(pager.page_token(), None);
+        assert!(matches!(
+            &err.issues[..],
+            [CLIError::OptionConflicts(a, b)] if a == "continue-from" && b == "page-token"
+        ));
+    }
+
+    #[test]
+    fn merged_pages() {
+        let mut err = InvalidOptionsError::new();
+        let mut pager = Pager::new(true, None, false, &mut err);
+        let mut out = Vec::new();
+        assert!(pager
+            .add_page(
+                json!({"items": [1, 2], "kind": "a", "nextPageToken": "t"}),
+                &mut out
+            )
+            .unwrap());
+        assert_eq!(pager.page_token(), Some("t"));
+        assert!(!pager
+            .add_page(json!({"items": [3], "kind": "b"}), &mut out)
+            .unwrap());
+        assert!(out.is_empty());
+        pager.finish(&mut out).unwrap();
+        let value: Value = json::from_slice(&out).unwrap();
+        assert_eq!(value, json!({"items": [1, 2, 3], "kind": "b"}));
+    }
+
+    #[derive(Default, serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Page {
+        items: Option<Vec<u32>>,
+        total_size: Option<u32>,
+        next_page_token: Option<String>,
+    }
+
+    impl Page {
+        fn merge(&mut self, other: Page) {
+            self.next_page_token = other.next_page_token;
+            match (&mut self.items, other.items) {
+                (Some(items), Some(more)) => items.extend(more),
+                (items, more) => {
+                    if more.is_some() {
+                        *items = more
+                    }
+                }
+            }
+            if other.total_size.is_some() {
+                self.total_size = other.total_size;
+            }
+        }
+    }
+
+    #[test]
+    fn typed_merge() {
+        let mut err = InvalidOptionsError::new();
+        let mut pager = Pager::new(true, None, false, &mut err)
+            .with_merge(|merged, page| merge_as(merged, page, Page::merge));
+        let mut out = Vec::new();
+        assert!(pager
+            .add_page(
+                json!({"items": [1, 2], "totalSize": 3, "nextPageToken": "t"}),
+                &mut out
+            )
+            .unwrap());
+        assert!(!pager.add_page(json!({"items": [3]}), &mut out).unwrap());
+        pager.finish(&mut out).unwrap();
+        let value: Value = json::from_slice(&out).unwrap();
+        assert_eq!(value, json!({"items": [1, 2, 3], "totalSize": 3}));
+
+        let mut merged = json!({"items": [1], "nextPageToken": "t1"});
+        merge_as(&mut merged, json!({"nextPageToken": "t2"}), Page::merge).unwrap();
+        assert_eq!(merged, json!({"items": [1], "nextPageToken": "t2"}));
+        assert!(merge_as(&mut merged, json!({"items": "x"}), Page::merge).is_err());
+    }
+
+    #[test]
+    fn partial_pages() {
+        let mut err = InvalidOptionsError::new();
+        let mut pager = Pager::new(true, None, false, &mut err);
+        let mut out = Vec::new();
+        assert!(pager
+            .add_page(json!({"items": [1], "nextPageToken": "t"}), &mut out)
+            .unwrap());
+        pager.finish_partial(&mut out).unwrap();
+        let value: Value = json::from_slice(&out).unwrap();
+        assert_eq!(value, json!({"items": [1]}));
+    }
+
+    #[test]
+    fn streamed_items() {
+        let mut err = InvalidOptionsError::new();
+        let mut pager = Pager::new(true, None, false, &mut err)
+            .with_items_field(Some("items"))
+            .with_stream_output(true, &mut err);
+        let mut out = Vec::new();
+        assert!(pager
+            .add_page(
+                json!({"items": [1, 2], "unreachable": ["x"], "nextPageToken": "t"}),
+                &mut out
+            )
+            .unwrap());
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), "1\n2\n");
+        assert!(!pager.add_page(json!({"items": [3]}), &mut out).unwrap());
+        pager.finish(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1\n2\n3\n");
+        assert!(err.issues.is_empty());
+
+        Pager::new(false, None, false, &mut err).with_stream_output(true, &mut err);
+        assert_eq!(err.issues.len(), 1);
+    }
+
+    #[test]
+    fn ndjson_stream() {
+        let mut err = InvalidOptionsError::new();
+        let mut pager = Pager::new(false, None, false, &mut err)
+            .with_items_field(Some("items"))
+            .with_output_format(OutputFormat::NdjsonStream);
+        let mut out = Vec::new();
+        assert!(!pager
+            .add_page(
+                json!({"items": [{"a": 1}, {"a": 2}], "nextPageToken": "t"}),
+                &mut out
+            )
+            .unwrap());
+        pager.finish(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "{\"a\":1}\n{\"a\":2}\n");
+        assert!(err.issues.is_empty());
+
+        Pager::new(true, None, false, &mut err)
+            .with_output_format(OutputFormat::NdjsonStream)
+            .with_sort_key(Some("a"), &mut err);
+        assert_eq!(err.issues.len(), 1);
+    }
+
+    #[test]
+    fn max_pages() {
+        let path = state_path("max-pages");
+        let mut err = InvalidOptionsError::new();
+        let mut pager =
+            Pager::new(true, Some(&path), false, &mut err).with_max_pages(Some("2"), &mut err);
+        assert!(err.issues.is_empty());
+        let mut out = Vec::new();
+        assert!(pager
+            .add_page(json!({"items": [1], "nextPageToken": "t1"}), &mut out)
+            .unwrap());
+        assert!(!pager.truncated());
+        assert!(!pager
+            .add_page(json!({"items": [2], "nextPageToken": "t2"}), &mut out)
+            .unwrap());
+        assert!(pager.truncated());
+        pager.finish(&mut out).unwrap();
+
+        let pager =
+            Pager::new(true, Some(&path), true, &mut err).with_max_pages(Some("2"), &mut err);
+        assert!(err.issues.is_empty());
+        assert_eq!(pager.page_token(), Some("t2"));
+        fs::remove_file(&path).unwrap();
+
+        Pager::new(false, None, false, &mut err).with_max_pages(Some("1"), &mut err);
+        Pager::new(true, None, false, &mut err).with_max_pages(Some("0"), &mut err);
+        Pager::new(true, None, false, &mut err).with_max_pages(Some("many"), &mut err);
+        assert_eq!(err.issues.len(), 3);
+    }
+
+    #[test]
+    fn projected_pages() {
+        let mut err = InvalidOptionsError::new();
+        let projection = Projection::new(Some("items.name"), None, &mut err);
+        let mut pager = Pager::new(true, None, false, &mut err).with_projection(projection);
+        let mut out = Vec::new();
+        assert!(pager
+            .add_page(
+                json!({"items": [{"name": "a", "size": 1}], "nextPageToken": "t"}),
+                &mut out
+            )
+            .unwrap());
+        assert_eq!(pager.page_token(), Some("t"));
+        assert!(!pager
+            .add_page(json!({"items": [{"name": "b"}], "kind": "k"}), &mut out)
+            .unwrap());
+        pager.finish(&mut out).unwrap();
+        let value: Value = json::from_slice(&out).unwrap();
+        assert_eq!(value, json!({"items": [{"name": "a"}, {"name": "b"}]}));
+    }
+
+    #[test]
+    fn split_pages() {
+        let dir =
+            std::env::temp_dir().join(format!("google-clis-common-pages-{}", std::process::id()));
+        let mut err = InvalidOptionsError::new();
+        let mut pager =
+            Pager::new(true, None, false, &mut err).with_pages_dir(dir.to_str(), &mut err);
+        assert!(err.issues.is_empty());
+        let mut out = Vec::new();
+        let first = json!({"items": [1], "nextPageToken": "t", "totalSize": 2});
+        assert!(pager.add_page(first.clone(), &mut out).unwrap());
+        assert!(!pager.add_page(json!({"items": [2]}), &mut out).unwrap());
+        pager.finish(&mut out).unwrap();
+        assert!(out.is_empty());
+
+        let page: Value = json::from_slice(&fs::read(dir.join("page-0001.json")).unwrap()).unwrap();
+        assert_eq!(page, first);
+        assert!(dir.join("page-0002.json").exists());
+        fs::remove_dir_all(&dir).unwrap();
+
+        Pager::new(false, None, false, &mut err).with_pages_dir(Some("pages"), &mut err);
+        assert_eq!(err.issues.len(), 1);
+    }
+
+    #[test]
+    fn sorted_pages() {
+        let mut err = InvalidOptionsError::new();
+        let mut pager =
+            Pager::new(true, None, false, &mut err).with_sort_key(Some("device.id"), &mut err);
+        assert!(err.issues.is_empty());
+        let mut out = Vec::new();
+        let page = json!({
+            "devices": [{"device": {"id": "b"}}, {"name": "none"}, {"device": {"id": "c"}}],
+            "nextPageToken": "t"
+        });
+        assert!(pager.add_page(page, &mut out).unwrap());
+        let page = json!({"devices": [{"device": {"id": "a"}}, {"name": "other"}], "total": 5});
+        assert!(!pager.add_page(page, &mut out).unwrap());
+        pager.finish(&mut out).unwrap();
+        let value: Value = json::from_slice(&out).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "devices": [
+                    {"device": {"id": "a"}},
+                    {"device": {"id": "b"}},
+                    {"device": {"id": "c"}},
+                    {"name": "none"},
+                    {"name": "other"}
+                ],
+                "total": 5
+            })
+        );
+        assert_eq!(
+            compare_fields(Some(&json!(10)), Some(&json!(9.5))),
+            Ordering::Greater
+        );
+
+        Pager::new(false, None, false, &mut err).with_sort_key(Some("id"), &mut err);
+        Pager::new(true, None, false, &mut err)
+            .with_stream_output(true, &mut err)
+            .with_sort_key(Some("a..b"), &mut err);
+        assert_eq!(err.issues.len(), 3);
+    }
+
+    #[test]
+    fn filtered_pages() {
+        let mut err = InvalidOptionsError::new();
+        let mut pager = Pager::new(true, None, false, &mut err)
+            .with_items_field(Some("devices"))
+            .with_stream_output(true, &mut err)
+            .with_filter(Some("ram > 4"), &mut err);
+        assert!(err.issues.is_empty());
+        let mut out = Vec::new();
+        let page = json!({"devices": [{"ram": 8}, {"ram": 2}], "nextPageToken": "t"});
+        assert!(pager.add_page(page, &mut out).unwrap());
+        let page = json!({"devices": [{"ram": "16"}, {"name": "none"}]});
+        assert!(!pager.add_page(page, &mut out).unwrap());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"ram\":8}\n{\"ram\":\"16\"}\n"
+        );
+
+        Pager::new(true, None, false, &mut err).with_filter(Some("ram >"), &mut err);
+        assert_eq!(err.issues.len(), 1);
+    }
+
+    #[test]
+    fn page_size_cap() {
+        let mut err = InvalidOptionsError::new();
+        check_page_size("page-size", 100, 100, true, true, &mut err);
+        assert!(err.issues.is_empty());
+        check_page_size("page-size", 101, 100, false, true, &mut err);
+        assert!(err.issues.is_empty());
+        check_page_size("page-size", 101, 100, true, false, &mut err);
+        assert_eq!(
+            err.issues[0].to_string(),
+            "The value of 'page-size' is invalid: must be at most 100, \
+             the documented maximum page size.\n"
+        );
+    }
+
+    #[test]
+    fn flag_combinations() {
+        let mut err = InvalidOptionsError::new();
+        Pager::new(false, None, true, &mut err);
+        Pager::new(false, Some("state.json"), false, &mut err);
+        assert_eq!(err.issues.len(), 2);
+
+        let mut err = InvalidOptionsError::new();
+        Pager::new(true, Some(&state_path("missing")), true, &mut err);
+        assert_eq!(err.issues.len(), 1);
+    }
+
+    #[test]
+    fn resume_from_state_file() {
+        let path = state_path("resume");
+        let mut err = InvalidOptionsError::new();
+        let mut pager = Pager::new(true, Some(&path), false, &mut err);
+        let mut out = Vec::new();
+        assert!(pager
+            .add_page(json!({"items": [1], "nextPageToken": "t1"}), &mut out)
+            .unwrap());
+        assert_eq!(String::from_utf8(out).unwrap(), "{\"items\":[1]}\n");
+        // the process would have been interrupted here
+
+        let mut pager = Pager::new(true, Some(&path), true, &mut err);
+        assert!(err.issues.is_empty());
+        assert_eq!(pager.page_token(), Some("t1"));
+        assert_eq!(pager.pages(), 1);
+        let mut out = Vec::new();
+        assert!(!pager.add_page(json!({"items": [2]}), &mut out).unwrap());
+        assert_eq!(pager.pages(), 2);
+        pager.finish(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "{\"items\":[2]}\n");
+        assert!(!std::path::Path::new(&path).exists());
+    }
+}
@@ -0,0 +1,176 @@
+//! Profiles allow to keep the tokens of multiple accounts side by side in the configuration
+//! directory, and to choose among them with `--profile` or interactively.
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use crate::CLIError;
+
+/// The name of the profile used if none is chosen.
+pub const DEFAULT_PROFILE: &str = "default";
+
+const PROFILE_INFIX: &str = "-profile-";
+
+/// The file to persist the tokens of the given profile to. The default profile uses the
+/// location of tokens written before profiles existed. Profile names may only contain ASCII
+/// letters, digits, `_` and `-`, so the file stays within the configuration directory.
+pub fn token_storage_path(
+    config_dir: &str,
+    program_name: &str,
+    profile: Option<&str>,
+) -> Result<String, CLIError> {
+    match profile {
+        Some(name) if !is_valid_name(name) => Err(CLIError::InvalidProfile(name.to_string())),
+        Some(name) if name != DEFAULT_PROFILE => Ok(format!(
+            "{}/{}{}{}",
+            config_dir, program_name, PROFILE_INFIX, name
+        )),
+        _ => Ok(format!("{}/{}", config_dir, program_name)),
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+/// The sorted names of all profiles with tokens in the configuration directory.
+pub fn list_profiles(config_dir: &str, program_name: &str) -> Vec<String> {
+    let prefix = format!("{}{}", program_name, PROFILE_INFIX);
+    let mut profiles: Vec<String> = fs::read_dir(Path::new(config_dir))
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter_map(|name| {
+                    if name == program_name {
+                        Some(DEFAULT_PROFILE.to_string())
+                    } else {
+                        name.strip_prefix(&prefix).map(str::to_string)
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    profiles.sort();
+    profiles
+}
+
+/// Ask the user to pick one of the given profiles by number, and return the chosen one.
+/// An empty answer or the end of input choose the default profile.
+pub fn select_profile(
+    profiles: &[String],
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> io::Result<Option<String>> {
+    writeln!(output, "Please choose a profile:")?;
+    for (i, name) in profiles.iter().enumerate() {
+        writeln!(output, "  {}) {}", i + 1, name)?;
+    }
+    loop {
+        write!(output, "Profile [{}]: ", DEFAULT_PROFILE)?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let answer = line.trim();
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= profiles.len() => return Ok(Some(profiles[n - 1].clone())),
+            _ => writeln!(
+                output,
+                "'{}' is not a number between 1 and {}.",
+                answer,
+                profiles.len()
+            )?,
+        }
+    }
+}
+
+/// Determine the profile to use. An explicitly given profile always wins. Otherwise, and only
+/// if `interactive` is set and both standard input and standard error are terminals, the user
+/// is asked to pick one if there is more than one profile. `None` means the default profile.
+pub fn resolve_profile(
+    config_dir: &str,
+    program_name: &str,
+    profile: Option<&str>,
+    interactive: bool,
+) -> Option<String> {
+    if let Some(name) = profile {
+        return Some(name.to_string());
+    }
+    if !interactive || !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return None;
+    }
+    let profiles = list_profiles(config_dir, program_name);
+    if profiles.len() < 2 {
+        return None;
+    }
+    select_profile(&profiles, &mut io::stdin().lock(), &mut io::stderr())
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn storage_paths_and_listing() {
+        let dir = std::env::temp_dir().join(format!(
+            "google-clis-common-profiles-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let dir = dir.to_str().unwrap();
+
+        assert_eq!(
+            token_storage_path(dir, "tool", None).unwrap(),
+            format!("{}/tool", dir)
+        );
+        assert_eq!(
+            token_storage_path(dir, "tool", Some(DEFAULT_PROFILE)).unwrap(),
+            format!("{}/tool", dir)
+        );
+        for invalid in ["../../x", "a/b", "", "wörk"] {
+            assert!(matches!(
+                token_storage_path(dir, "tool", Some(invalid)),
+                Err(CLIError::InvalidProfile(_))
+            ));
+        }
+        for profile in [None, Some("work"), Some("home")] {
+            fs::write(token_storage_path(dir, "tool", profile).unwrap(), "[]").unwrap();
+        }
+        fs::write(format!("{}/tool-secret.json", dir), "{}").unwrap();
+
+        assert_eq!(list_profiles(dir, "tool"), vec!["default", "home", "work"]);
+        assert!(list_profiles(dir, "other").is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn interactive_selection() {
+        let profiles = vec!["default".to_string(), "work".to_string()];
+        let mut out = Vec::new();
+        let chosen = select_profile(&profiles, &mut "3\nwork\n2\n".as_bytes(), &mut out).unwrap();
+        assert_eq!(chosen.as_deref(), Some("work"));
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("  2) work"));
+        assert!(out.contains("'3' is not a number between 1 and 2."));
+        assert!(out.contains("'work' is not a number between 1 and 2."));
+
+        assert_eq!(
+            select_profile(&profiles, &mut "\n".as_bytes(), &mut Vec::new()).unwrap(),
+            None
+        );
+        assert_eq!(
+            select_profile(&profiles, &mut "".as_bytes(), &mut Vec::new()).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Pruning responses before they are written, with `--only-fields` and `--strip-fields`
+//!
+//! Both take a comma separated list of dotted paths into the response, like `items.name`. Arrays
+//! are looked through, so `items.name` refers to the `name` of each element of `items`.
+//! `--only-fields` keeps the given paths and drops everything else, while `--strip-fields` drops
+//! the given paths. If both are given, the fields to keep are picked first.
+//!
+//! Unlike the `fields` parameter, which has the server leave out fields, this works with any
+//! method and any path, but doesn't reduce the amount of data which is transferred.
+use serde_json::Value;
+
+use crate::{CLIError, InvalidOptionsError};
+
+const PATH_SEP: char = ',';
+const FIELD_SEP: char = '.';
+
+type Path = Vec<String>;
+
+/// The fields to keep and to drop, as given on the command-line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Projection {
+    only: Option<Vec<Path>>,
+    strip: Vec<Path>,
+}
+
+impl Projection {
+    /// Create a projection from the values of `--only-fields` and `--strip-fields`. Paths with an
+    /// empty field name are pushed onto `err`.
+    pub fn new(
+        only_fields: Option<&str>,
+        strip_fields: Option<&str>,
+        err: &mut InvalidOptionsError,
+    ) -> Projection {
+        Projection {
+            only: only_fields.map(|v| parse_paths("--only-fields", v, err)),
+            strip: strip_fields
+                .map(|v| parse_paths("--strip-fields", v, err))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns true if applying the projection leaves any value as is.
+    pub fn is_empty(&self) -> bool {
+        self.only.is_none() && self.strip.is_empty()
+    }
+
+    /// Prune `value` in place.
+    pub fn apply(&self, value: &mut Value) {
+        if let Some(only) = &self.only {
+            let paths: Vec<&[String]> = only.iter().map(Vec::as_slice).collect();
+            keep(value, &paths);
+        }
+        for path in &self.strip {
+            strip(value, path);
+        }
+    }
+}
+
+fn parse_paths(flag: &str, value: &str, err: &mut InvalidOptionsError) -> Vec<Path> {
+    let mut paths = Vec::new();
+    for path in value.split(PATH_SEP).map(str::trim) {
+        let fields: Path = path.split(FIELD_SEP).map(str::to_string).collect();
+        if fields.iter().any(String::is_empty) {
+            err.issues.push(CLIError::ParseError(
+                flag.to_string(),
+                "field paths".to_string(),
+                value.to_string(),
+                format!("'{}' has an empty field name", path),
+            ));
+            continue;
+        }
+        paths.push(fields);
+    }
+    paths
+}
+
+/// Drop all fields of `value` which are not on or below one of `paths`.
+fn keep(value: &mut Value, paths: &[&[String]]) {
+    if paths.iter().any(|p| p.is_empty()) {
+        return;
+    }
+    match value {
+        Value::Object(object) => object.retain(|key, value| {
+            let rest: Vec<&[String]> = paths
+                .iter()
+                .filter(|p| p[0] == *key)
+                .map(|p| &p[1..])
+                .collect();
+            if !rest.is_empty() {
+                keep(value, &rest);
+            }
+            !rest.is_empty()
+        }),
+        Value::Array(items) => items.iter_mut().for_each(|item| keep(item, paths)),
+        _ => {}
+    }
+}
+
+/// Drop the field at `path` below `value`, if there is one.
+fn strip(value: &mut Value, path: &[String]) {
+    match value {
+        Value::Object(object) => match path {
+            [field] => {
+                object.remove(field);
+            }
+            [field, rest @ ..] => {
+                if let Some(value) = object.get_mut(field) {
+                    strip(value, rest);
+                }
+            }
+            [] => {}
+        },
+        Value::Array(items) => items.iter_mut().for_each(|item| strip(item, path)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    fn response() -> Value {
+        json!({
+            "items": [
+                {"name": "a", "sample": [1, 2], "info": {"os": "x", "ram": 1}},
+                {"name": "b", "info": {"os": "y"}}
+            ],
+            "total": 2
+        })
+    }
+
+    #[test]
+    fn only_and_strip() {
+        let mut err = InvalidOptionsError::new();
+        let projection = Projection::new(
+            Some("items.name, items.info,total"),
+            Some("items.info.ram"),
+            &mut err,
+        );
+        assert!(err.issues.is_empty());
+        let mut value = response();
+        projection.apply(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "items": [{"name": "a", "info": {"os": "x"}}, {"name": "b", "info": {"os": "y"}}],
+                "total": 2
+            })
+        );
+
+        let mut value = response();
+        Projection::new(None, Some("items.sample,missing.field"), &mut err).apply(&mut value);
+        assert_eq!(
+            value["items"][0],
+            json!({"name": "a", "info": {"os": "x", "ram": 1}})
+        );
+        assert!(Projection::new(None, None, &mut err).is_empty());
+        assert!(err.issues.is_empty());
+
+        Projection::new(Some("items..name"), Some(""), &mut err);
+        assert_eq!(err.issues.len(), 2);
+    }
+}
@@ -0,0 +1,148 @@
+//! Saving calls as fixtures, with `--record <dir>`
+//!
+//! Each call made through a delegate with a [`Recorder`] is written into a file of its own in the
+//! given directory, named after its position and the method, like
+//! `0001-chromemanagement.customers.apps.android.get.json`. The file holds a [`Fixture`], which
+//! has the request and the response as they were sent, along with the status code. Failed calls
+//! are recorded as well, as long as there was a response.
+//!
+//! Fixtures which are recorded into a directory already holding fixtures are numbered after the
+//! existing ones, which allows to build up a set of fixtures across many invocations.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The query parameter carrying the API key, which is never recorded.
+const API_KEY_PARAM: &str = "key";
+
+/// A call as it happened, with the request and the response.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    /// The id of the method, like `chromemanagement.customers.apps.android.get`.
+    pub method: String,
+    /// The HTTP method of the request.
+    pub http_method: String,
+    /// The URL of the request, without the API key.
+    pub url: String,
+    /// The body of the request, if it had one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<Value>,
+    /// The status code of the response.
+    pub status: u16,
+    /// The body of the response, if it was JSON.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+}
+
+impl Fixture {
+    /// The name of the file the fixture is written to if it is the `number`th of the directory.
+    pub fn file_name(&self, number: usize) -> String {
+        format!("{:04}-{}.json", number, self.method)
+    }
+}
+
+/// Writes fixtures into a directory, numbering them in the order they are recorded. Clones
+/// share the numbering, so calls made concurrently each get a file of their own.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    dir: PathBuf,
+    count: Arc<AtomicUsize>,
+}
+
+impl Recorder {
+    /// Record into `dir`, which is created with the first fixture if it doesn't exist yet.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Recorder {
+        let dir = dir.as_ref().to_path_buf();
+        let existing = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+                    .count()
+            })
+            .unwrap_or(0);
+        Recorder {
+            dir,
+            count: Arc::new(AtomicUsize::new(existing)),
+        }
+    }
+
+    /// Write `fixture` into a new file, and return its path.
+    pub fn record(&self, fixture: &Fixture) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let number = self.count.fetch_add(1, Ordering::SeqCst) + 1;
+        let path = self.dir.join(fixture.file_name(number));
+        let mut file = fs::File::create(&path)?;
+        serde_json::to_writer_pretty(&mut file, fixture)?;
+        Ok(path)
+    }
+}
+
+/// Returns `url` without the API key, if it has one.
+pub fn redact_url(url: &str) -> String {
+    let (base, query) = match url.split_once('?') {
+        Some(parts) => parts,
+        None => return url.to_string(),
+    };
+    let query: Vec<&str> = query
+        .split('&')
+        .filter(|pair| pair.split('=').next() != Some(API_KEY_PARAM))
+        .collect();
+    if query.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}?{}", base, query.join("&"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn redacts_api_key() {
+        assert_eq!(redact_url("https://x/v1/a"), "https://x/v1/a");
+        assert_eq!(redact_url("https://x/v1/a?key=s"), "https://x/v1/a");
+        assert_eq!(
+            redact_url("https://x/v1/a?alt=json&key=s&keys=k"),
+            "https://x/v1/a?alt=json&keys=k"
+        );
+    }
+
+    #[test]
+    fn numbers_fixtures() {
+        let dir =
+            std::env::temp_dir().join(format!("google-clis-common-record-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        let fixture = Fixture {
+            method: "test.things.get".to_string(),
+            http_method: "GET".to_string(),
+            url: "https://x/v1/things/a".to_string(),
+            request: None,
+            status: 200,
+            response: Some(json!({"name": "a"})),
+        };
+
+        let recorder = Recorder::new(&dir);
+        let first = recorder.record(&fixture).unwrap();
+        assert!(first.ends_with("0001-test.things.get.json"));
+        assert!(recorder
+            .clone()
+            .record(&fixture)
+            .unwrap()
+            .ends_with("0002-test.things.get.json"));
+
+        let path = Recorder::new(&dir).record(&fixture).unwrap();
+        assert!(path.ends_with("0003-test.things.get.json"));
+        let read: Fixture = serde_json::from_reader(fs::File::open(&first).unwrap()).unwrap();
+        assert_eq!(read, fixture);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,298 @@
+//! Answering calls from fixtures instead of the network, with `--replay <dir>`
+//!
+//! The fixtures are the files written with `--record`, as read by [`Replayer::from_dir`]. Each
+//! request is answered with the status and response body of the first fixture which wasn't used
+//! yet and was recorded for the same HTTP method and URL, ignoring the API key. Once all of them
+//! were used, the last one is used again. Requests without a matching fixture are answered with
+//! a `404 Not Found` error, just like the API would.
+//!
+//! The [`Connector`] either connects to the API as usual, or to the replayer, which speaks
+//! HTTP/1 through an in-memory stream. That way, calls are made exactly like they would be
+//! against the API, but need neither network nor authentication.
+use std::error::Error as StdError;
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use hyper::client::connect::{Connected, Connection};
+use hyper::{Body, Request, Response, Uri};
+use serde_json::json;
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf};
+
+use crate::record::{redact_url, Fixture};
+
+/// The size of the buffer of the in-memory stream, which only limits how much is written at once.
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Default)]
+struct Fixtures {
+    fixtures: Vec<Fixture>,
+    used: Vec<bool>,
+}
+
+/// Answers requests with previously recorded fixtures. Clones share the fixtures, and which of
+/// them were used.
+#[derive(Debug, Clone, Default)]
+pub struct Replayer(Arc<Mutex<Fixtures>>);
+
+impl Replayer {
+    /// Answer requests with the given fixtures, in the given order.
+    pub fn new(fixtures: Vec<Fixture>) -> Replayer {
+        let used = vec![false; fixtures.len()];
+        Replayer(Arc::new(Mutex::new(Fixtures { fixtures, used })))
+    }
+
+    /// Read all fixtures in `dir`, ordered by their file name as written with `--record`.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> io::Result<Replayer> {
+        let mut paths: Vec<_> = fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+        let mut fixtures = Vec::with_capacity(paths.len());
+        for path in paths {
+            let fixture: Fixture = serde_json::from_slice(&fs::read(&path)?).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("'{}' is no fixture: {}", path.display(), err),
+                )
+            })?;
+            fixtures.push(fixture);
+        }
+        Ok(Replayer::new(fixtures))
+    }
+
+    /// The fixture answering a request with the given HTTP method to `path_and_query`, if any.
+    pub fn fixture(&self, http_method: &str, path_and_query: &str) -> Option<Fixture> {
+        let mut state = self.0.lock().unwrap();
+        let target = redact_url(path_and_query);
+        let matching: Vec<usize> = state
+            .fixtures
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.http_method == http_method && path_of(&f.url) == target)
+            .map(|(i, _)| i)
+            .collect();
+        let index = matching
+            .iter()
+            .copied()
+            .find(|&i| !state.used[i])
+            .or_else(|| matching.last().copied())?;
+        state.used[index] = true;
+        Some(state.fixtures[index].clone())
+    }
+
+    async fn respond(self, req: Request<Body>) -> Result<Response<Body>, hyper::http::Error> {
+        let path_and_query = req
+            .uri()
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+        let (status, body) = match self.fixture(req.method().as_str(), path_and_query) {
+            Some(fixture) => (fixture.status, fixture.response),
+            None => (
+                404,
+                Some(json!({"error": {
+                    "code": 404,
+                    "message": format!("There is no fixture for {} {}.", req.method(), path_and_query),
+                    "status": "NOT_FOUND",
+                }})),
+            ),
+        };
+        let mut res = Response::builder().status(status);
+        let body = match body {
+            Some(body) => {
+                res = res.header(hyper::header::CONTENT_TYPE, "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+        res.body(body)
+    }
+
+    /// Open a connection answered by this replayer.
+    fn connect(&self) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+        let replayer = self.clone();
+        let service = hyper::service::service_fn(move |req| replayer.clone().respond(req));
+        tokio::spawn(
+            hyper::server::conn::Http::new()
+                .http1_only(true)
+                .serve_connection(server, service),
+        );
+        client
+    }
+}
+
+/// The path and query of `url`, without the API key.
+fn path_of(url: &str) -> String {
+    match url.parse::<Uri>() {
+        Ok(uri) => redact_url(uri.path_and_query().map(|p| p.as_str()).unwrap_or("/")),
+        Err(_) => redact_url(url),
+    }
+}
+
+/// Connects either through `C` to the API, or to a replayer.
+#[derive(Debug, Clone)]
+pub enum Connector<C> {
+    Network(C),
+    Fixtures(Replayer),
+}
+
+impl<C> tower_service::Service<Uri> for Connector<C>
+where
+    C: tower_service::Service<Uri>,
+    C::Future: Send + 'static,
+    C::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    type Response = Stream<C::Response>;
+    type Error = Box<dyn StdError + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            Connector::Network(inner) => inner.poll_ready(cx).map_err(Into::into),
+            Connector::Fixtures(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        match self {
+            Connector::Network(inner) => {
+                let future = inner.call(uri);
+                Box::pin(async move { future.await.map(Stream::Network).map_err(Into::into) })
+            }
+            Connector::Fixtures(replayer) => {
+                let stream = replayer.connect();
+                Box::pin(async move { Ok(Stream::Fixtures(stream)) })
+            }
+        }
+    }
+}
+
+/// A connection made by a [`Connector`].
+#[derive(Debug)]
+pub enum Stream<T> {
+    Network(T),
+    Fixtures(DuplexStream),
+}
+
+impl<T: Connection> Connection for Stream<T> {
+    fn connected(&self) -> Connected {
+        match self {
+            Stream::Network(inner) => inner.connected(),
+            Stream::Fixtures(_) => Connected::new(),
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Stream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Network(inner) => Pin::new(inner).poll_read(cx, buf),
+            Stream::Fixtures(inner) => Pin::new(inner).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Stream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Stream::Network(inner) => Pin::new(inner).poll_write(cx, buf),
+            Stream::Fixtures(inner) => Pin::new(inner).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Network(inner) => Pin::new(inner).poll_flush(cx),
+            Stream::Fixtures(inner) => Pin::new(inner).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Network(inner) => Pin::new(inner).poll_shutdown(cx),
+            Stream::Fixtures(inner) => Pin::new(inner).poll_shutdown(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fixture(url: &str, status: u16, name: &str) -> Fixture {
+        Fixture {
+            method: "test.things.get".to_string(),
+            http_method: "GET".to_string(),
+            url: url.to_string(),
+            request: None,
+            status,
+            response: Some(json!({ "name": name })),
+        }
+    }
+
+    #[test]
+    fn matches_fixtures() {
+        let replayer = Replayer::new(vec![
+            fixture("https://x/v1/things/a?alt=json", 500, "first"),
+            fixture("https://x/v1/things/a?alt=json", 200, "second"),
+            fixture("https://x/v1/things/b", 200, "b"),
+        ]);
+        let name = |f: Option<Fixture>| f.and_then(|f| f.response).map(|r| r["name"].clone());
+        assert_eq!(
+            name(replayer.fixture("GET", "/v1/things/a?alt=json&key=k")),
+            Some(json!("first"))
+        );
+        assert_eq!(
+            name(replayer.clone().fixture("GET", "/v1/things/a?alt=json")),
+            Some(json!("second"))
+        );
+        assert_eq!(
+            name(replayer.fixture("GET", "/v1/things/a?alt=json")),
+            Some(json!("second"))
+        );
+        assert_eq!(
+            name(replayer.fixture("GET", "/v1/things/b")),
+            Some(json!("b"))
+        );
+        assert!(replayer.fixture("DELETE", "/v1/things/b").is_none());
+        assert!(replayer.fixture("GET", "/v1/things/c").is_none());
+    }
+
+    #[tokio::test]
+    async fn serves_fixtures() {
+        let replayer = Replayer::new(vec![fixture("https://x/v1/things/a", 200, "a")]);
+        let client = hyper::Client::builder().build::<_, Body>(Connector::<
+            hyper::client::HttpConnector,
+        >::Fixtures(replayer));
+
+        let res = client
+            .get("https://x/v1/things/a".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(&body[..], br#"{"name":"a"}"#);
+
+        let res = client
+            .get("https://x/v1/things/b".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 404);
+    }
+}
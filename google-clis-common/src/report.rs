@@ -0,0 +1,290 @@
+//! Rendering the responses of count methods for monitoring systems, as chosen by `--report-format`
+//!
+//! In the Prometheus text exposition format, each integer field of the response becomes a gauge
+//! named after it. Each object in an array of the response becomes a sample of a gauge named
+//! after the array. Its value is taken from its count fields, like `count` or `deviceCount`, and
+//! its remaining fields become the labels of the sample:
+//!
+//! ```text
+//! # TYPE chrome_management_cpu_reports gauge
+//! chrome_management_cpu_reports{bucket="Intel(R) Core(TM) i5"} 42
+//! ```
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde_json as json;
+use serde_json::{Map, Value};
+
+/// The format to write the response of a count method in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Prometheus,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ReportFormat::Json),
+            "prometheus" => Ok(ReportFormat::Prometheus),
+            _ => Err("expected 'json' or 'prometheus'".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ReportFormat::Json => "json",
+            ReportFormat::Prometheus => "prometheus",
+        })
+    }
+}
+
+/// Writes responses in the chosen format, using the given prefix for the names of all metrics.
+#[derive(Debug, Clone, Default)]
+pub struct ReportWriter {
+    format: ReportFormat,
+    metric_prefix: String,
+}
+
+impl ReportWriter {
+    pub fn new(format: ReportFormat, metric_prefix: &str) -> ReportWriter {
+        ReportWriter {
+            format,
+            metric_prefix: metric_prefix.to_string(),
+        }
+    }
+
+    pub fn format(&self) -> ReportFormat {
+        self.format
+    }
+
+    /// Write `value`, which is expected to have its null values removed already.
+    pub fn write(&self, value: &Value, out: &mut dyn Write) -> io::Result<()> {
+        match self.format {
+            ReportFormat::Json => json::to_writer_pretty(&mut *out, value)?,
+            ReportFormat::Prometheus => {
+                let mut metrics = Metrics::default();
+                if let Value::Object(object) = value {
+                    metrics.add_object(&self.metric_prefix, object);
+                }
+                metrics.write(out)?;
+            }
+        }
+        out.flush()
+    }
+}
+
+struct Sample {
+    labels: Vec<(String, String)>,
+    value: String,
+}
+
+/// Samples grouped by the name of their metric, in the order the metrics were first seen.
+#[derive(Default)]
+struct Metrics(Vec<(String, Vec<Sample>)>);
+
+impl Metrics {
+    fn add(&mut self, name: String, sample: Sample) {
+        match self.0.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, samples)) => samples.push(sample),
+            None => self.0.push((name, vec![sample])),
+        }
+    }
+
+    fn add_object(&mut self, prefix: &str, object: &Map<String, Value>) {
+        for (key, value) in object {
+            let name = metric_name(prefix, key);
+            match value {
+                Value::Object(nested) => self.add_object(&name, nested),
+                Value::Array(items) => {
+                    for item in items {
+                        if let Value::Object(item) = item {
+                            self.add_item(&name, item);
+                        }
+                    }
+                }
+                _ => {
+                    if let Some(number) = as_number(value) {
+                        self.add(
+                            name,
+                            Sample {
+                                labels: Vec::new(),
+                                value: number,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Add a sample for each count field of `item`, or for each of its numbers if it has no
+    /// count fields. All other scalar fields are used as labels.
+    fn add_item(&mut self, name: &str, item: &Map<String, Value>) {
+        let has_counts = item
+            .iter()
+            .any(|(key, value)| is_count_field(key) && as_number(value).is_some());
+        let is_metric = |key: &str, value: &Value| {
+            as_number(value).is_some() && (!has_counts || is_count_field(key))
+        };
+
+        let labels: Vec<(String, String)> = item
+            .iter()
+            .filter(|(key, value)| !is_metric(key, value))
+            .filter_map(|(key, value)| {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    Value::Bool(_) | Value::Number(_) => value.to_string(),
+                    _ => return None,
+                };
+                Some((snake_case(key), value))
+            })
+            .collect();
+        for (key, value) in item.iter().filter(|(key, value)| is_metric(key, value)) {
+            let name = if key == "count" {
+                name.to_string()
+            } else {
+                metric_name(name, key)
+            };
+            self.add(
+                name,
+                Sample {
+                    labels: labels.clone(),
+                    value: as_number(value).expect("metrics to be numbers"),
+                },
+            );
+        }
+    }
+
+    fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        for (name, samples) in &self.0 {
+            writeln!(out, "# TYPE {} gauge", name)?;
+            for sample in samples {
+                write!(out, "{}", name)?;
+                if !sample.labels.is_empty() {
+                    let labels: Vec<String> = sample
+                        .labels
+                        .iter()
+                        .map(|(k, v)| format!("{}=\"{}\"", k, escape_label_value(v)))
+                        .collect();
+                    write!(out, "{{{}}}", labels.join(","))?;
+                }
+                writeln!(out, " {}", sample.value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_count_field(key: &str) -> bool {
+    key == "count" || key.ends_with("Count")
+}
+
+/// Numbers, as well as 64 bit integers which are transmitted as strings.
+fn as_number(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) if s.parse::<i64>().is_ok() || s.parse::<u64>().is_ok() => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn metric_name(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        snake_case(key)
+    } else {
+        format!("{}_{}", prefix, snake_case(key))
+    }
+}
+
+/// Convert a camel-cased field name into a valid metric or label name.
+fn snake_case(key: &str) -> String {
+    let mut name = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            if !name.is_empty() {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() {
+            name.push(c);
+        } else {
+            name.push('_');
+        }
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    fn prometheus(value: Value) -> String {
+        let mut out = Vec::new();
+        ReportWriter::new(ReportFormat::Prometheus, "chrome_management")
+            .write(&value, &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn counts_with_labels() {
+        let out = prometheus(json!({
+            "cpuReports": [
+                {"bucket": "Intel \"i5\"", "count": "42"},
+                {"bucket": "AMD", "count": "7"}
+            ],
+            "memoryReports": [{"bucket": "8192", "count": "3"}]
+        }));
+        assert_eq!(
+            out,
+            "# TYPE chrome_management_cpu_reports gauge\n\
+             chrome_management_cpu_reports{bucket=\"Intel \\\"i5\\\"\"} 42\n\
+             chrome_management_cpu_reports{bucket=\"AMD\"} 7\n\
+             # TYPE chrome_management_memory_reports gauge\n\
+             chrome_management_memory_reports{bucket=\"8192\"} 3\n"
+        );
+    }
+
+    #[test]
+    fn plain_counts_and_items_without_count_fields() {
+        let out = prometheus(json!({
+            "pendingUpdate": "5",
+            "noRecentPolicySyncCount": "1",
+            "nextPageToken": "abc",
+            "requestedApps": [{"appId": "x", "requestCount": "2", "latestRequestTime": "2023"}],
+            "versions": [{"version": "110.0.1", "devices": 4}]
+        }));
+        assert_eq!(
+            out,
+            "# TYPE chrome_management_no_recent_policy_sync_count gauge\n\
+             chrome_management_no_recent_policy_sync_count 1\n\
+             # TYPE chrome_management_pending_update gauge\n\
+             chrome_management_pending_update 5\n\
+             # TYPE chrome_management_requested_apps_request_count gauge\n\
+             chrome_management_requested_apps_request_count{app_id=\"x\",latest_request_time=\"2023\"} 2\n\
+             # TYPE chrome_management_versions_devices gauge\n\
+             chrome_management_versions_devices{version=\"110.0.1\"} 4\n"
+        );
+        assert_eq!(ReportFormat::from_str("json"), Ok(ReportFormat::Json));
+        assert!(ReportFormat::from_str("csv").is_err());
+    }
+}
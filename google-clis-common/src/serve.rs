@@ -0,0 +1,358 @@
+//! Forwarding calls of other tools to the API, with `--serve <addr>`
+//!
+//! Instead of making a call itself, the CLI listens on the given address and forwards each request
+//! it receives to the API, authorized with the same token it would use for its own calls. That way,
+//! tools which know nothing about authentication, like `curl`, can be pointed at the proxy while
+//! looking into an integration, like `curl http://127.0.0.1:8080/v1/customers/my_customer/...`.
+//!
+//! Only read requests are forwarded for now, any other request is answered with
+//! `405 Method Not Allowed`. Each request is logged to stderr along with the status it was answered
+//! with. As anyone who can reach the address acts with the token, it should be a loopback address.
+//! On a loopback address, requests are only answered if their `Host` is that address, as web pages
+//! could reach the proxy through a domain resolving to it otherwise. Headers which only apply to a
+//! single connection, like `Connection` and `Proxy-Authorization`, are not passed on.
+use std::convert::Infallible;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use google_apis_common::GetToken;
+use hyper::client::connect::Connect;
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use hyper::server::conn::AddrIncoming;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, StatusCode, Uri};
+use serde_json::json;
+
+/// The HTTP methods of requests which are forwarded.
+const READ_METHODS: &[Method] = &[Method::GET, Method::HEAD];
+
+/// Headers which only apply to a single connection, and aren't passed on in either direction.
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Forwards read requests to the API, authorized with a token of the CLI. Clones share the
+/// client and authenticator.
+pub struct Proxy<C> {
+    root_url: Arc<str>,
+    client: hyper::Client<C, Body>,
+    auth: Arc<dyn GetToken>,
+    scopes: Arc<[String]>,
+    hosts: Arc<[String]>,
+}
+
+impl<C: Clone> Clone for Proxy<C> {
+    fn clone(&self) -> Self {
+        Proxy {
+            root_url: self.root_url.clone(),
+            client: self.client.clone(),
+            auth: self.auth.clone(),
+            scopes: self.scopes.clone(),
+            hosts: self.hosts.clone(),
+        }
+    }
+}
+
+impl<C> Proxy<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    /// Forward requests to the API at `root_url` through `client`, authorized with a token for
+    /// `scopes` obtained from `auth`.
+    pub fn new(
+        root_url: &str,
+        client: hyper::Client<C, Body>,
+        auth: Box<dyn GetToken>,
+        scopes: &[&str],
+    ) -> Proxy<C> {
+        Proxy {
+            root_url: root_url.trim_end_matches('/').into(),
+            client,
+            auth: Arc::from(auth),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            hosts: Arc::new([]),
+        }
+    }
+
+    /// Listen on `addr` and forward the requests received there until the process is stopped. On
+    /// a loopback address, only requests naming that address as their `Host` are answered.
+    pub async fn serve(mut self, addr: SocketAddr) -> hyper::Result<()> {
+        let incoming = AddrIncoming::bind(&addr)?;
+        if addr.ip().is_loopback() {
+            self.hosts = loopback_hosts(incoming.local_addr()).into();
+        }
+        let server = hyper::Server::builder(incoming);
+        let root_url = self.root_url.clone();
+        let make_service = make_service_fn(move |_| {
+            let proxy = self.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| proxy.clone().respond(req))) }
+        });
+        let server = server.serve(make_service);
+        writeln!(
+            io::stderr(),
+            "Forwarding read requests on http://{} to {}",
+            server.local_addr(),
+            root_url
+        )
+        .ok();
+        server.await
+    }
+
+    async fn respond(self, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+        let start = Instant::now();
+        let method = req.method().clone();
+        let path_and_query = req
+            .uri()
+            .path_and_query()
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| "/".to_string());
+        let res = self.forward(req).await;
+        writeln!(
+            io::stderr(),
+            "{} {} -> {} in {}ms",
+            method,
+            path_and_query,
+            res.status(),
+            start.elapsed().as_millis()
+        )
+        .ok();
+        Ok(res)
+    }
+
+    async fn forward(&self, req: Request<Body>) -> Response<Body> {
+        if !self.hosts.is_empty() {
+            let host = req
+                .headers()
+                .get(header::HOST)
+                .and_then(|h| h.to_str().ok());
+            if !host.is_some_and(|host| self.hosts.iter().any(|h| h == host)) {
+                return error_response(
+                    StatusCode::FORBIDDEN,
+                    format!(
+                        "Only requests for {} are answered.",
+                        self.hosts.join(" or ")
+                    ),
+                );
+            }
+        }
+        if !READ_METHODS.contains(req.method()) {
+            return error_response(
+                StatusCode::METHOD_NOT_ALLOWED,
+                format!("Only {} requests are forwarded.", method_names()),
+            );
+        }
+        let scopes: Vec<&str> = self.scopes.iter().map(String::as_str).collect();
+        let token = match self.auth.get_token(&scopes).await {
+            Ok(token) => token,
+            Err(err) => {
+                return error_response(
+                    StatusCode::BAD_GATEWAY,
+                    format!("Failed to obtain a token: {}", err),
+                )
+            }
+        };
+        let req = match self.upstream_request(req, token.as_deref()) {
+            Ok(req) => req,
+            Err(err) => return error_response(StatusCode::BAD_REQUEST, err.to_string()),
+        };
+        match self.client.request(req).await {
+            Ok(mut res) => {
+                remove_hop_by_hop_headers(res.headers_mut());
+                res
+            }
+            Err(err) => error_response(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to reach the API: {}", err),
+            ),
+        }
+    }
+
+    /// The request to send to the API for `req`, with its authorization replaced by `token`.
+    fn upstream_request(
+        &self,
+        req: Request<Body>,
+        token: Option<&str>,
+    ) -> Result<Request<Body>, hyper::http::Error> {
+        let (mut parts, body) = req.into_parts();
+        let path_and_query = parts
+            .uri
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+        parts.uri = format!("{}{}", self.root_url, path_and_query).parse::<Uri>()?;
+        remove_hop_by_hop_headers(&mut parts.headers);
+        parts.headers.remove(header::HOST);
+        parts.headers.remove(header::AUTHORIZATION);
+        if let Some(token) = token {
+            parts.headers.insert(
+                header::AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token))?,
+            );
+        }
+        Ok(Request::from_parts(parts, body))
+    }
+}
+
+/// The values of the `Host` header naming the loopback address `addr`.
+fn loopback_hosts(addr: SocketAddr) -> Vec<String> {
+    let mut hosts = vec![addr.to_string(), format!("localhost:{}", addr.port())];
+    if addr.port() == 80 {
+        hosts.push(addr.to_string().trim_end_matches(":80").to_string());
+        hosts.push("localhost".to_string());
+    }
+    hosts
+}
+
+/// Remove the headers which only apply to a single connection, including those named by the
+/// `Connection` header.
+fn remove_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let named: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in named {
+        headers.remove(name);
+    }
+    for name in HOP_BY_HOP_HEADERS {
+        headers.remove(*name);
+    }
+}
+
+fn method_names() -> String {
+    READ_METHODS
+        .iter()
+        .map(Method::as_str)
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// An error in the format of the API, so tools handle it like one of the API.
+fn error_response(status: StatusCode, message: String) -> Response<Body> {
+    let body = json!({"error": {
+        "code": status.as_u16(),
+        "message": message,
+        "status": status.canonical_reason().unwrap_or_default(),
+    }});
+    let mut res = Response::new(Body::from(body.to_string()));
+    *res.status_mut() = status;
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::record::Fixture;
+    use crate::replay::{Connector, Replayer};
+
+    fn proxy() -> Proxy<Connector<hyper::client::HttpConnector>> {
+        let replayer = Replayer::new(vec![Fixture {
+            method: "test.things.get".to_string(),
+            http_method: "GET".to_string(),
+            url: "https://x/v1/things/a".to_string(),
+            request: None,
+            status: 200,
+            response: Some(json!({ "name": "a" })),
+        }]);
+        let client = hyper::Client::builder().build(Connector::Fixtures(replayer));
+        Proxy::new(
+            "https://x/",
+            client,
+            Box::new("token".to_string()),
+            &["https://x/auth/things.readonly"],
+        )
+    }
+
+    #[test]
+    fn rewrites_requests() {
+        let req = Request::get("http://127.0.0.1:8080/v1/things?pageSize=2")
+            .header(header::HOST, "127.0.0.1:8080")
+            .header(header::AUTHORIZATION, "Bearer other")
+            .header(header::CONNECTION, "keep-alive, x-private")
+            .header("x-private", "1")
+            .header(header::PROXY_AUTHORIZATION, "Basic secret")
+            .header(header::TE, "trailers")
+            .header(header::ACCEPT, "application/json")
+            .body(Body::empty())
+            .unwrap();
+        let req = proxy().upstream_request(req, Some("token")).unwrap();
+        assert_eq!(req.uri(), "https://x/v1/things?pageSize=2");
+        assert_eq!(req.headers()[header::AUTHORIZATION], "Bearer token");
+        assert_eq!(req.headers()[header::ACCEPT], "application/json");
+        for name in [
+            "host",
+            "connection",
+            "x-private",
+            "proxy-authorization",
+            "te",
+        ] {
+            assert!(req.headers().get(name).is_none(), "{} was passed on", name);
+        }
+    }
+
+    fn request(method: Method, host: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri("/v1/things/a")
+            .header(header::HOST, host)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn forwards_reads() {
+        let res = proxy()
+            .respond(request(Method::GET, "127.0.0.1:8080"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(&body[..], br#"{"name":"a"}"#);
+
+        let res = proxy()
+            .respond(request(Method::DELETE, "127.0.0.1:8080"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn checks_hosts() {
+        let mut proxy = proxy();
+        proxy.hosts = loopback_hosts("127.0.0.1:8080".parse().unwrap()).into();
+        for (host, status) in [
+            ("127.0.0.1:8080", StatusCode::OK),
+            ("localhost:8080", StatusCode::OK),
+            ("attacker.example:8080", StatusCode::FORBIDDEN),
+            ("127.0.0.1:9090", StatusCode::FORBIDDEN),
+        ] {
+            let res = proxy
+                .clone()
+                .respond(request(Method::GET, host))
+                .await
+                .unwrap();
+            assert_eq!(res.status(), status, "{}", host);
+        }
+        assert_eq!(
+            loopback_hosts("[::1]:80".parse().unwrap()),
+            ["[::1]:80", "localhost:80", "[::1]", "localhost"]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! The phases of a call, as written with `--timing`
+//!
+//! The connector of the hub is assembled from layers which are each wrapped in [`Timing::wrap`],
+//! to learn when the name was resolved, the connection was established and secured. Together
+//! with the start of the call, the arrival of the response headers and the end of the call, as
+//! told by the delegate, this yields a line on standard error like
+//!
+//! ```text
+//! [chromemanagement.customers.apps.android.get] dns: 12 ms, connect: 31 ms, tls: 58 ms, first byte: 143 ms, total: 150 ms
+//! ```
+//!
+//! All times are measured from the start of the request, once the token was obtained, so the
+//! connections made to fetch the token aren't counted. If the call was retried, the phases are
+//! the ones of the last attempt. Phases which didn't happen, like connecting if a connection could
+//! be reused, are left out.
+use std::fmt::Write as _;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// A phase of a call, in the order they happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Resolved,
+    Connected,
+    Secured,
+    FirstByte,
+    Done,
+}
+
+impl Phase {
+    fn label(self) -> &'static str {
+        match self {
+            Phase::Resolved => "dns",
+            Phase::Connected => "connect",
+            Phase::Secured => "tls",
+            Phase::FirstByte => "first byte",
+            Phase::Done => "total",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Marks {
+    start: Option<Instant>,
+    phases: Vec<(Phase, Instant)>,
+}
+
+/// The points in time each phase of the current call ended at, shared by the connector and the
+/// delegate.
+#[derive(Debug, Clone, Default)]
+pub struct Timing(Arc<Mutex<Marks>>);
+
+impl Timing {
+    /// Forget about the previous call, and measure from now on.
+    pub fn start(&self) {
+        let mut marks = self.0.lock().unwrap();
+        marks.start = Some(Instant::now());
+        marks.phases.clear();
+    }
+
+    /// Note that `phase` ended now. Phases ending before a call was started are ignored.
+    pub fn mark(&self, phase: Phase) {
+        let mut marks = self.0.lock().unwrap();
+        if marks.start.is_some() {
+            marks.phases.push((phase, Instant::now()));
+        }
+    }
+
+    /// Wrap `service`, like a resolver or connector, to mark `phase` whenever it completes a
+    /// request successfully.
+    pub fn wrap<S>(&self, service: S, phase: Phase) -> Timed<S> {
+        Timed {
+            inner: service,
+            timing: self.clone(),
+            phase,
+        }
+    }
+
+    /// The time each phase took since the start, or `None` if no call was started.
+    pub fn phases(&self) -> Option<Vec<(Phase, Duration)>> {
+        let marks = self.0.lock().unwrap();
+        let start = marks.start?;
+        Some(
+            marks
+                .phases
+                .iter()
+                .map(|&(phase, at)| (phase, at.duration_since(start)))
+                .collect(),
+        )
+    }
+
+    /// A line summarizing the phases of the current call, or `None` if no call was started.
+    pub fn summary(&self) -> Option<String> {
+        let mut line = String::new();
+        for (phase, elapsed) in self.phases()? {
+            if !line.is_empty() {
+                line.push_str(", ");
+            }
+            write!(line, "{}: {} ms", phase.label(), elapsed.as_millis()).ok();
+        }
+        Some(line)
+    }
+}
+
+/// A service marking a phase of the call whenever it completes a request successfully.
+#[derive(Debug, Clone)]
+pub struct Timed<S> {
+    inner: S,
+    timing: Timing,
+    phase: Phase,
+}
+
+impl<S, R> tower_service::Service<R> for Timed<S>
+where
+    S: tower_service::Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let future = self.inner.call(request);
+        let (timing, phase) = (self.timing.clone(), self.phase);
+        Box::pin(async move {
+            let response = future.await?;
+            timing.mark(phase);
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use tower_service::Service;
+
+    struct Echo;
+
+    impl Service<u32> for Echo {
+        type Response = u32;
+        type Error = ();
+        type Future = std::future::Ready<Result<u32, ()>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: u32) -> Self::Future {
+            std::future::ready(Ok(request))
+        }
+    }
+
+    #[tokio::test]
+    async fn phases() {
+        let timing = Timing::default();
+        let mut resolver = timing.wrap(Echo, Phase::Resolved);
+        assert_eq!(resolver.call(1).await, Ok(1));
+        assert_eq!(timing.summary(), None);
+
+        timing.start();
+        assert_eq!(resolver.call(2).await, Ok(2));
+        timing.mark(Phase::Done);
+        let phases: Vec<_> = timing.phases().unwrap().into_iter().map(|p| p.0).collect();
+        assert_eq!(phases, vec![Phase::Resolved, Phase::Done]);
+        assert!(timing.summary().unwrap().starts_with("dns: "));
+
+        timing.start();
+        assert_eq!(timing.summary(), Some(String::new()));
+    }
+}
@@ -0,0 +1,382 @@
+//! A bounded token cache shared among the authenticators of multiple hubs
+//!
+//! Hubs of different services which are created with the same credentials would otherwise fetch
+//! and refresh tokens for overlapping scopes independently. Wrapping each of their authenticators
+//! with the same [`TokenCache`] lets them reuse each other's tokens instead. Callers asking for a
+//! token while it is fetched wait for that fetch, and tokens are fetched anew before they expire,
+//! as far as the wrapped authenticator tells when that is.
+//!
+//! The cache must only be shared among authenticators of the same credentials, as tokens are
+//! looked up by their scopes alone.
+use std::any::Any;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use google_apis_common::GetToken;
+use tokio::sync::Mutex;
+
+type GetTokenOutput<'a> = Pin<
+    Box<
+        dyn Future<Output = Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>>
+            + Send
+            + 'a,
+    >,
+>;
+
+type GetExpiringTokenOutput<'a> = Pin<
+    Box<
+        dyn Future<
+                Output = Result<
+                    Option<(String, Option<SystemTime>)>,
+                    Box<dyn std::error::Error + Send + Sync>,
+                >,
+            > + Send
+            + 'a,
+    >,
+>;
+
+/// The amount of time a token is reused by default. Tokens issued by Google are valid for an
+/// hour, which leaves plenty of time for them to be used once handed out.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(30 * 60);
+
+/// How long a token must still be valid to be handed out, so it doesn't expire on its way to the
+/// server.
+pub const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+struct Token {
+    token: String,
+    expires_at: Option<SystemTime>,
+    fetched_at: Instant,
+}
+
+impl Token {
+    fn is_fresh(&self, max_age: Duration) -> bool {
+        self.fetched_at.elapsed() < max_age
+            && self
+                .expires_at
+                .is_none_or(|at| SystemTime::now() + EXPIRY_MARGIN < at)
+    }
+}
+
+struct Entry {
+    scopes: Vec<String>,
+    // locked while the token is fetched, so callers asking for the same scopes meanwhile wait for
+    // it instead of fetching one as well
+    token: Arc<Mutex<Option<Token>>>,
+}
+
+struct Inner {
+    capacity: usize,
+    max_age: Duration,
+    // least recently used first
+    entries: Mutex<Vec<Entry>>,
+}
+
+/// A cache of tokens keyed by their set of scopes, which evicts the least recently used token
+/// once it is full. Clones refer to the same cache.
+#[derive(Clone)]
+pub struct TokenCache {
+    inner: Arc<Inner>,
+}
+
+impl TokenCache {
+    /// Create a cache holding up to `capacity` tokens, each of which is reused for
+    /// [`DEFAULT_MAX_AGE`].
+    pub fn new(capacity: usize) -> TokenCache {
+        TokenCache::with_max_age(capacity, DEFAULT_MAX_AGE)
+    }
+
+    /// Create a cache holding up to `capacity` tokens, each of which is reused for `max_age`, or
+    /// until [`EXPIRY_MARGIN`] before it expires if that is sooner.
+    pub fn with_max_age(capacity: usize, max_age: Duration) -> TokenCache {
+        TokenCache {
+            inner: Arc::new(Inner {
+                capacity,
+                max_age,
+                entries: Mutex::new(Vec::with_capacity(capacity)),
+            }),
+        }
+    }
+
+    /// Wrap the given authenticator so it consults this cache before fetching a token.
+    /// The result can be handed to a hub like any other authenticator.
+    pub fn wrap<A: 'static + GetToken>(&self, auth: A) -> CachedAuth {
+        // An authenticator wrapped with this cache already is returned as is, as it would wait
+        // for its own fetch otherwise.
+        if let Some(cached) = (&auth as &dyn Any).downcast_ref::<CachedAuth>() {
+            if Arc::ptr_eq(&cached.cache.inner, &self.inner) {
+                return cached.clone();
+            }
+        }
+        CachedAuth {
+            cache: self.clone(),
+            auth: Box::new(auth),
+        }
+    }
+
+    /// The amount of tokens currently held.
+    pub async fn len(&self) -> usize {
+        self.inner.entries.lock().await.len()
+    }
+
+    /// Returns true if no token is held.
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Forget all tokens, for instance after the credentials were revoked.
+    pub async fn clear(&self) {
+        self.inner.entries.lock().await.clear();
+    }
+
+    async fn get_token(
+        &self,
+        auth: &dyn GetToken,
+        scopes: &[&str],
+    ) -> Result<Option<(String, Option<SystemTime>)>, Box<dyn std::error::Error + Send + Sync>>
+    {
+        if self.inner.capacity == 0 {
+            return auth.get_expiring_token(scopes).await;
+        }
+        let mut key: Vec<String> = scopes.iter().map(|s| s.to_string()).collect();
+        key.sort();
+        key.dedup();
+
+        let slot = {
+            let mut entries = self.inner.entries.lock().await;
+            let entry = match entries.iter().position(|e| e.scopes == key) {
+                Some(pos) => entries.remove(pos),
+                None => {
+                    if entries.len() >= self.inner.capacity {
+                        entries.remove(0);
+                    }
+                    Entry {
+                        scopes: key,
+                        token: Arc::default(),
+                    }
+                }
+            };
+            let slot = entry.token.clone();
+            entries.push(entry);
+            slot
+        };
+
+        // Only the slot of these scopes stays locked while fetching, so fetches for other scopes
+        // don't wait on this one.
+        let mut token = slot.lock().await;
+        if let Some(token) = token.as_ref().filter(|t| t.is_fresh(self.inner.max_age)) {
+            return Ok(Some((token.token.clone(), token.expires_at)));
+        }
+        *token = None;
+        let fetched = auth.get_expiring_token(scopes).await;
+        match &fetched {
+            Ok(Some((fetched, expires_at))) => {
+                *token = Some(Token {
+                    token: fetched.clone(),
+                    expires_at: *expires_at,
+                    fetched_at: Instant::now(),
+                })
+            }
+            // nothing is kept without a token, so the next call asks again
+            _ => self
+                .inner
+                .entries
+                .lock()
+                .await
+                .retain(|e| !Arc::ptr_eq(&e.token, &slot)),
+        }
+        fetched
+    }
+}
+
+/// An authenticator which consults a [`TokenCache`] before asking the authenticator it wraps.
+#[derive(Clone)]
+pub struct CachedAuth {
+    cache: TokenCache,
+    auth: Box<dyn GetToken>,
+}
+
+impl GetToken for CachedAuth {
+    fn get_token<'a>(&'a self, scopes: &'a [&str]) -> GetTokenOutput<'a> {
+        Box::pin(async move {
+            let token = self.cache.get_token(&*self.auth, scopes).await?;
+            Ok(token.map(|(token, _)| token))
+        })
+    }
+
+    fn get_expiring_token<'a>(&'a self, scopes: &'a [&str]) -> GetExpiringTokenOutput<'a> {
+        Box::pin(self.cache.get_token(&*self.auth, scopes))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Default)]
+    struct CountingAuth {
+        fetches: Arc<AtomicUsize>,
+        // how long the tokens are valid, if known
+        lifetime: Option<Duration>,
+        without_token: bool,
+    }
+
+    impl GetToken for CountingAuth {
+        fn get_token<'a>(&'a self, scopes: &'a [&str]) -> GetTokenOutput<'a> {
+            Box::pin(async move {
+                let token = self.get_expiring_token(scopes).await?;
+                Ok(token.map(|(token, _)| token))
+            })
+        }
+
+        fn get_expiring_token<'a>(&'a self, scopes: &'a [&str]) -> GetExpiringTokenOutput<'a> {
+            Box::pin(async move {
+                let n = self.fetches.fetch_add(1, Ordering::SeqCst);
+                // give concurrent callers the chance to ask meanwhile
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                if self.without_token {
+                    return Ok(None);
+                }
+                let expires_at = self.lifetime.map(|lifetime| SystemTime::now() + lifetime);
+                Ok(Some((format!("{}-{}", scopes.join(","), n), expires_at)))
+            })
+        }
+    }
+
+    impl CountingAuth {
+        fn fetches(&self) -> usize {
+            self.fetches.load(Ordering::SeqCst)
+        }
+    }
+
+    #[tokio::test]
+    async fn shared_among_authenticators() {
+        let cache = TokenCache::new(2);
+        let (a, b) = (CountingAuth::default(), CountingAuth::default());
+        let (ca, cb) = (cache.wrap(a.clone()), cache.wrap(b.clone()));
+
+        let token = ca.get_token(&["x", "y"]).await.unwrap();
+        assert_eq!(token.as_deref(), Some("x,y-0"));
+        assert_eq!(cb.get_token(&["y", "x", "x"]).await.unwrap(), token);
+        assert_eq!((a.fetches(), b.fetches()), (1, 0));
+        assert_eq!(cache.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used() {
+        let cache = TokenCache::new(2);
+        let auth = CountingAuth::default();
+        let cached = cache.wrap(auth.clone());
+
+        cached.get_token(&["a"]).await.unwrap();
+        cached.get_token(&["b"]).await.unwrap();
+        cached.get_token(&["a"]).await.unwrap();
+        cached.get_token(&["c"]).await.unwrap();
+        assert_eq!(auth.fetches(), 3);
+        assert_eq!(cache.len().await, 2);
+
+        cached.get_token(&["a"]).await.unwrap();
+        assert_eq!(auth.fetches(), 3);
+        cached.get_token(&["b"]).await.unwrap();
+        assert_eq!(auth.fetches(), 4);
+    }
+
+    #[tokio::test]
+    async fn nested_authenticators() {
+        let cache = TokenCache::new(2);
+        let auth = CountingAuth::default();
+        let cached = cache.wrap(cache.wrap(auth.clone()));
+
+        let token = cached.get_token(&["a"]).await.unwrap();
+        assert_eq!(token.as_deref(), Some("a-0"));
+        assert_eq!(cached.get_token(&["a"]).await.unwrap(), token);
+        assert_eq!(auth.fetches(), 1);
+        assert_eq!(cache.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn expires_tokens() {
+        let cache = TokenCache::with_max_age(2, Duration::from_secs(0));
+        let auth = CountingAuth::default();
+        let cached = cache.wrap(auth.clone());
+
+        cached.get_token(&["a"]).await.unwrap();
+        cached.get_token(&["a"]).await.unwrap();
+        assert_eq!(auth.fetches(), 2);
+        assert_eq!(cache.len().await, 1);
+
+        cache.clear().await;
+        assert!(cache.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn expires_with_token() {
+        let cache = TokenCache::new(2);
+        let auth = CountingAuth {
+            lifetime: Some(Duration::from_secs(30)),
+            ..CountingAuth::default()
+        };
+        let cached = cache.wrap(auth.clone());
+
+        // the token expires long before the maximum age, and too soon to be handed out again
+        assert_eq!(
+            cached.get_token(&["a"]).await.unwrap().as_deref(),
+            Some("a-0")
+        );
+        assert_eq!(
+            cached.get_token(&["a"]).await.unwrap().as_deref(),
+            Some("a-1")
+        );
+        assert_eq!(auth.fetches(), 2);
+
+        let auth = CountingAuth {
+            lifetime: Some(Duration::from_secs(3600)),
+            ..CountingAuth::default()
+        };
+        let cached = cache.wrap(auth.clone());
+        let (token, expires_at) = cached.get_expiring_token(&["b"]).await.unwrap().unwrap();
+        assert!(expires_at.is_some());
+        assert_eq!(
+            cached.get_expiring_token(&["b"]).await.unwrap(),
+            Some((token, expires_at))
+        );
+        assert_eq!(auth.fetches(), 1);
+    }
+
+    #[tokio::test]
+    async fn deduplicates_concurrent_fetches() {
+        let cache = TokenCache::new(2);
+        let auth = CountingAuth::default();
+        let cached = cache.wrap(auth.clone());
+
+        let calls: Vec<_> = (0..8)
+            .map(|_| {
+                let cached = cached.clone();
+                tokio::spawn(async move { cached.get_token(&["a"]).await.unwrap() })
+            })
+            .collect();
+        for call in calls {
+            assert_eq!(call.await.unwrap().as_deref(), Some("a-0"));
+        }
+        assert_eq!(auth.fetches(), 1);
+    }
+
+    #[tokio::test]
+    async fn keeps_no_missing_tokens() {
+        let cache = TokenCache::new(2);
+        let auth = CountingAuth {
+            without_token: true,
+            ..CountingAuth::default()
+        };
+        let cached = cache.wrap(auth.clone());
+
+        assert_eq!(cached.get_token(&["a"]).await.unwrap(), None);
+        assert_eq!(cached.get_token(&["a"]).await.unwrap(), None);
+        assert_eq!(auth.fetches(), 2);
+        assert!(cache.is_empty().await);
+    }
+}
@@ -0,0 +1,497 @@
+//! Graduated request logging for the `--verbose` flag
+//!
+//! The [`VerboseDelegate`] is handed to every call builder and prints what happens on the wire
+//! to standard error, with more detail for each additional `-v`:
+//!
+//! 1. each attempt, its status code and latency, as well as the overall outcome
+//! 2. the response headers
+//! 3. request and error response bodies
+//!
+//! Independently of the level, it writes how long each phase of the call took if it is given a
+//! [`Timing`], as done with `--timing`, and saves each call as a fixture if it is given a
+//! [`Recorder`], as done with `--record`. A warning is written if the hub checks the API revision
+//! and finds it too old.
+//!
+//! Failed requests aren't retried unless it is given a maximum of retries, as done with
+//! `--max-retries`. Then, they are retried as a [`Backoff`] does: connection errors and responses
+//! with a transient status, like `503 Service Unavailable`, are retried after the delay the server
+//! asks for, if any, or with exponential backoff otherwise. As repeating a request which may have
+//! been carried out could create a resource twice, only `GET`, `HEAD`, `PUT` and `DELETE` requests
+//! are retried that way. Other requests are only retried after `429 Too Many Requests` and
+//! `503 Service Unavailable` with a `Retry-After` header, which tell that it wasn't carried out.
+use std::io::{self, Write};
+use std::time::Instant;
+
+use google_apis_common::retry::Backoff;
+use google_apis_common::{Delegate, MethodInfo, Retry, RevisionDrift};
+use hyper::Method;
+use serde_json::Value;
+
+use crate::record::{redact_url, Fixture, Recorder};
+use crate::timing::{Phase, Timing};
+
+/// Log attempts and their timing.
+pub const LOG_ATTEMPTS: u64 = 1;
+/// Additionally log response headers.
+pub const LOG_HEADERS: u64 = 2;
+/// Additionally log request and error bodies.
+pub const LOG_BODIES: u64 = 3;
+
+/// A delegate which logs each request attempt according to a verbosity level.
+/// A level of 0 keeps it silent.
+pub struct VerboseDelegate {
+    level: u64,
+    method: &'static str,
+    http_method: Method,
+    attempts: u32,
+    backoff: Backoff,
+    started_at: Option<Instant>,
+    attempt_started_at: Option<Instant>,
+    request_body: Option<String>,
+    timing: Option<Timing>,
+    recorder: Option<Recorder>,
+    recorded_request: Option<Value>,
+    fixture: Option<Fixture>,
+    out: Box<dyn Write + Send>,
+}
+
+impl VerboseDelegate {
+    /// Create a new instance logging to standard error.
+    pub fn new(level: u64) -> VerboseDelegate {
+        VerboseDelegate::with_writer(level, Box::new(io::stderr()))
+    }
+
+    /// Create a new instance logging into the given writer.
+    pub fn with_writer(level: u64, out: Box<dyn Write + Send>) -> VerboseDelegate {
+        VerboseDelegate {
+            level,
+            method: "",
+            http_method: Method::GET,
+            attempts: 0,
+            backoff: Backoff {
+                max_retries: 0,
+                ..Backoff::default()
+            },
+            started_at: None,
+            attempt_started_at: None,
+            request_body: None,
+            timing: None,
+            recorder: None,
+            recorded_request: None,
+            fixture: None,
+            out,
+        }
+    }
+
+    /// Write the phases of each call measured by `timing` once it finished.
+    pub fn with_timing(mut self, timing: Timing) -> VerboseDelegate {
+        self.timing = Some(timing);
+        self
+    }
+
+    /// Save each call which got a response as a fixture with `recorder`.
+    pub fn with_recorder(mut self, recorder: Recorder) -> VerboseDelegate {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Retry transient failures of each call up to `max_retries` times.
+    pub fn with_max_retries(mut self, max_retries: u32) -> VerboseDelegate {
+        self.backoff.max_retries = max_retries;
+        self
+    }
+
+    pub fn level(&self) -> u64 {
+        self.level
+    }
+
+    /// Remember the request body to log it once the call begins, as it is assembled by the
+    /// caller before the call is made.
+    pub fn request_body(&mut self, body: &Value) {
+        if self.level >= LOG_BODIES {
+            self.request_body = serde_json::to_string_pretty(body).ok();
+        }
+        if self.recorder.is_some() {
+            self.recorded_request = Some(body.clone());
+        }
+    }
+
+    fn elapsed_ms(since: Option<Instant>) -> u128 {
+        since.map(|t| t.elapsed().as_millis()).unwrap_or(0)
+    }
+
+    fn log(&mut self, msg: String) {
+        writeln!(self.out, "[{}] {}", self.method, msg).ok();
+    }
+
+    /// Log the delay of a retry the backoff asks for, if any.
+    fn retry(&mut self, retry: Retry) -> Retry {
+        if let Retry::After(delay) = retry {
+            if self.level >= LOG_ATTEMPTS {
+                self.log(format!("retrying in {} ms", delay.as_millis()));
+            }
+        }
+        retry
+    }
+}
+
+impl Delegate for VerboseDelegate {
+    fn begin(&mut self, info: MethodInfo) {
+        self.method = info.id;
+        self.http_method = info.http_method.clone();
+        self.attempts = 0;
+        self.started_at = Some(Instant::now());
+        if self.recorder.is_some() {
+            self.fixture = Some(Fixture {
+                method: info.id.to_string(),
+                http_method: info.http_method.to_string(),
+                request: self.recorded_request.take(),
+                ..Default::default()
+            });
+        }
+        if let Some(body) = self.request_body.take() {
+            self.log(format!("request body: {}", body));
+        }
+    }
+
+    fn pre_request(&mut self) {
+        self.attempts += 1;
+        self.attempt_started_at = Some(Instant::now());
+        // Started only now, as connections made to obtain the token would be counted otherwise.
+        if let Some(timing) = &self.timing {
+            timing.start();
+        }
+    }
+
+    fn request_url(&mut self, url: &str) {
+        if let Some(fixture) = &mut self.fixture {
+            fixture.url = redact_url(url);
+        }
+    }
+
+    fn http_response(&mut self, res: &hyper::Response<hyper::body::Body>) {
+        if let Some(timing) = &self.timing {
+            timing.mark(Phase::FirstByte);
+        }
+        if let Some(fixture) = &mut self.fixture {
+            fixture.status = res.status().as_u16();
+            fixture.response = None;
+        }
+        if self.level < LOG_ATTEMPTS {
+            return;
+        }
+        let elapsed = Self::elapsed_ms(self.attempt_started_at);
+        self.log(format!(
+            "attempt {}: {} ({} ms)",
+            self.attempts,
+            res.status(),
+            elapsed
+        ));
+        if self.level >= LOG_HEADERS {
+            for (name, value) in res.headers() {
+                let value = value.to_str().unwrap_or("<binary>");
+                self.log(format!("  {}: {}", name, value));
+            }
+        }
+    }
+
+    fn response_body(&mut self, body: &str) {
+        if let Some(fixture) = &mut self.fixture {
+            fixture.response = serde_json::from_str(body).ok();
+        }
+    }
+
+    fn api_revision_drift(&mut self, drift: &RevisionDrift) {
+        writeln!(
+            self.out,
+            "Warning: this client was generated from API revision {}, which is {} days old. \
+             Fields added to the API since are not available.",
+            drift.revision,
+            drift.age.num_days()
+        )
+        .ok();
+    }
+
+    fn http_error(&mut self, err: &hyper::Error) -> Retry {
+        if self.level >= LOG_ATTEMPTS {
+            let elapsed = Self::elapsed_ms(self.attempt_started_at);
+            self.log(format!(
+                "attempt {}: {} ({} ms)",
+                self.attempts, err, elapsed
+            ));
+        }
+        let retry = self.backoff.http_error(&self.http_method, self.attempts);
+        self.retry(retry)
+    }
+
+    fn http_failure(
+        &mut self,
+        res: &hyper::Response<hyper::body::Body>,
+        err: Option<Value>,
+    ) -> Retry {
+        if let Some(fixture) = &mut self.fixture {
+            fixture.response = err.clone();
+        }
+        if let Some(err) = err.as_ref().filter(|_| self.level >= LOG_BODIES) {
+            let body = serde_json::to_string_pretty(err).unwrap_or_default();
+            self.log(format!("error body: {}", body));
+        }
+        let retry = self
+            .backoff
+            .http_failure(&self.http_method, self.attempts, res, err.as_ref());
+        self.retry(retry)
+    }
+
+    fn finished(&mut self, is_success: bool) {
+        if self.level >= LOG_ATTEMPTS {
+            let elapsed = Self::elapsed_ms(self.started_at);
+            self.log(format!(
+                "{} after {} attempt(s) in {} ms",
+                if is_success { "succeeded" } else { "failed" },
+                self.attempts,
+                elapsed
+            ));
+        }
+        if let Some(timing) = self.timing.clone() {
+            timing.mark(Phase::Done);
+            if let Some(summary) = timing.summary() {
+                self.log(summary);
+            }
+        }
+        if let (Some(recorder), Some(fixture)) = (&self.recorder, self.fixture.take()) {
+            if fixture.status != 0 {
+                if let Err(err) = recorder.record(&fixture) {
+                    self.log(format!("failed to record the call: {}", err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuf {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }
+    }
+
+    fn run(level: u64) -> Vec<String> {
+        let buf = SharedBuf::default();
+        let mut dlg = VerboseDelegate::with_writer(level, Box::new(buf.clone()));
+        dlg.request_body(&serde_json::json!({"name": "thing"}));
+        dlg.begin(MethodInfo {
+            id: "test.things.get",
+            http_method: Method::GET,
+        });
+        let res = hyper::Response::builder()
+            .status(404)
+            .header("x-test", "yes")
+            .body(hyper::body::Body::empty())
+            .unwrap();
+        for _ in 0..2 {
+            dlg.pre_request();
+            dlg.http_response(&res);
+        }
+        dlg.http_failure(&res, Some(serde_json::json!({"error": "not found"})));
+        dlg.finished(false);
+        buf.lines()
+    }
+
+    #[test]
+    fn silent_without_level() {
+        assert!(run(0).is_empty());
+    }
+
+    #[test]
+    fn attempts_and_timing() {
+        let lines = run(LOG_ATTEMPTS);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("[test.things.get] attempt 1: 404 Not Found ("));
+        assert!(lines[1].starts_with("[test.things.get] attempt 2: 404 Not Found ("));
+        assert!(lines[2].starts_with("[test.things.get] failed after 2 attempt(s) in "));
+    }
+
+    #[test]
+    fn retries() {
+        let buf = SharedBuf::default();
+        let mut dlg =
+            VerboseDelegate::with_writer(LOG_ATTEMPTS, Box::new(buf.clone())).with_max_retries(2);
+        dlg.begin(MethodInfo {
+            id: "test.things.get",
+            http_method: Method::GET,
+        });
+        let unavailable = || {
+            hyper::Response::builder()
+                .status(503)
+                .body(hyper::body::Body::empty())
+                .unwrap()
+        };
+        dlg.pre_request();
+        assert!(matches!(
+            dlg.http_failure(&unavailable(), None),
+            Retry::After(d) if d <= Duration::from_secs(1)
+        ));
+        dlg.pre_request();
+        let res = hyper::Response::builder()
+            .status(429)
+            .header("retry-after", "7")
+            .body(hyper::body::Body::empty())
+            .unwrap();
+        assert!(matches!(
+            dlg.http_failure(&res, None),
+            Retry::After(d) if d == Duration::from_secs(7)
+        ));
+        dlg.pre_request();
+        assert!(matches!(
+            dlg.http_failure(&unavailable(), None),
+            Retry::Abort
+        ));
+        assert!(buf.lines()[0].starts_with("[test.things.get] retrying in "));
+
+        let mut dlg = VerboseDelegate::with_writer(0, Box::new(io::sink())).with_max_retries(2);
+        dlg.pre_request();
+        let not_found = hyper::Response::builder()
+            .status(404)
+            .body(hyper::body::Body::empty())
+            .unwrap();
+        assert!(matches!(dlg.http_failure(&not_found, None), Retry::Abort));
+        let mut dlg = VerboseDelegate::new(0);
+        dlg.pre_request();
+        assert!(matches!(
+            dlg.http_failure(&unavailable(), None),
+            Retry::Abort
+        ));
+    }
+
+    #[test]
+    fn retries_only_idempotent_requests() {
+        let mut dlg = VerboseDelegate::with_writer(0, Box::new(io::sink())).with_max_retries(2);
+        dlg.begin(MethodInfo {
+            id: "test.things.insert",
+            http_method: Method::POST,
+        });
+        let status = |code: u16| {
+            hyper::Response::builder()
+                .status(code)
+                .header("retry-after", "1")
+                .body(hyper::body::Body::empty())
+                .unwrap()
+        };
+        dlg.pre_request();
+        assert!(matches!(dlg.http_failure(&status(500), None), Retry::Abort));
+        assert!(matches!(
+            dlg.http_failure(&status(503), None),
+            Retry::After(_)
+        ));
+        assert!(matches!(
+            dlg.http_failure(&status(429), None),
+            Retry::After(_)
+        ));
+        let unavailable = hyper::Response::builder()
+            .status(503)
+            .body(hyper::body::Body::empty())
+            .unwrap();
+        assert!(matches!(dlg.http_failure(&unavailable, None), Retry::Abort));
+
+        dlg.begin(MethodInfo {
+            id: "test.things.update",
+            http_method: Method::PUT,
+        });
+        dlg.pre_request();
+        assert!(matches!(
+            dlg.http_failure(&status(500), None),
+            Retry::After(_)
+        ));
+    }
+
+    #[test]
+    fn timing() {
+        let buf = SharedBuf::default();
+        let timing = Timing::default();
+        let mut dlg =
+            VerboseDelegate::with_writer(0, Box::new(buf.clone())).with_timing(timing.clone());
+        dlg.begin(MethodInfo {
+            id: "test.things.get",
+            http_method: Method::GET,
+        });
+        timing.start();
+        timing.mark(Phase::Connected);
+        dlg.pre_request();
+        dlg.http_response(&hyper::Response::new(hyper::body::Body::empty()));
+        dlg.finished(true);
+        let lines = buf.lines();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("[test.things.get] first byte: "));
+        assert!(lines[0].contains(", total: "));
+    }
+
+    #[test]
+    fn records() {
+        let dir = std::env::temp_dir().join(format!(
+            "google-clis-common-verbose-record-{}",
+            std::process::id()
+        ));
+        let mut dlg = VerboseDelegate::with_writer(0, Box::new(SharedBuf::default()))
+            .with_recorder(Recorder::new(&dir));
+        dlg.request_body(&serde_json::json!({"name": "thing"}));
+        dlg.begin(MethodInfo {
+            id: "test.things.insert",
+            http_method: Method::POST,
+        });
+        dlg.pre_request();
+        dlg.request_url("https://x/v1/things?key=secret&alt=json");
+        dlg.http_response(&hyper::Response::new(hyper::body::Body::empty()));
+        dlg.response_body(r#"{"name": "thing", "id": 1}"#);
+        dlg.finished(true);
+
+        let path = dir.join("0001-test.things.insert.json");
+        let fixture: Fixture =
+            serde_json::from_reader(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(
+            fixture,
+            Fixture {
+                method: "test.things.insert".to_string(),
+                http_method: "POST".to_string(),
+                url: "https://x/v1/things?alt=json".to_string(),
+                request: Some(serde_json::json!({"name": "thing"})),
+                status: 200,
+                response: Some(serde_json::json!({"name": "thing", "id": 1})),
+            }
+        );
+    }
+
+    #[test]
+    fn headers_and_bodies() {
+        let lines = run(LOG_HEADERS);
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[1], "[test.things.get]   x-test: yes");
+
+        let lines = run(LOG_BODIES);
+        assert!(lines[0].starts_with("[test.things.get] request body: {"));
+        assert!(lines.iter().any(|l| l.contains("error body: {")));
+    }
+}
@@ -0,0 +1,160 @@
+//! Shorthands for well-known Google types in `-r` arguments
+//!
+//! A `google.type.Date`, which is made of `year`, `month` and `day` fields, may be set at once
+//! with a value like `2024-01-15`, instead of each of its fields on its own. Durations, which are
+//! sent as a number of seconds like `5400s`, may also be given as `1h30m` or `1m30.5s`.
+use serde_json::{Map, Value};
+
+/// Parse `value`, formatted as `YYYY-MM-DD`, into the fields of a `google.type.Date`.
+pub fn parse_date(value: &str) -> Result<Value, String> {
+    let parts: Vec<&str> = value.split('-').collect();
+    let (year, month, day) = match parts[..] {
+        [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => {
+            (number(year)?, number(month)?, number(day)?)
+        }
+        _ => return Err("expected a date like 2024-01-15".to_string()),
+    };
+    if !(1..=12).contains(&month) {
+        return Err(format!("there is no month {}", month));
+    }
+    if day == 0 || day > days_in_month(year, month) {
+        return Err(format!("there is no day {} in month {}", day, month));
+    }
+
+    let mut fields = Map::new();
+    fields.insert("year".to_string(), year.into());
+    fields.insert("month".to_string(), month.into());
+    fields.insert("day".to_string(), day.into());
+    Ok(Value::Object(fields))
+}
+
+fn number(digits: &str) -> Result<u32, String> {
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("'{}' is not a number", digits));
+    }
+    digits.parse().map_err(|e| format!("{}", e))
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn is_leap_year(year: u32) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+/// Parse `value` as a duration, like `1h30m`, `90s` or `1.5s`, into the number of seconds the API
+/// expects, like `5400s`. Hours (`h`), minutes (`m`), seconds (`s`) and milliseconds (`ms`) may
+/// be combined, and the whole duration may be negative.
+pub fn parse_duration(value: &str) -> Result<String, String> {
+    let (negative, mut rest) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    if rest.is_empty() {
+        return Err("expected a duration like 1h30m or 90s".to_string());
+    }
+
+    let mut nanos: u128 = 0;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .ok_or_else(|| format!("'{}' has no unit, like h, m, s or ms", rest))?;
+        let (amount, unit_and_rest) = rest.split_at(digits);
+        let units = unit_and_rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(unit_and_rest.len());
+        let (unit, next) = unit_and_rest.split_at(units);
+        let scale: u128 = match unit {
+            "h" => 3_600_000_000_000,
+            "m" => 60_000_000_000,
+            "s" => 1_000_000_000,
+            "ms" => 1_000_000,
+            _ => return Err(format!("'{}' is not a unit, like h, m, s or ms", unit)),
+        };
+        nanos += scaled(amount, scale)?;
+        rest = next;
+    }
+
+    let seconds = nanos / 1_000_000_000;
+    let fraction = nanos % 1_000_000_000;
+    let sign = if negative && nanos > 0 { "-" } else { "" };
+    if fraction == 0 {
+        Ok(format!("{}{}s", sign, seconds))
+    } else {
+        let fraction = format!("{:09}", fraction);
+        Ok(format!(
+            "{}{}.{}s",
+            sign,
+            seconds,
+            fraction.trim_end_matches('0')
+        ))
+    }
+}
+
+/// The number of nanoseconds in `amount` of a unit lasting `scale` nanoseconds.
+fn scaled(amount: &str, scale: u128) -> Result<u128, String> {
+    let invalid = || format!("'{}' is not a number", amount);
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if whole.is_empty() && fraction.is_empty() || fraction.contains('.') {
+        return Err(invalid());
+    }
+    let whole: u128 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| invalid())?
+    };
+    let mut nanos = whole.checked_mul(scale).ok_or_else(invalid)?;
+    let mut place = scale;
+    for digit in fraction.bytes() {
+        place /= 10;
+        nanos += u128::from(digit - b'0') * place;
+    }
+    Ok(nanos)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            parse_date("2024-01-15"),
+            Ok(json!({"year": 2024, "month": 1, "day": 15}))
+        );
+        assert!(parse_date("2024-02-29").is_ok());
+        assert_eq!(
+            parse_date("2023-02-29"),
+            Err("there is no day 29 in month 2".to_string())
+        );
+        assert_eq!(
+            parse_date("2024-13-01"),
+            Err("there is no month 13".to_string())
+        );
+        assert!(parse_date("2024-1-15").is_err());
+        assert!(parse_date("15.01.2024").is_err());
+        assert!(parse_date("+024-01-15").is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("1h30m"), Ok("5400s".to_string()));
+        assert_eq!(parse_duration("5400s"), Ok("5400s".to_string()));
+        assert_eq!(parse_duration("1m30.5s"), Ok("90.5s".to_string()));
+        assert_eq!(parse_duration("1.5h"), Ok("5400s".to_string()));
+        assert_eq!(parse_duration("250ms"), Ok("0.25s".to_string()));
+        assert_eq!(parse_duration("-2s"), Ok("-2s".to_string()));
+        assert_eq!(parse_duration("0s"), Ok("0s".to_string()));
+        assert!(parse_duration("90").is_err());
+        assert!(parse_duration("1d").is_err());
+        assert!(parse_duration("1..5s").is_err());
+        assert!(parse_duration("").is_err());
+    }
+}
@@ -0,0 +1,58 @@
+#!/usr/bin/env python
+
+import unittest
+import json
+
+from generator.lib.cli import schema_outline
+
+
+class AttrDict(dict):
+    __getattr__ = dict.__getitem__
+
+
+def attr_dicts(value):
+    return json.loads(json.dumps(value), object_hook=AttrDict)
+
+
+class CliTest(unittest.TestCase):
+
+    def test_schema_outline(self):
+        schemas = attr_dicts({
+            'ListDevicesResponse': {
+                'id': 'ListDevicesResponse',
+                'properties': {
+                    'devices': {'type': 'array', 'items': {'$ref': 'Device'}},
+                    'nextPageToken': {'type': 'string'},
+                },
+            },
+            'Device': {
+                'id': 'Device',
+                'properties': {
+                    'deviceId': {'type': 'string'},
+                    'totalRamBytes': {'type': 'string', 'format': 'int64'},
+                    'state': {'type': 'string', 'enum': ['ACTIVE', 'DISABLED']},
+                    'labels': {'type': 'object', 'additionalProperties': {'type': 'string'}},
+                    'parent': {'$ref': 'Device'},
+                    'siblings': {'type': 'array', 'items': {'$ref': 'Device'}},
+                    'cpu': {'type': 'object', 'properties': {'cores': {'type': 'integer', 'format': 'int32'}}},
+                },
+            },
+        })
+        c = AttrDict(schemas=schemas)
+        self.assertEqual(schema_outline(c, schemas['ListDevicesResponse']), [
+            'ListDevicesResponse',
+            '  devices: [Device]',
+            '    cpu: object',
+            '      cores: integer (int32)',
+            '    deviceId: string',
+            '    labels: { string: string }',
+            '    parent: Device (see above)',
+            '    siblings: [Device] (see above)',
+            '    state: string, one of ACTIVE, DISABLED',
+            '    totalRamBytes: string (int64)',
+            '  nextPageToken: string',
+        ])
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -3,10 +3,18 @@
 import unittest
 import json
 
-from generator.lib.util import to_api_version, library_name, re_find_replacements, to_rust_type
+from generator.lib.util import (to_api_version, library_name, re_find_replacements, to_rust_type, identifier_newtype,
+                                paged_report_items_prop, to_json_schema_type, max_page_size, is_date_schema,
+                                method_const_name, method_required_params, filter_fields, snapshot_groups,
+                                constraint_checks, has_next_page_token, has_error_format_param,
+                                extra_properties_field, order_by_fields, page_items_prop)
 from .test_data.discovery_document import DISCOVERY_DOC
 
 
+class AttrDict(dict):
+    __getattr__ = dict.__getitem__
+
+
 class UtilsTest(unittest.TestCase):
 
     def test_to_version_ok(self):
@@ -59,6 +67,170 @@ class UtilsTest(unittest.TestCase):
         self.assertEqual(len(ms), 1)
         self.assertEqual(ms[0], '{+project}')
 
+    def test_identifier_newtype(self):
+        schemas = {'Album': {}, 'AlbumId': {}}
+        for name, param, want in (('deviceId', {'type': 'string'}, 'DeviceId'),
+                                  ('orgUnitId', {'type': 'string'}, 'OrgUnitId'),
+                                  ('albumId', {'type': 'string'}, None),
+                                  ('id', {'type': 'string'}, None),
+                                  ('Id', {'type': 'string'}, None),
+                                  ('pageSize', {'type': 'string'}, None),
+                                  ('ruleId', {'type': 'integer'}, None),
+                                  ('entityId', {'type': 'string', 'format': 'int64'}, None),
+                                  ('kindId', {'type': 'string', 'enum': ['A', 'B']}, None)):
+            p = AttrDict(param, name=name)
+            self.assertEqual(identifier_newtype(schemas, p), want, name)
+
+    def test_paged_report_items_prop(self):
+        token = {'type': 'string'}
+        total = {'type': 'integer', 'format': 'int32'}
+        items = {'type': 'array', 'items': {'$ref': 'Item'}}
+        for props, want in (({'nextPageToken': token, 'totalSize': total, 'items': items}, 'items'),
+                            ({'nextPageToken': token, 'totalSize': total, 'a': items, 'b': items}, None),
+                            ({'nextPageToken': token, 'items': items}, None),
+                            ({'totalSize': total, 'items': items}, None),
+                            ({'nextPageToken': token, 'totalSize': {'type': 'string'}, 'items': items}, None)):
+            self.assertEqual(paged_report_items_prop({'properties': props}), want, list(props))
+
+    def test_page_items_prop(self):
+        token = {'type': 'string'}
+        items = {'type': 'array', 'items': {'$ref': 'Item'}}
+        names = {'type': 'array', 'items': {'type': 'string'}}
+        for props, want in (({'nextPageToken': token, 'items': items}, 'items'),
+                            ({'nextPageToken': token, 'names': names}, 'names'),
+                            ({'nextPageToken': token, 'items': items, 'unreachable': names}, 'items'),
+                            ({'nextPageToken': token, 'a': items, 'b': items}, None),
+                            ({'nextPageToken': token, 'a': names, 'b': names}, None),
+                            ({'items': items}, None)):
+            self.assertEqual(page_items_prop({'properties': props}), want, list(props))
+
+    def test_has_next_page_token(self):
+        for props, want in (({'nextPageToken': {'type': 'string'}, 'items': {'type': 'array'}}, True),
+                            ({'nextPageToken': {'type': 'integer'}}, False),
+                            ({'pageToken': {'type': 'string'}}, False),
+                            ({}, False)):
+            self.assertEqual(has_next_page_token({'properties': props}), want, list(props))
+
+    def test_extra_properties_field(self):
+        any_value = {'type': 'any'}
+        for s, want in (({'properties': {'name': {'type': 'string'}}, 'additionalProperties': any_value}, 'extra'),
+                        ({'properties': {'extra': {'type': 'string'}}, 'additionalProperties': any_value}, 'extra_'),
+                        ({'properties': {'name': {'type': 'string'}}}, None),
+                        ({'additionalProperties': any_value}, None)):
+            self.assertEqual(extra_properties_field(s), want, s)
+
+    def test_order_by_fields(self):
+        def param(description):
+            return {'type': 'string', 'description': description}
+        for pn, p, want in (('orderBy', param('Field used to order results. Supported fields: * request_count * '
+                                              'latest_request_time'), ['request_count', 'latest_request_time']),
+                            ('orderBy', param('Comma separated list of fields to order by. Supported fields are: - '
+                                              '`create_time`: when it was created. - `name`: its name.'),
+                             ['create_time', 'name']),
+                            ('orderBy', param('Sorts list results by a certain order.'), []),
+                            ('filter', param('Supported fields: * request_count'), [])):
+            self.assertEqual(order_by_fields(pn, p), want, p['description'])
+
+    def test_is_date_schema(self):
+        i32 = {'type': 'integer', 'format': 'int32'}
+        for props, want in (({'year': i32, 'month': i32, 'day': i32}, True),
+                            ({'year': i32, 'month': i32}, False),
+                            ({'year': i32, 'month': i32, 'day': i32, 'hours': i32}, False),
+                            ({'year': i32, 'month': i32, 'day': {'type': 'string'}}, False)):
+            self.assertEqual(is_date_schema({'properties': props}), want, list(props))
+
+    def test_method_const_name(self):
+        for method_id, want in (('chromemanagement.customers.apps.countChromeAppRequests',
+                                 'CUSTOMERS_APPS_COUNT_CHROME_APP_REQUESTS'),
+                                ('storage.objects.get', 'OBJECTS_GET'),
+                                ('youtube.liveChatMessages.list', 'LIVE_CHAT_MESSAGES_LIST')):
+            self.assertEqual(method_const_name(method_id), want)
+
+    def test_method_required_params(self):
+        m = {'parameterOrder': ['bucket', 'object'],
+             'parameters': {'object': {'required': True}, 'bucket': {'required': True},
+                            'generation': {}, 'alt': {'required': False}}}
+        self.assertEqual(method_required_params(m), ['bucket', 'object'])
+        self.assertEqual(method_required_params({}), [])
+
+    def test_filter_fields(self):
+        schemas = {'ListResponse': {'properties': {'nextPageToken': {'type': 'string'},
+                                                   'items': {'type': 'array', 'items': {'$ref': 'Item'}}}},
+                   'Item': {'properties': {'appName': {'type': 'string'},
+                                           'installCount': {'type': 'integer', 'format': 'int32'},
+                                           'reportTime': {'type': 'string', 'format': 'google-datetime'},
+                                           'details': {'$ref': 'Details'},
+                                           'lastSeen': {'$ref': 'Date'}}},
+                   'Date': {'properties': {n: {'type': 'integer', 'format': 'int32'}
+                                           for n in ('year', 'month', 'day')}},
+                   'Details': {'properties': {}}}
+        m = {'response': {'$ref': 'ListResponse'}, 'parameters': {'filter': {'type': 'string'}}}
+        self.assertEqual(filter_fields(schemas, m), [('appName', 'String'), ('installCount', 'i32'),
+                                                     ('lastSeen', 'client::chrono::NaiveDate'),
+                                                     ('reportTime', 'client::chrono::DateTime<client::chrono::Utc>')])
+
+        m['parameters']['filter']['description'] = 'Supported filter fields: * app_name * last_active_date * user_id * total_count'
+        self.assertEqual(filter_fields(schemas, m), [('app_name', 'String'),
+                                                     ('last_active_date', 'client::chrono::NaiveDate'),
+                                                     ('user_id', 'String'), ('total_count', 'i64')])
+        self.assertEqual(filter_fields(schemas, {'parameters': {}}), [])
+
+    def test_snapshot_groups(self):
+        def count(extra=None, **kwargs):
+            params = {'customer': {'type': 'string', 'required': True}, 'orgUnitId': {'type': 'string'},
+                      'pageToken': {'type': 'string'}}
+            params.update(extra or {})
+            m = {'httpMethod': 'GET', 'parameters': params, 'response': {'$ref': 'Response'}}
+            m.update(kwargs)
+            return m
+        methods = {'reports.countVersions': count(),
+                   'reports.countDevices': count({'readMask': {'type': 'string'}}),
+                   'reports.findDevices': count(),
+                   'apps.countRequests': count(),
+                   'users.countUsers': count(),
+                   'users.countGroups': count(httpMethod='POST')}
+        c = AttrDict(rta_map={'customers': list(methods)}, rtc_map={'customers': 'api'},
+                     fqan_map={'api.customers.' + a: m for a, m in methods.items()})
+        self.assertEqual(snapshot_groups(c, 'customers'),
+                         [('reports', [('reports.countDevices', methods['reports.countDevices']),
+                                       ('reports.countVersions', methods['reports.countVersions'])],
+                           ['customer'], ['orgUnitId'])])
+
+        methods['reports.countDevices']['parameters']['customer']['type'] = 'integer'
+        self.assertEqual(snapshot_groups(c, 'customers'), [])
+
+    def test_max_page_size(self):
+        for p, want in (({'maximum': '500', 'description': 'Maximum value is 50.'}, 500),
+                        ({'description': 'Maximum number of results to return. Maximum and default are 50, '
+                                         'anything above will be coerced to 50.'}, 50),
+                        ({'description': 'Default value is 100. Maximum value is 1,000.'}, 1000),
+                        ({'description': 'The page size cannot exceed 200.'}, 200),
+                        ({'description': 'Maximum number of results to return.'}, None),
+                        ({}, None)):
+            self.assertEqual(max_page_size(p), want, p)
+
+    def test_has_error_format_param(self):
+        self.assertTrue(has_error_format_param(json.loads(DISCOVERY_DOC)['parameters']))
+        self.assertFalse(has_error_format_param({'key': {'type': 'string'}}))
+        self.assertFalse(has_error_format_param(None))
+
+    def test_constraint_checks(self):
+        check = lambda p: constraint_checks(p, 'path', 'value', 'errors')
+        self.assertEqual(check({'type': 'string', 'enum': ['A', 'B'], 'pattern': '[a-z\\.]+'}),
+                         ['client::validate::check_enum(path, value, &["A", "B"], errors);',
+                          'client::validate::check_pattern(path, value, "[a-z\\\\.]+", errors);'])
+        self.assertEqual(check({'type': 'array', 'items': {'type': 'string', 'enum': ['A']}}),
+                         ['client::validate::check_enum(path, value, &["A"], errors);'])
+        self.assertEqual(check({'type': 'integer', 'format': 'uint32', 'minimum': '1', 'maximum': '500'}),
+                         ['client::validate::check_range(path, value, Some(1), Some(500), errors);'])
+        self.assertEqual(check({'type': 'number', 'format': 'double', 'minimum': '0'}),
+                         ['client::validate::check_range(path, value, Some(0.0), None, errors);'])
+        for p in ({'type': 'integer', 'format': 'uint32', 'minimum': '-1'},
+                  {'type': 'string', 'format': 'int64', 'pattern': '[0-9]+'},
+                  {'type': 'array', 'items': {'$ref': 'Schema'}},
+                  {'type': 'string'}):
+            self.assertEqual(check(p), [], p)
+
     def test_to_rust_type(self):
         full_api_schema = json.loads(DISCOVERY_DOC)
 
@@ -106,6 +278,21 @@ class UtilsTest(unittest.TestCase):
         rust_type = to_rust_type(schemas, class_name, property_name, property_value, allow_optionals=True)
         self.assertEqual(rust_type, 'Option<Vec<HashMap<String, json::Value>>>')
 
+    def test_to_json_schema_type(self):
+        for t, want in (({'type': 'string', 'format': 'int64'}, ('Option<String>', True)),
+                        ({'type': 'array', 'items': {'type': 'string', 'format': 'byte'}}, ('Option<Vec<String>>', True)),
+                        ({'type': 'object', 'additionalProperties': {'type': 'string', 'format': 'google-duration'}},
+                         ('Option<HashMap<String, String>>', True)),
+                        ({'type': 'string', 'format': 'google-fieldmask'}, ('Option<String>', True)),
+                        ({'type': 'string', 'format': 'date-time'},
+                         ('Option<client::chrono::DateTime<client::chrono::offset::Utc>>', False)),
+                        ({'type': 'integer', 'format': 'int32'}, ('Option<i32>', False))):
+            rust_type, changed = to_json_schema_type({}, 'Schema', 'property', t)
+            self.assertEqual((str(rust_type), changed), want, t)
+        rust_type, changed = to_json_schema_type({}, 'Schema', 'property', {'type': 'string', 'format': 'uint64'},
+                                                 allow_optionals=False)
+        self.assertEqual((str(rust_type), changed), ('String', True))
+
 
 def main():
     unittest.main()
@@ -19,7 +19,46 @@ VALUE_ARG = 'v'
 KEY_VALUE_ARG = 'kv'
 SCOPE_FLAG = 'scope'
 CONFIG_DIR_FLAG = 'config-dir'
+CONFIG_FILE_FLAG = 'config-file'
 DEBUG_FLAG = 'debug'
+RAW_ERRORS_FLAG = 'raw-errors'
+DRY_RUN_FLAG = 'dry-run'
+VALIDATE_ONLY_FLAG = 'validate-only'
+STRICT_FLAG = 'strict'
+ASSUME_YES_FLAG = 'assume-yes'
+ASSUME_YES_SHORT_FLAG = 'y'
+INTERACTIVE_FLAG = 'interactive'
+VERBOSE_FLAG = 'verbose'
+VERBOSE_SHORT_FLAG = 'v'
+TIMING_FLAG = 'timing'
+RECORD_FLAG = 'record'
+REPLAY_FLAG = 'replay'
+SERVE_FLAG = 'serve'
+SERVE_ALLOW_REMOTE_FLAG = 'serve-allow-remote'
+OUTPUT_SCHEMA_FLAG = 'output-schema'
+ADDRESS_FAMILY_FLAG = 'address-family'
+HAPPY_EYEBALLS_FLAG = 'happy-eyeballs-timeout'
+MAX_RETRIES_FLAG = 'max-retries'
+ALL_PAGES_FLAG = 'all-pages'
+STATE_FILE_FLAG = 'state-file'
+RESUME_FLAG = 'resume'
+CONTINUE_FROM_FLAG = 'continue-from'
+STREAM_OUTPUT_FLAG = 'stream-output'
+MAX_PAGES_FLAG = 'max-pages'
+SPLIT_PAGES_FLAG = 'output-split-pages'
+SORT_BY_FLAG = 'sort-by'
+WHERE_FLAG = 'where'
+ONLY_FIELDS_FLAG = 'only-fields'
+STRIP_FIELDS_FLAG = 'strip-fields'
+PROFILE_FLAG = 'profile'
+SELECT_PROFILE_FLAG = 'select-profile-interactive'
+CONCURRENCY_FLAG = 'concurrency'
+REPORT_FORMAT_FLAG = 'report-format'
+ON_ERROR_FLAG = 'on-error'
+HUMAN_FLAG = 'human'
+HUMAN_SHORT_FLAG = 'H'
+OUTPUT_FORMAT_FLAG = 'output-format'
+ERROR_FORMAT_FLAG = 'error-format'
 DEFAULT_MIME = 'application/octet-stream'
 
 MODE_ARG = 'mode'
@@ -27,14 +66,37 @@ FILE_ARG = 'file'
 FILE_FLAG = 'f'
 MIME_ARG = 'mime'
 MIME_FLAG = 'm'
+MIME_LONG_FLAG = 'upload-mime'
 OUT_ARG = 'out'
 
 SCOPE_ARG = 'url'
 CONFIG_DIR_ARG = 'folder'
+CONFIG_FILE_ARG = 'config-path'
+STATE_FILE_ARG = 'state-path'
+CONTINUE_FROM_ARG = 'response-path'
+PROFILE_ARG = 'name'
+CONCURRENCY_ARG = 'limit'
+REPORT_FORMAT_ARG = 'format'
+ON_ERROR_ARG = 'policy'
+MAX_PAGES_ARG = 'pages'
+SPLIT_PAGES_ARG = 'pages-dir'
+SORT_BY_ARG = 'field-path'
+WHERE_ARG = 'expression'
+RECORD_ARG = 'fixtures-dir'
+REPLAY_ARG = 'replay-dir'
+SERVE_ARG = 'listen-addr'
+ADDRESS_FAMILY_ARG = 'family'
+HAPPY_EYEBALLS_ARG = 'milliseconds'
+MAX_RETRIES_ARG = 'retries'
+ONLY_FIELDS_ARG = 'kept-paths'
+STRIP_FIELDS_ARG = 'stripped-paths'
+OUTPUT_FORMAT_ARG = 'style'
+ERROR_FORMAT_ARG = 'errors-version'
 
 FIELD_SEP = '.'
 
 CONFIG_DIR = '~/.google-service-cli'
+DEFAULT_CONCURRENCY = 8
 
 POD_TYPES = set(('boolean', 'integer', 'number', 'uint32', 'double', 'float', 'int32', 'int64', 'uint64', 'string'))
 
@@ -89,14 +151,93 @@ def new_method_context(resource, method, c):
     return MethodContext(m, response_schema, params, request_value, media_params,
                          required_props, optional_props, part_prop)
 
+# Returns the optional page-token parameter of methods returning paged results, or None.
+# Methods dealing with media are never considered paged.
+def page_token_param(mc):
+    if mc.media_params or mc.m.get('supportsMediaDownload', False) or not mc.response_schema:
+        return None
+    if 'nextPageToken' not in mc.response_schema.get('properties', dict()):
+        return None
+    for p in mc.optional_props:
+        if p.name == 'pageToken':
+            return p
+    return None
+
+# Returns the optional page-size parameter of paged methods along with its documented maximum, or (None, None)
+# if either is unknown.
+def page_size_param(mc):
+    if not page_token_param(mc):
+        return None, None
+    for p in mc.optional_props:
+        if p.name in ('pageSize', 'maxResults'):
+            max_size = util.max_page_size(p)
+            if max_size is not None:
+                return p, max_size
+    return None, None
+
+# Returns the only required parameter of get-style methods, which can be called once per name read from a file
+# to fan out the call, or None.
+def fan_out_param(mc):
+    if mc.m.get('httpMethod') != 'GET' or mc.request_value or mc.media_params or not mc.response_schema:
+        return None
+    if mc.m.get('supportsMediaDownload', False) or page_token_param(mc):
+        return None
+    if len(mc.required_props) != 1:
+        return None
+    p = mc.required_props[0]
+    if p.get('type') != 'string' or p.get('repeated', False):
+        return None
+    return p
+
+# Returns True for count methods, like 'countChromeVersions', whose response can be written in a report format
+def is_report_method(mc):
+    if mc.media_params or mc.m.get('supportsMediaDownload', False) or not mc.response_schema:
+        return False
+    return mc.m.id.split('.')[-1].startswith('count')
+
+# Returns True for methods which delete or otherwise destroy data, like 'delete', 'batchDelete' or 'purge',
+# and which should only be called after asking for confirmation
+def is_destructive_method(mc):
+    if mc.m.get('httpMethod') == 'DELETE':
+        return True
+    name = mc.m.id.split('.')[-1]
+    return any(name.startswith(prefix) for prefix in ('delete', 'batchDelete', 'purge'))
+
+# Returns the prefix of the names of all metrics reported by the CLI of the given API, like 'chrome_management'
+def metric_prefix(canonical_name):
+    return re.sub(r'[^a-z0-9]+', '_', canonical_name.lower()).strip('_')
+
+# Returns True if the predicate returns a truthy value for the context of any method of the API
+def any_method_context(c, predicate):
+    for resource in c.rta_map.keys():
+        for method in c.rta_map[resource]:
+            if predicate(new_method_context(resource, method, c)):
+                return True
+    return False
+
+# Returns True if any method of the API returns paged results
+def has_paged_methods(c):
+    return any_method_context(c, page_token_param)
+
+# Returns True if any method of the API is a count method
+def has_report_methods(c):
+    return any_method_context(c, is_report_method)
+
+# Returns True if any method of the API is destructive
+def has_destructive_methods(c):
+    return any_method_context(c, is_destructive_method)
+
+# Returns True if any method of the API can fan out calls
+def has_fan_out_methods(c):
+    return any_method_context(c, fan_out_param)
+
+# Returns True if any method of the API may make more than one call
+def has_multi_call_methods(c):
+    return any_method_context(c, lambda mc: page_token_param(mc) or fan_out_param(mc))
+
 def comma_sep_fields(fields):
     return ', '.join('"%s"' % mangle_subcommand(f) for f in sorted(fields))
 
-# Returns a string representing a string-vector of mangled names
-# fields is an iterator
-def field_vec(fields):
-    return "vec![%s]" % comma_sep_fields(fields)
-
 def pretty(n):
     return ' '.join(s.capitalize() for s in mangle_subcommand(n).split('-'))
 
@@ -144,6 +285,12 @@ def docopt_mode(protocols):
 def actual_json_type(name, type):
     return type
 
+# return the name of the JsonType variant a field entry is parsed as, with durations parsed from a form like 1h30m
+def json_type_enum(name, fe):
+    if fe.actual_property.get('format') == 'google-duration':
+        return 'Duration'
+    return JSON_TYPE_TO_ENUM_MAP[actual_json_type(name, fe.actual_property.type)]
+
 # return a string representing property `p` suitable for docopt argument parsing
 def to_docopt_arg(p):
     return '<%s>%s' % (mangle_subcommand(p.name), p.get('repeated', False) and '...' or '')
@@ -222,6 +369,50 @@ def cli_schema_to_yaml(schema, prefix=''):
     return o
 
 
+# Returns the structure of the given schema as lines of json field names and their types, with the fields of nested
+# structures indented below theirs. Each schema is only expanded once, later uses refer to it.
+def schema_outline(c, schema):
+    return [schema.id] + _schema_outline_fields(c, schema, '  ', {schema.id})
+
+
+def _schema_outline_fields(c, s, indent, seen):
+    lines = list()
+    for pn, p in sorted(util.items(s.get('properties', dict()))):
+        t, nested = _schema_outline_type(c, p)
+        nid = nested is not None and nested.get('id')
+        if nid and nid in seen:
+            t += ' (see above)'
+            nested = None
+        lines.append('%s%s: %s' % (indent, pn, t))
+        if nested is not None:
+            if nid:
+                seen.add(nid)
+            lines.extend(_schema_outline_fields(c, nested, indent + '  ', seen))
+    # end for each property
+    return lines
+
+
+# Returns the type of the given property, and the schema whose fields are nested below it, if any
+def _schema_outline_type(c, p):
+    if util.TREF in p:
+        s = c.schemas[p[util.TREF]]
+        return s.id, s
+    if p.get('type') == 'array':
+        t, nested = _schema_outline_type(c, p.get('items', dict()))
+        return '[%s]' % t, nested
+    if util.is_map_prop(p):
+        t, nested = _schema_outline_type(c, p.additionalProperties)
+        return '{ string: %s }' % t, nested
+    if 'properties' in p:
+        return 'object', p
+    t = p.get('type', 'any')
+    if 'format' in p:
+        t += ' (%s)' % p.format
+    if 'enum' in p:
+        t += ', one of %s' % ', '.join(p.enum)
+    return t, None
+
+
 # Return a value string suitable for the given field.
 def field_to_value(f):
     v = JSON_TYPE_RND_MAP[f.actual_property.type]()
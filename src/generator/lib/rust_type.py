@@ -39,12 +39,43 @@ class RustType:
             Base("client::chrono::Duration"): Base("::client::serde::duration::Wrapper"),
             Base("i64"): Base("::client::serde_with::DisplayFromStr"),
             Base("u64"): Base("::client::serde_with::DisplayFromStr"),
+            Base("i32"): Base("::client::serde::number_or_string::Wrapper"),
+            Base("u32"): Base("::client::serde::number_or_string::Wrapper"),
         }
 
         changed = copied.serde_replace_inner_ty(from_to)
 
         return copied, changed
 
+    def json_schema_as(self) -> Tuple["RustType", bool]:
+        """Create a type which describes the JSON representation of this type to schemars, for use with
+        `#[schemars(with = "...")]`. Unlike with serde_as, unchanged members are kept as they are.
+        Returns true if the type changes.
+        """
+        string = Base("String")
+        from_to = {
+            Vec(Base("u8")): string,
+            Base("client::chrono::Duration"): string,
+            Base("client::FieldMask"): string,
+            Base("i64"): string,
+            Base("u64"): string,
+        }
+        if self in from_to:
+            return from_to[self], True
+
+        copied = deepcopy(self)
+        return copied, copied._json_schema_replace_inner_ty(from_to)
+
+    def _json_schema_replace_inner_ty(self, from_to) -> bool:
+        changed = False
+        for i, member in enumerate(self.members or []):
+            if member in from_to:
+                self.members[i] = from_to[member]
+                changed = True
+            elif member._json_schema_replace_inner_ty(from_to):
+                changed = True
+        return changed
+
     def __str__(self):
         if self.members:
             return f"{self.name}<{', '.join(str(m) for m in self.members)}>"
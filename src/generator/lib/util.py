@@ -18,6 +18,7 @@ re_desc_parts = re.compile(
     flags=re.IGNORECASE | re.MULTILINE)
 
 re_find_replacements = re.compile(r"\{[/\+]?\w+\*?\}")
+re_identifier_param = re.compile(r"^[a-z][a-zA-Z0-9]*[a-z0-9]Id$")
 re_relative_links = re.compile(r"\]\s*\([^h]")
 
 HTTP_METHODS = set(("OPTIONS", "GET", "POST", "PUT", "DELETE", "HEAD", "TRACE", "CONNECT", "PATCH"))
@@ -50,6 +51,8 @@ SPACES_PER_TAB = 4
 
 NESTED_TYPE_SUFFIX = 'item'
 DELEGATE_TYPE = 'client::Delegate'
+DELEGATE_TYPE_PARAM = 'D'
+DEFAULT_DELEGATE_TYPE = 'client::DefaultDelegate'
 REQUEST_PRIORITY = 100
 REQUEST_MARKER_TRAIT = 'client::RequestValue'
 RESPONSE_MARKER_TRAIT = 'client::ResponseResult'
@@ -59,6 +62,12 @@ METHODS_BUILDER_MARKER_TRAIT = 'client::MethodsBuilder'
 PART_MARKER_TRAIT = 'client::Part'
 NESTED_MARKER_TRAIT = 'client::NestedType'
 REQUEST_VALUE_PROPERTY_NAME = 'request'
+# the query parameter naming the project billed for accessing a resource
+USER_PROJECT_PARAM = 'userProject'
+# the query parameter taking a filter expression
+FILTER_PARAM = 'filter'
+# the global parameter selecting the format of errors
+ERROR_FORMAT_PARAM = '$.xgafv'
 DELEGATE_PROPERTY_NAME = 'delegate'
 TO_PARTS_MARKER = 'client::ToParts'
 UNUSED_TYPE_MARKER = 'client::UnusedType'
@@ -381,6 +390,82 @@ def is_map_prop(p):
     return 'additionalProperties' in p
 
 
+# Returns the name of the field keeping the keys of an object schema which aren't among its properties, or None.
+# Only schemas with both properties and additionalProperties have one, as those without properties are maps already.
+def extra_properties_field(s):
+    if not s.get('properties') or 'additionalProperties' not in s:
+        return None
+    fn = 'extra'
+    while fn in (mangle_ident(pn) for pn in s['properties']):
+        fn += '_'
+    return fn
+
+
+# Returns True if the schema is a page of results, which has a nextPageToken string to fetch the next page with.
+def has_next_page_token(s):
+    return s.get('properties', dict()).get('nextPageToken', dict()).get('type') == 'string'
+
+
+# Returns the name of the items property of a schema which is a page of a list, or None.
+# Such a schema has a nextPageToken and a single array, or a single array of objects besides arrays
+# of plain values, like the names of unreachable locations.
+def page_items_prop(s):
+    props = s.get('properties', dict())
+    if not has_next_page_token(s):
+        return None
+    arrays = [pn for pn, p in props.items() if p.get('type') == 'array']
+    if len(arrays) != 1:
+        arrays = [pn for pn in arrays if TREF in props[pn].get('items', dict())]
+    if len(arrays) != 1:
+        return None
+    return arrays[0]
+
+
+# Returns the name of the items property of a schema which is a page of a report, or None.
+# Such a schema has a nextPageToken, an integer totalSize, and exactly one array of items.
+def paged_report_items_prop(s):
+    props = s.get('properties', dict())
+    if not has_next_page_token(s):
+        return None
+    if props.get('totalSize', dict()).get('type') not in ('integer', 'string') or \
+       props.get('totalSize').get('format') not in ('int32', 'int64', 'uint32', 'uint64'):
+        return None
+    arrays = [pn for pn, p in props.items() if p.get('type') == 'array']
+    if len(arrays) != 1:
+        return None
+    return arrays[0]
+
+
+# Returns True if the schema is a calendar date made of an int32 year, month and day, like google.type.Date.
+def is_date_schema(s):
+    props = s.get('properties', dict())
+    if set(props.keys()) != {'year', 'month', 'day'}:
+        return False
+    return all(p.get('type') == 'integer' and p.get('format') == 'int32' for p in props.values())
+
+
+_MAX_PAGE_SIZE_RE = re.compile(
+    r'(?:maximum(?: value| page size| allowed value)?(?: and default)? (?:is|are|of)|'
+    r'(?:cannot|may not|must not) (?:be greater than|be larger than|exceed)|at most)\s+(\d[\d,]*)',
+    re.IGNORECASE)
+
+
+# Returns the maximum value of a page size parameter as documented in the discovery document, or None.
+# It is taken from the 'maximum' field if present, or from the description otherwise.
+def max_page_size(p):
+    if 'maximum' in p:
+        return int(p['maximum'])
+    match = _MAX_PAGE_SIZE_RE.search(p.get('description', ''))
+    if match is None:
+        return None
+    return int(match.group(1).replace(',', ''))
+
+
+# Returns True if the API selects the format of its errors with a global parameter, which the hub then sets.
+def has_error_format_param(parameters):
+    return bool(parameters) and ERROR_FORMAT_PARAM in parameters
+
+
 def _assure_unique_type_name(schemas, tn):
     if tn in schemas:
         tn += 'Nested'
@@ -413,6 +498,17 @@ def to_serde_type(
     return to_rust_type_inner(schemas, schema_name, property_name, t, allow_optionals, _is_recursive).serde_as()
 
 
+def to_json_schema_type(
+        schemas,
+        schema_name,
+        property_name,
+        t,
+        allow_optionals=True,
+        _is_recursive=False
+) -> Tuple[RustType, bool]:
+    return to_rust_type_inner(schemas, schema_name, property_name, t, allow_optionals, _is_recursive).json_schema_as()
+
+
 def to_rust_type_inner(
         schemas,
         schema_name,
@@ -589,6 +685,220 @@ def to_fqan(name, resource, method):
     return '%s.%s.%s' % (name, resource, method)
 
 
+# chromemanagement.customers.apps.countChromeAppRequests -> CUSTOMERS_APPS_COUNT_CHROME_APP_REQUESTS
+def method_const_name(method_id):
+    name = '_'.join(camel_to_under(t) for t in method_id.split('.')[1:])
+    return re.sub('[^a-z0-9_]', '_', name).upper()
+
+
+_UNSIGNED_FORMATS = ('uint32', 'uint64')
+_INTEGER_FORMATS = ('int32', 'int64') + _UNSIGNED_FORMATS
+_FLOAT_FORMATS = ('float', 'double')
+
+
+# Returns v as a Rust string literal.
+def rust_str_literal(v):
+    return '"%s"' % v.replace('\\', '\\\\').replace('"', '\\"').replace('\n', '\\n')
+
+
+# Returns the Rust literal of a minimum or maximum of a number with the given format, or None if it doesn't fit.
+def _bound_literal(v, fmt):
+    if v is None:
+        return None
+    try:
+        if fmt in _FLOAT_FORMATS:
+            return repr(float(v))
+        n = int(v)
+    except ValueError:
+        return None
+    if n < 0 and fmt in _UNSIGNED_FORMATS:
+        return None
+    return str(n)
+
+
+# Returns the calls checking the constraints the discovery document gives for the values of property p, which
+# are at the path `path` and accessible as `value`, both Rust expressions. Violations are pushed onto `errors`.
+# Only values stored as strings or numbers are checked, which may also be contained in arrays and maps.
+def constraint_checks(p, path, value, errors):
+    t = p
+    while TREF not in t:
+        if t.get('type') == 'array' and 'items' in t:
+            t = t['items']
+        elif t.get('type') == 'object' and is_map_prop(t):
+            t = t['additionalProperties']
+        else:
+            break
+    if TREF in t:
+        return []
+    checks = []
+    fmt = t.get('format')
+    if t.get('type') == 'string' and fmt is None:
+        if t.get('enum'):
+            checks.append('client::validate::check_enum(%s, %s, &[%s], %s);'
+                          % (path, value, ', '.join(rust_str_literal(v) for v in t['enum']), errors))
+        if t.get('pattern'):
+            checks.append('client::validate::check_pattern(%s, %s, %s, %s);'
+                          % (path, value, rust_str_literal(t['pattern']), errors))
+    elif fmt in _INTEGER_FORMATS + _FLOAT_FORMATS:
+        bounds = [_bound_literal(t.get(k), fmt) for k in ('minimum', 'maximum')]
+        if any(bounds):
+            checks.append('client::validate::check_range(%s, %s, %s, %s);'
+                          % (path, value, ', '.join(b and 'Some(%s)' % b or 'None' for b in bounds), errors))
+    return checks
+
+
+# Returns the fields results can be ordered by, as listed by the description of an orderBy parameter p, like
+# 'Supported fields: * request_count * latest_request_time', or an empty list if it lists none.
+def order_by_fields(pn, p):
+    if pn not in ('orderBy', 'order_by') or p.get('type') != 'string':
+        return []
+    m = re.search(r'Supported (?:order by )?fields(?: are)?:((?:\s*[-*]\s*`?[\w.]+`?(?::[^-*]*)?)+)',
+                  p.get('description', ''))
+    if not m:
+        return []
+    return re.findall(r'[-*]\s*`?([\w.]+)', m.group(1))
+
+
+# Returns the properties of the struct generated for schema s, or an empty dict if it isn't a struct with fields.
+def schema_struct_properties(s):
+    if s.get('type') == 'object':
+        return s.get('properties', dict())
+    if s.get('type') == 'array' and s.get('items', dict()).get('type') == 'object':
+        return s['items'].get('properties', dict())
+    return dict()
+
+
+# Returns the names of the types a Rust type is made of, leaving out those given by path like client::FieldMask.
+def _type_names(rust_type):
+    return re.findall(r'(?<![:\w])\w+(?![:\w])', rust_type)
+
+
+# Returns the ids of all used schemas which implement client::Validate, as values of their fields have constraints
+# or are schemas implementing it themselves.
+def validated_schemas(c):
+    structs = dict((s.id, s) for s in c.schemas.values()
+                   if schema_struct_properties(s) and UNUSED_TYPE_MARKER not in schema_markers(s, c, transitive=True))
+    validated = set(sid for sid, s in structs.items()
+                    if any(constraint_checks(p, '', '', '') for _, p in items(schema_struct_properties(s))))
+    refs = dict((sid, set(tn for pn, p in items(schema_struct_properties(s))
+                          for tn in _type_names(to_rust_type(c.schemas, sid, pn, p)) if tn in structs))
+                for sid, s in structs.items())
+    grown = True
+    while grown:
+        grown = False
+        for sid in structs:
+            if sid not in validated and refs[sid] & validated:
+                validated.add(sid)
+                grown = True
+    return validated
+
+
+# Returns the id of the schema implementing client::Validate which property pn of schema s refers to, possibly
+# through arrays or maps, or None.
+def validated_property_schema(schemas, s, pn, p, validated):
+    for tn in _type_names(to_rust_type(schemas, s.id, pn, p)):
+        if tn in validated:
+            return tn
+    return None
+
+
+# Returns the names of the required parameters of the method, in the order given by the discovery document.
+def method_required_params(m):
+    params = m.get('parameters', dict())
+    order = [pn for pn in m.get('parameterOrder', list()) if pn in params]
+    order += sorted(pn for pn in params if pn not in order)
+    return [pn for pn in order if params[pn].get('required', False)]
+
+
+_SUPPORTED_FILTER_FIELDS_RE = re.compile(r'Supported filter fields:\s*((?:[*-]\s*\w+\s*)+)')
+_CHRONO_TIMESTAMP = 'client::chrono::DateTime<client::chrono::Utc>'
+_CHRONO_DATE = 'client::chrono::NaiveDate'
+
+
+# Returns the rust type of the values a filter field can be compared with, or None if it can't be compared.
+# The property p describes the field, or is None if it isn't part of the filtered schema, in which case the type
+# is guessed from the name.
+def _filter_value_type(schemas, name, p):
+    if p is None:
+        if name.endswith('date'):
+            return _CHRONO_DATE
+        if name.endswith('time') or name.endswith('timestamp'):
+            return _CHRONO_TIMESTAMP
+        if name.endswith('count') or name.startswith('number_of'):
+            return 'i64'
+        return 'String'
+    if '$ref' in p:
+        return _CHRONO_DATE if is_date_schema(schemas.get(p['$ref'], dict())) else None
+    fmt = p.get('format')
+    if p.get('type') == 'string':
+        return {'google-datetime': _CHRONO_TIMESTAMP, 'date-time': _CHRONO_TIMESTAMP, 'date': _CHRONO_DATE,
+                'int64': 'i64', 'uint64': 'u64'}.get(fmt, 'String')
+    if p.get('type') == 'integer':
+        return {'uint32': 'u32'}.get(fmt, 'i32')
+    return {'number': 'f64', 'boolean': 'bool'}.get(p.get('type'))
+
+
+# Returns the fields the method can be filtered by as (name, rust type) pairs, or an empty list if the method has
+# no filter parameter. The fields are those listed as supported in the description of the parameter, or the scalar
+# fields of the filtered resource otherwise, which is the item type of the only list in the response.
+def filter_fields(schemas, m):
+    fp = m.get('parameters', dict()).get(FILTER_PARAM)
+    if not fp or fp.get('type') != 'string' or fp.get('repeated', False):
+        return list()
+    rs = schemas.get(m.get('response', dict()).get('$ref'), dict())
+    lists = [p for _, p in items(rs.get('properties', dict()))
+             if p.get('type') == 'array' and '$ref' in p.get('items', dict())]
+    if len(lists) == 1:
+        rs = schemas.get(lists[0]['items']['$ref'], dict())
+    props = rs.get('properties', dict())
+
+    match = _SUPPORTED_FILTER_FIELDS_RE.search(fp.get('description', ''))
+    if match:
+        names = re.findall(r'[*-]\s*(\w+)', match.group(1))
+        fields = [(n, _filter_value_type(schemas, n, props.get(n, props.get(_to_lower_camel(n))))) for n in names]
+        return [(n, t or 'String') for n, t in fields]
+    fields = [(pn, _filter_value_type(schemas, pn, p)) for pn, p in sorted(items(props))]
+    return [(n, t) for n, t in fields if t is not None]
+
+
+# Returns the groups of report methods of the resource which can be fetched at once, as (sub-resource, activities,
+# required params, shared optional params) tuples. A group is made of at least two `count*` methods of the same
+# sub-resource, like `reports.countChromeVersions`, which are plain GET requests with a response and share the same
+# required string parameters. Optional string parameters all of them have in common, except for those of paging,
+# can be set once for the whole group.
+def snapshot_groups(c, resource):
+    groups = dict()
+    for a in c.rta_map.get(resource, list()):
+        tokens = a.split('.')
+        if len(tokens) != 2 or not tokens[1].startswith('count'):
+            continue
+        m = c.fqan_map[to_fqan(c.rtc_map[resource], resource, a)]
+        if (m.get('httpMethod') != 'GET' or 'request' in m or 'response' not in m
+                or m.get('supportsMediaDownload', False)):
+            continue
+        groups.setdefault(tokens[0], list()).append((a, m))
+
+    res = list()
+    for sub, activities in sorted(groups.items()):
+        if len(activities) < 2 or sub in c.rta_map[resource]:
+            continue
+        required = method_required_params(activities[0][1])
+        if any(method_required_params(m) != required for _, m in activities):
+            continue
+        string_params = [{pn for pn, p in m.get('parameters', dict()).items()
+                          if p.get('type') == 'string' and not p.get('repeated', False)} for _, m in activities]
+        if not all(set(required) <= names for names in string_params):
+            continue
+        optional = sorted(set.intersection(*string_params) - set(required) - {'pageToken'})
+        res.append((sub, sorted(activities, key=lambda am: am[0]), required, optional))
+    return res
+
+
+def _to_lower_camel(name):
+    tokens = name.split('_')
+    return tokens[0] + ''.join(capitalize(t) for t in tokens[1:])
+
+
 # videos -> Video
 def activity_name_to_type_name(an):
     return canonical_type_name(an)[:-1]
@@ -725,14 +1035,49 @@ def method_media_params(m):
     return res
 
 
+# 'deviceId' -> 'DeviceId' if the given parameter is an opaque string identifier which can be
+# represented by its own newtype, or None otherwise. Names clashing with schemas are not used.
+def identifier_newtype(schemas, p):
+    if p.get('type') != 'string' or 'format' in p or 'enum' in p:
+        return None
+    if not re_identifier_param.match(p.name):
+        return None
+    tn = canonical_type_name(p.name)
+    if tn in schemas:
+        return None
+    return tn
+
+
+# return a sorted list of all identifier newtype names used by the parameters of all methods
+def identifier_newtypes(c):
+    res = set()
+    for m in c.fqan_map.values():
+        for p in _method_params(m):
+            tn = identifier_newtype(c.schemas, p)
+            if tn:
+                res.add(tn)
+    return sorted(res)
+
+
 # Build all parameters used in a given method !
 # schemas, context, method(dict), 'request'|'response', request_prop_name -> (params, request_value|None)
-def build_all_params(c, m):
+# If newtype_identifiers is set, identifier parameters are passed in using their newtype, see identifier_newtype()
+def build_all_params(c, m, newtype_identifiers=False):
     request_value = method_request(c, m)
     params = _method_params(m)
+    if newtype_identifiers:
+        for p in params:
+            tn = identifier_newtype(c.schemas, p)
+            if tn is None:
+                continue
+            p.newtype = tn
+            p.input_type = 'impl Into<%s>' % tn
+            p.clone_value = '{}.into().0'
+        # end for each identifier param
     if request_value:
         params.insert(0, schema_to_required_property(request_value, REQUEST_VALUE_PROPERTY_NAME))
     # add the delegate. It's a type parameter, which has to remain in sync with the type-parameters we actually build.
+    # Its setter takes a trait object, while the builder stores it as type parameter, see mb_type_params_s().
     dp = type(m)({'name': DELEGATE_PROPERTY_NAME,
                   TREF: "&'a mut dyn %s" % DELEGATE_TYPE,
                   'input_type': "&'a mut dyn %s" % DELEGATE_TYPE,
@@ -1047,9 +1392,15 @@ def struct_type_bounds_s():
     return ', '.join(tp + ": 'a" for tp in HUB_TYPE_PARAMETERS)
 
 
-# type params for the given method builder, as string suitable for Rust code
+# type params for the given method builder, as string suitable for Rust code. The delegate is a type parameter
+# to allow calling it without dynamic dispatch.
 def mb_type_params_s(m):
-    return _to_type_params_s(_rb_type_params)
+    return _to_type_params_s(_rb_type_params + (DELEGATE_TYPE_PARAM,))
+
+
+# as mb_type_params_s, but including defaults, for use in the definition of the method builder
+def mb_type_params_def_s(m):
+    return _to_type_params_s(_rb_type_params + ('%s = %s' % (DELEGATE_TYPE_PARAM, DEFAULT_DELEGATE_TYPE),))
 
 
 # as rb_additional_type_params, but for an individual method, as seen from a resource builder !
@@ -1062,6 +1413,11 @@ def mb_type(r, m):
     return "%s%sCall" % (singular(canonical_type_name(r)), dot_sep_to_canonical_type_name(m))
 
 
+# customers, reports -> CustomerReportSnapshot
+def snapshot_type(r, sub):
+    return "%s%sSnapshot" % (singular(canonical_type_name(r)), dot_sep_to_canonical_type_name(sub))
+
+
 # canonicalName = util.canonical_name()
 def hub_type(schemas, canonicalName):
     name = canonical_type_name(canonicalName)
@@ -37,6 +37,7 @@ itertools = "^ 0.10"
 google-clis-common = { path = "../../google-clis-common", version = "6.0" }
 % else:
 google-apis-common = { path = "../../google-apis-common", version = "6.0" }
+schemars = { version = "0.8", features = ["chrono"], optional = true }
 % endif
 % for dep in cargo.get('dependencies', list()):
 ${dep}
@@ -60,5 +61,6 @@ version = "${util.crate_version()}"
 % if not cargo.get("is_executable", False):
 [features]
 yup-oauth2 = ["google-apis-common/yup-oauth2"]
+jsonschema = ["dep:schemars"]
 default = ["yup-oauth2"]
 % endif
@@ -7,9 +7,12 @@
     from generator.lib.util import (new_context, rust_comment, rust_doc_comment, rust_module_doc_comment,
                       rb_type, hub_type, mangle_ident, hub_type_params_s,
                       rb_type_params_s, find_fattest_resource, HUB_TYPE_PARAMETERS, METHODS_RESOURCE,
-                      UNUSED_TYPE_MARKER, schema_markers)
+                      UNUSED_TYPE_MARKER, schema_markers, to_fqan, method_const_name,
+                      method_required_params, enclose_in, filter_fields, camel_to_under, snapshot_groups,
+                      validated_schemas, identifier_newtypes, has_error_format_param, ERROR_FORMAT_PARAM)
 
     c = new_context(schemas, resources)
+    validated = validated_schemas(c)
     hub_type = hub_type(c.schemas, util.canonical_name())
     ht_params = hub_type_params_s()
 
@@ -39,6 +42,7 @@ use crate::{client, client::GetToken, client::serde_with};
 
 ${lib.scope_enum()}
 
+${lib.identifier_newtypes(c)}\
 
 // ########
 // HUB ###
@@ -60,19 +64,88 @@ pub struct ${hub_type}${ht_params} {
     _user_agent: String,
     _base_url: String,
     _root_url: String,
+    _quota_project: Option<String>,
+    _revision_max_age: Option<client::chrono::Duration>,
+    _default_timeout: Option<std::time::Duration>,
+    _on_retry: Option<client::retry::RetryHook>,
+    _max_retries: Option<u32>,
+    _retry_policy: Option<client::retry::Backoff>,
+    _rate_limiter: Option<client::rate::RateLimiter>,
+    _tracing: bool,
+% if has_error_format_param(parameters):
+    _error_format: client::status::ErrorFormat,
+% endif
 }
 
 impl<'a, ${', '.join(HUB_TYPE_PARAMETERS)}> client::Hub for ${hub_type}${ht_params} {}
 
+impl<'a, ${', '.join(HUB_TYPE_PARAMETERS)}> AsRef<hyper::Client<S, hyper::body::Body>> for ${hub_type}${ht_params} {
+    fn as_ref(&self) -> &hyper::Client<S, hyper::body::Body> {
+        self.http_client()
+    }
+}
+
 impl<'a, ${', '.join(HUB_TYPE_PARAMETERS)}> ${hub_type}${ht_params} {
 
     pub fn new<A: 'static + client::GetToken>(client: hyper::Client<S, hyper::body::Body>, auth: A) -> ${hub_type}${ht_params} {
+        ${hub_type}::with_auth(client, Box::new(auth))
+    }
+
+    /// Like `new()`, but takes the result of building the authenticator, like
+    /// `oauth2::InstalledFlowAuthenticator::builder(secret).build().await`, and fails with
+    /// `client::Error::Io` if it couldn't be built. That way, failing to set up the hub is handled
+    /// like any failed call instead of needing an `unwrap()`.
+    pub fn try_new<A: 'static + client::GetToken>(client: hyper::Client<S, hyper::body::Body>, auth: std::io::Result<A>) -> client::Result<${hub_type}${ht_params}> {
+        Ok(${hub_type}::new(client, auth?))
+    }
+
+    /// Like `new()`, but set up for robust use in production, which takes these defaults:
+    ///
+    /// * `request_timeout(Some(Duration::from_secs(60)))`: a request fails with `client::Error::Timeout`
+    ///   if there is no response within a minute.
+    /// * `retry_policy(Some(client::retry::Backoff::default()))`: requests failing with a connection error
+    ///   or a transient status, like `503 Service Unavailable`, are retried up to 5 times, waiting as long
+    ///   as the server asks to, or with jittered exponential backoff otherwise. This only applies to
+    ///   `GET`, `HEAD`, `PUT` and `DELETE` requests. Others, like creating a resource, may have been
+    ///   carried out already, so they are only retried after `429 Too Many Requests` or
+    ///   `503 Service Unavailable` with a `Retry-After` header.
+    /// * `rate_limit(Some(client::rate::RateLimiter::per_second(10)))`: at most 10 requests are sent per
+    ///   second, by all clones of the hub together.
+    /// * `tracing(true)`: responses, failed requests and retries are emitted as `tracing` events, as
+    ///   described in `client::trace`.
+    ///
+    /// Each of them can be changed or turned off with its setter afterwards, like `hub.rate_limit(None)`.
+    pub fn with_middleware_default<A: 'static + client::GetToken>(client: hyper::Client<S, hyper::body::Body>, auth: A) -> ${hub_type}${ht_params} {
+        let mut hub = ${hub_type}::new(client, auth);
+        hub.request_timeout(Some(std::time::Duration::from_secs(60)));
+        hub.retry_policy(Some(client::retry::Backoff::default()));
+        hub.rate_limit(Some(client::rate::RateLimiter::per_second(10)));
+        hub.tracing(true);
+        hub
+    }
+
+    /// Create a new hub using an authenticator which was boxed already.
+    ///
+    /// Use it to share a single authenticator among the hubs of multiple services, as each clone of
+    /// a boxed authenticator can be handed to another hub.
+    pub fn with_auth(client: hyper::Client<S, hyper::body::Body>, auth: Box<dyn client::GetToken>) -> ${hub_type}${ht_params} {
         ${hub_type} {
             client,
-            auth: Box::new(auth),
+            auth,
             _user_agent: "${default_user_agent}".to_string(),
             _base_url: "${baseUrl}".to_string(),
             _root_url: "${rootUrl}".to_string(),
+            _quota_project: None,
+            _revision_max_age: None,
+            _default_timeout: None,
+            _on_retry: None,
+            _max_retries: None,
+            _retry_policy: None,
+            _rate_limiter: None,
+            _tracing: false,
+% if has_error_format_param(parameters):
+            _error_format: client::status::ErrorFormat::default(),
+% endif
         }
     }
 
@@ -93,17 +166,142 @@ impl<'a, ${', '.join(HUB_TYPE_PARAMETERS)}> ${hub_type}${ht_params} {
     /// Set the base url to use in all requests to the server.
     /// It defaults to `${baseUrl}`.
     ///
+    /// A missing trailing slash is added, as paths are appended to it.
+    ///
     /// Returns the previously set base url.
     pub fn base_url(&mut self, new_base_url: String) -> String {
-        mem::replace(&mut self._base_url, new_base_url)
+        mem::replace(&mut self._base_url, client::url::with_trailing_slash(new_base_url))
     }
 
     /// Set the root url to use in all requests to the server.
     /// It defaults to `${rootUrl}`.
     ///
+    /// A missing trailing slash is added, as paths are appended to it.
+    ///
     /// Returns the previously set root url.
     pub fn root_url(&mut self, new_root_url: String) -> String {
-        mem::replace(&mut self._root_url, new_root_url)
+        mem::replace(&mut self._root_url, client::url::with_trailing_slash(new_root_url))
+    }
+
+    /// Set the quota project, which is sent in the `X-Goog-User-Project` header of all requests.
+    /// It is the project whose quota is consumed and which is billed for using the API, regardless of
+    /// the project owning the resources a method is called on. It needs the API to be enabled.
+    ///
+    /// Not to be confused with the `userProject` parameter of some methods, which names the project
+    /// billed for accessing a particular resource. See `client::project` for details.
+    ///
+    /// Fails if the given project is neither a valid project ID nor a project number.
+    /// Returns the previously set quota project.
+    pub fn set_quota_project(&mut self, project: String) -> client::Result<Option<String>> {
+        if !client::project::is_valid_project(&project) {
+            return Err(client::Error::InvalidProject(project));
+        }
+        Ok(self._quota_project.replace(project))
+    }
+
+    /// Stop sending a quota project, which makes the project of the credentials the quota project.
+    ///
+    /// Returns the previously set quota project.
+    pub fn clear_quota_project(&mut self) -> Option<String> {
+        self._quota_project.take()
+    }
+
+    /// Check the age of the API revision this crate was generated from, `crate::API_REVISION`, against
+    /// the date of each successful response. If it is older than `max_age`, the delegate is told about it
+    /// through `api_revision_drift()`, which does nothing by default. This helps to notice that fields
+    /// which are missing from responses may have been added to the API after the crate was generated.
+    ///
+    /// It is disabled by default. Returns the previously set maximum age.
+    pub fn check_api_revision(&mut self, max_age: Option<client::chrono::Duration>) -> Option<client::chrono::Duration> {
+        mem::replace(&mut self._revision_max_age, max_age)
+    }
+
+    /// Set the time to wait for the response to each request made by the calls of this hub, unless
+    /// a call sets its own with `request_timeout()`. A request which takes longer fails with
+    /// `client::Error::Timeout`.
+    ///
+    /// There is no timeout by default. Returns the previously set timeout.
+    pub fn request_timeout(&mut self, timeout: Option<std::time::Duration>) -> Option<std::time::Duration> {
+        mem::replace(&mut self._default_timeout, timeout)
+    }
+
+    /// Call `hook` right before waiting to retry a request of any call of this hub, with the number of
+    /// the attempt which failed, starting at 1, why it failed, and how long it waits until the next
+    /// attempt. This is all it takes to log or count retries, without implementing `client::Delegate`.
+    ///
+    /// Whether to retry is still decided by the delegate of the call, which doesn't retry by default.
+    /// Returns the previously set hook.
+    pub fn on_retry<F>(&mut self, hook: F) -> Option<client::retry::RetryHook>
+    where
+        F: Fn(u32, &client::retry::RetryReason<'_>, std::time::Duration) + Send + Sync + 'static,
+    {
+        self._on_retry.replace(std::sync::Arc::new(hook))
+    }
+
+    /// Set how often a failed request of any call of this hub may be retried at most, unless a call
+    /// sets its own with `max_retries()`. This holds however often the delegate asks to retry, so
+    /// `Some(0)` makes each call fail right with the first error.
+    ///
+    /// There is no maximum by default, which leaves it to the delegate. Returns the previously set maximum.
+    pub fn max_retries(&mut self, max_retries: Option<u32>) -> Option<u32> {
+        mem::replace(&mut self._max_retries, max_retries)
+    }
+
+    /// Set how to retry failed requests of any call of this hub whenever the delegate of the call doesn't,
+    /// like `client::retry::Backoff::default()`, which retries connection errors and transient statuses of
+    /// idempotent requests.
+    ///
+    /// There is none by default, which leaves retries to the delegate. Returns the previously set policy.
+    pub fn retry_policy(&mut self, policy: Option<client::retry::Backoff>) -> Option<client::retry::Backoff> {
+        mem::replace(&mut self._retry_policy, policy)
+    }
+
+    /// Set the limiter each request of any call of this hub waits for before it is sent, including
+    /// retries, like `client::rate::RateLimiter::per_second(10)`. Clones of the hub share it.
+    ///
+    /// There is no limit by default. Returns the previously set limiter.
+    pub fn rate_limit(&mut self, limiter: Option<client::rate::RateLimiter>) -> Option<client::rate::RateLimiter> {
+        mem::replace(&mut self._rate_limiter, limiter)
+    }
+
+    /// Set whether to emit `tracing` events about the requests of all calls of this hub, as described
+    /// in `client::trace`.
+    ///
+    /// It is disabled by default. Returns whether it was enabled.
+    pub fn tracing(&mut self, enabled: bool) -> bool {
+        mem::replace(&mut self._tracing, enabled)
+    }
+% if has_error_format_param(parameters):
+
+    /// Set the format of the errors returned by the API, which is sent as the `${ERROR_FORMAT_PARAM}` parameter of all
+    /// requests, unless a call sets it with `param()`. It defaults to `ErrorFormat::V2`, whose details are
+    /// understood by `Error::localized_message()`.
+    ///
+    /// Returns the previously set format.
+    pub fn error_format(&mut self, format: client::status::ErrorFormat) -> client::status::ErrorFormat {
+        mem::replace(&mut self._error_format, format)
+    }
+% endif
+
+    /// The quota project sent with all requests, if any.
+    pub fn quota_project(&self) -> Option<${'&'}str> {
+        self._quota_project.as_deref()
+    }
+
+    /// The HTTP client all calls of this hub are made with. Use it for requests the hub has no method for,
+    /// like to an undocumented endpoint, to reuse the configured transport and its pool of connections.
+    pub fn http_client(&self) -> ${'&'}hyper::Client<S, hyper::body::Body> {
+        &self.client
+    }
+
+    /// Get an access token for `scopes`, like the URLs given by `Scope::as_ref()`, from the authenticator of
+    /// this hub, as calls do for their `Authorization` header. Use it to authorize requests made with
+    /// `http_client()`.
+    ///
+    /// It is `None` if the authenticator needs no token, like `client::NoToken`, and fails with
+    /// `client::Error::MissingToken` if it couldn't get one.
+    pub async fn token_for(&self, scopes: ${'&'}[${'&'}str]) -> client::Result<Option<String>> {
+        self.auth.get_token(scopes).await.map_err(client::Error::MissingToken)
     }
 }
 
@@ -114,7 +312,7 @@ impl<'a, ${', '.join(HUB_TYPE_PARAMETERS)}> ${hub_type}${ht_params} {
 // ##########
 % for s in c.schemas.values():
 % if UNUSED_TYPE_MARKER not in schema_markers(s, c, transitive=True):
-${schema.new(s, c)}
+${schema.new(s, c, validated)}
 % endif
 % endfor
 % endif
@@ -136,7 +334,141 @@ ${rbuild.new(resource, c)}
 
 % for resource, methods in c.rta_map.items():
 % for method in methods:
-${mbuild.new(resource, method, c)}
+${mbuild.new(resource, method, c, validated)}
 
 % endfor ## method in methods
+% for sub, activities, required, optional in snapshot_groups(c, resource):
+${mbuild.snapshot(resource, sub, activities, required, optional, c)}
+
+% endfor ## snapshot groups
 % endfor ## resource, methods
+
+// ###################
+// Methods        ###
+// #################
+
+<%
+    all_methods = sorted((c.fqan_map[to_fqan(c.rtc_map[resource], resource, method)]
+                          for resource, methods in c.rta_map.items() for method in methods), key=lambda m: m.id)
+%>/// Descriptions of all methods of the API, which allow to build request inspectors, documentation or
+/// routing tables without making a call.
+pub mod methods {
+    use crate::client::MethodDescription;
+
+% for m in all_methods:
+    /// The `${m.id}` method.
+    pub const ${method_const_name(m.id)}: MethodDescription = MethodDescription {
+        id: "${m.id}",
+        http_method: "${m.httpMethod}",
+        path: "${m.path}",
+        required_params: &[${', '.join(enclose_in('"', method_required_params(m)))}],
+        scopes: &[${', '.join(enclose_in('"', m.get('scopes', [])))}],
+    };
+
+% endfor
+    /// All of the above, ordered by their identifier.
+    pub const ALL: &[MethodDescription] = &[
+% for m in all_methods:
+        ${method_const_name(m.id)},
+% endfor
+    ];
+}
+<%
+    filtered_methods = [(m, filter_fields(c.schemas, m)) for m in all_methods]
+    filtered_methods = [(m, fields) for m, fields in filtered_methods if fields]
+%>\
+% if filtered_methods:
+
+// ###################
+// Filters        ###
+// #################
+
+/// The fields each method with a `filter` parameter can be filtered by, typed after the values they can be
+/// compared with. See `client::filter` for how to combine them into an expression.
+pub mod filters {
+% for i, (m, fields) in enumerate(filtered_methods):
+% if i > 0:
+
+% endif
+    /// The fields to filter the results of `${m.id}` by.
+    pub mod ${method_const_name(m.id).lower()} {
+        use crate::client::FilterField;
+
+    % for fn, ft in fields:
+        pub const ${camel_to_under(fn).upper()}: FilterField<${ft}> = FilterField::new("${fn}");
+    % endfor
+    }
+% endfor
+}
+% endif
+<%
+    audited_schemas = [s for s in c.schemas.values()
+                       if UNUSED_TYPE_MARKER not in schema_markers(s, c, transitive=True)
+                       and s.type == 'object' and s.get('properties') and 'variant' not in s]
+%>\
+% if audited_schemas:
+
+#[cfg(test)]
+mod test_wire_names {
+    use super::*;
+
+    /// The names the fields of `T` are serialized with, which includes unset optional fields as `null`.
+    fn wire_names<T: Default + Serialize>() -> Vec<String> {
+        match json::to_value(T::default()).unwrap() {
+            json::Value::Object(map) => {
+                let mut names: Vec<_> = map.keys().cloned().collect();
+                names.sort();
+                names
+            }
+            value => panic!("expected an object, got {}", value),
+        }
+    }
+
+    /// Each field must be serialized with the name the discovery document gives it, or the server would
+    /// ignore it.
+    #[test]
+    fn match_discovery() {
+    % for s in audited_schemas:
+        assert_eq!(wire_names::<${s.id}>(), [${', '.join(enclose_in('"', sorted(s.properties.keys())))}]);
+    % endfor
+    }
+
+    /// A mask built with `FieldMask::full_for()` must name each field the way it is serialized.
+    #[test]
+    fn field_names_are_wire_names() {
+        fn field_names<T: client::FieldNames>() -> Vec<String> {
+            let mut names: Vec<_> = T::FIELD_NAMES.iter().map(|name| name.to_string()).collect();
+            names.sort();
+            names
+        }
+    % for s in audited_schemas:
+        assert_eq!(wire_names::<${s.id}>(), field_names::<${s.id}>());
+    % endfor
+    }
+}
+% endif
+<%
+    newtypes = api.get('newtype_identifiers', False) and identifier_newtypes(c) or []
+%>\
+% if newtypes:
+
+#[cfg(test)]
+mod test_identifiers {
+    use super::*;
+
+    /// Identifiers are meant to key collections, which must be possible to look up with a string.
+    #[test]
+    fn key_collections() {
+        let mut by_id = std::collections::BTreeMap::new();
+        by_id.insert(${newtypes[0]}::from("b"), 1);
+        by_id.insert(${newtypes[0]}::from("a"), 2);
+        by_id.insert(${newtypes[0]}::from("b"), 3);
+        assert_eq!(by_id.keys().map(AsRef::as_ref).collect::<Vec<${'&'}str>>(), ["a", "b"]);
+        assert_eq!(by_id.get("b"), Some(&3));
+
+        let ids: std::collections::HashSet<${newtypes[0]}> = ["a", "b", "a"].iter().map(|&id| id.into()).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains("a"));
+    }
+}
+% endif
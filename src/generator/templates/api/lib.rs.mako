@@ -51,6 +51,9 @@ pub mod api;
 pub use api::${hub_type};
 pub use client::{Result, Error, Delegate, FieldMask};
 
+/// The revision of the API schema this crate was generated from.
+pub const API_REVISION: &str = "${revision is UNDEFINED and '00000000' or revision}";
+
 // Re-export the yup_oauth2 crate, that is required to call some methods of the hub and the client
 #[cfg(feature = "yup-oauth2")]
 pub use client::oauth2;
\ No newline at end of file
@@ -6,8 +6,9 @@
                       find_fattest_resource, build_all_params, pass_through, parts_from_params,
                       REQUEST_MARKER_TRAIT, RESPONSE_MARKER_TRAIT, supports_scopes, to_api_version,
                       to_fqan, METHODS_RESOURCE, ADD_PARAM_MEDIA_EXAMPLE, PROTOCOL_TYPE_INFO, enclose_in,
-                      upload_action_fn, METHODS_BUILDER_MARKER_TRAIT, DELEGATE_TYPE,
-                      to_extern_crate_name, rust_doc_sanitize)  
+                      upload_action_fn, METHODS_BUILDER_MARKER_TRAIT, DELEGATE_TYPE, rust_str_literal,
+                      to_extern_crate_name, rust_doc_sanitize, identifier_newtypes, snapshot_groups,
+                      has_error_format_param, ERROR_FORMAT_PARAM)
 
     def pretty_name(name):
         return ' '.join(split_camelcase_s(name).split('.'))
@@ -166,6 +167,8 @@ The `resource()` and `activity(...)` calls create [builders][builder-pattern]. T
 supports various methods to configure the impending operation (not shown here). It is made such that all required arguments have to be 
 specified right away (i.e. `(...)`), whereas all optional ones can be [build up][builder-pattern] as desired.
 The `${api.terms.action}()` method performs the actual communication with the server and returns the respective result.
+Results decoded from the response are returned as ${link('CallResponse', 'client::CallResponse')}, where `into_body()` yields
+the decoded value, while `status()` and `headers()` provide access to the response itself.
 
 # Usage
 
@@ -184,6 +187,46 @@ ${'##'} A complete example
 
 ${self.hub_usage_example(c, rust_doc, fr=fr)}\
 
+${'##'} Sharing an Authenticator
+
+`${hub_url}::new(client, auth)` accepts any type implementing `GetToken`. If the authenticator is
+available as `Box<dyn GetToken>` already, for instance because it is shared with the hubs of other services, use
+`${hub_url}::with_auth(client, auth)` instead. This allows to authenticate once and use the
+resulting tokens with all services.
+
+Building an authenticator can fail, for instance if its token file can't be read. Hand the result of building it to
+`${hub_url}::try_new(client, auth)` to get that failure as `Error::Io`, like the errors of any call, instead of unwrapping it.
+Errors while making a call are returned as well, including request values which can't be encoded as JSON
+(`Error::JsonEncodeError`) and requests which can't be built, like with a user agent which isn't a valid header value
+(`Error::InvalidRequest`).
+
+${'##'} Quota Project
+
+Requests are billed to, and consume the quota of, the project of the credentials by default. Use
+`hub.set_quota_project(project)` to choose another project, which is then sent in the `X-Goog-User-Project` header
+of all requests and must have the API enabled. This is independent of the project owning the resources, which is
+passed to the methods themselves, and of the `userProject` parameter some methods have to name the project billed
+for accessing a particular resource. Both are validated to be a project ID or number before they are sent.
+
+${'##'} Dual-Stack Networks
+
+The hub connects with whichever connector its client was built with, so name resolution and connection establishment
+are configured there. In networks where IPv6 or IPv4 is configured but broken, build a `hyper::client::HttpConnector`
+with `HttpConnector::new_with_resolver()` and a resolver returning the addresses of the working family first, or only
+those. `set_happy_eyeballs_timeout()` sets how long the first address family gets before the other one is tried alongside
+it, which is 300 milliseconds by default. Call `enforce_http(false)` on it and hand it to
+`hyper_rustls::HttpsConnectorBuilder::wrap_connector()` to use it for HTTPS.
+
+${'##'} JSON Schema
+
+Enable the `jsonschema` feature to have all schema types implement `schemars::JsonSchema`. This allows to produce a JSON Schema of request bodies, for instance to validate them
+before sending, or to generate forms to fill them in.
+
+```toml
+[dependencies]
+${util.crate_name()} = { version = "*", features = ["jsonschema"] }
+```
+
 ${'##'} Handling Errors
 
 All errors produced by the system are provided either as ${link('Result', 'client::Result')} enumeration as return value of
@@ -193,9 +236,18 @@ ${link('Hub Delegate', delegate_url)}, or the ${link('Authenticator Delegate', u
 When delegates handle errors or intermediate values, they may have a chance to instruct the system to retry. This 
 makes the system potentially resilient to all kinds of errors.
 
+Retrying outside of the delegate is possible as well, and `Error::retry_after()` returns the delay the server asked to wait
+before trying again, if any. If the server provided a message meant for users along with the error, in their language,
+`Error::localized_message()` returns it.
+% if has_error_format_param(parameters):
+
+Errors are requested in the format of a `google.rpc.Status`, which has these details, by sending the `${ERROR_FORMAT_PARAM}` parameter
+with all requests. `hub.error_format(client::status::ErrorFormat::V1)` selects the legacy format instead.
+% endif
+
 ${'##'} Uploads and Downloads
 If a method supports downloads, the response body, which is part of the ${link('Result', 'client::Result')}, should be
-read by you to obtain the media.
+read by you to obtain the media. Use `into_parts()` to obtain it from a ${link('CallResponse', 'client::CallResponse')}.
 If such a method also supports a ${link('Response Result', 'client::ResponseResult')}, it will return that by default.
 You can see it as meta-data for the actual media. To trigger a media download, you will have to set up the builder by making
 this call: `${ADD_PARAM_MEDIA_EXAMPLE}`.
@@ -213,6 +265,68 @@ retry on failure.
 
 The ${link('delegate trait', delegate_url)} is default-implemented, allowing you to customize it with minimal effort.
 
+To merely log or count the retries a delegate asks for, `hub.on_retry(|attempt, reason, delay| ...)` is called right before
+waiting to retry a request of any call, without implementing the delegate trait.
+
+Delegates set with `delegate()` are called through a trait object. To avoid the dynamic dispatch, for instance when
+making a great number of small calls, pass the delegate or a mutable borrow of it to `with_delegate()` instead.
+
+Each optional parameter has a `maybe_` setter taking an `Option` besides its regular one, like `maybe_page_size(page_size)`,
+which only sets it if the value is `Some`. That way, optional values are passed on without breaking the chain of calls.
+
+Requests wait for a response as long as it takes by default. Use `hub.request_timeout(Some(duration))` to set a timeout for
+the requests of all calls, which each call can override with its own `request_timeout()`. Requests taking longer fail with
+`Error::Timeout`.
+
+To abort a call from elsewhere, for instance when the client of a service went away, give it a `client::CancellationToken`
+with `cancellation_token()`. Once the token is cancelled, the request in flight is dropped, no further attempts are made, and
+the call fails with `Error::Cancelled`.
+
+The delegate decides whether a failed request is retried. To cap the retries of all delegates, use
+`hub.max_retries(Some(retries))`, which each call can override with its own `max_retries()`. With `Some(0)`, calls fail
+right with the first error, even if their delegate asks for another attempt.
+
+For requests the hub has no method for, like to an undocumented endpoint, `hub.http_client()` returns the `hyper::Client`
+all calls are made with, and `hub.token_for(scopes)` gets an access token from the authenticator of the hub to authorize
+them with. That way, such requests reuse the configured transport, its connections and the credentials.
+
+For robust defaults without assembling them one by one, create the hub with `with_middleware_default()` instead of `new()`.
+It sets a timeout of a minute for each request, retries connection errors and transient statuses up to 5 times with
+jittered exponential backoff or as long as the server asks to wait, sends at most 10 requests per second and emits `tracing`
+events about requests and retries. Requests which are not idempotent, like creating a resource, are only retried when the
+server answers `429 Too Many Requests` or `503 Service Unavailable` with a `Retry-After` header, as they may have been
+carried out otherwise. Each of them is turned off with its own setter, like `hub.rate_limit(None)`,
+`hub.retry_policy(None)` or `hub.tracing(false)`.
+
+Requests are encoded in memory before they are sent. For requests of many megabytes, `stream_request(true)` encodes them
+while they are sent instead, a chunk at a time, which keeps the memory needed for them low.
+
+Calls which need no further arguments to be made implement `client::service::ServiceRequest`, which allows to make them
+through a `tower_service::Service` like `client::service::CallService`. That way, the layers of the tower ecosystem, like
+rate limits or load shedding, can be stacked around all calls alike.
+
+Call builders and the schemas of requests implement `client::Validate`, whose `validate()` checks parameters and request
+fields against the constraints of the API description, like the patterns of resource names, the values an enumeration
+allows, the ranges of numbers and the fields the results of a method can be ordered by. This catches mistakes before making the call, with the path of each offending value.
+
+${'##'} Method Descriptions
+
+The verb, URI template and required parameters of each method are available as constants in the `api::methods`
+module, and `api::methods::ALL` lists all of them, for instance to build request inspectors or routing tables.
+The fields methods with a `filter` parameter can be filtered by are typed constants in the `api::filters` module,
+which build filter expressions like `APP_TYPE.eq("EXTENSION").and(TOTAL_INSTALL_COUNT.gte(100))` checked by the compiler.
+<%
+    snapshots = [(r, sub) for r in sorted(c.rta_map) for sub, _, _, _ in snapshot_groups(c, r)]
+%>\
+% if snapshots:
+
+${'##'} Report Snapshots
+
+Related reports can be fetched at once, with ${put_and(['`hub.%s().%s(...)`' % (mangle_ident(r), mangle_ident(sub)) for r, sub in snapshots])}
+making the underlying calls concurrently and combining their results into a single structure. The amount of calls in flight
+is bounded by `concurrency()`, and `interval()` spreads out their start to stay within rate limits.
+% endif
+
 ${'##'} Optional Parts in Server-Requests
 
 All structures provided by this library are made to be ${link('encodable', request_trait_url)} and 
@@ -221,6 +335,15 @@ are valid.
 Most optionals are are considered ${link('Parts', part_trait_url)} which are identifiable by name, which will be sent to 
 the server to indicate either the set parts of the request or the desired parts in the response.
 
+Schemas allowing keys besides their listed fields, like those named dynamically, have an `extra` map keeping those keys
+with their JSON values. That way, objects read from the API are written back without losing anything.
+
+${'##'} Field Masks
+
+Methods taking a `FieldMask`, like a read mask, often need to be told to return everything. Each schema implements
+`client::FieldNames`, which allows `FieldMask::full_for::<T>()` to build a mask naming all fields of the type `T`.
+Naming a field includes everything below it, so the mask stays complete as the API adds fields.
+
 ${'##'} Builder Arguments
 
 Using ${link('method builders', call_builder_url)}, you are able to prepare an action call by repeatedly calling it's methods.
@@ -285,8 +408,7 @@ let mut hub = ${hub_type}::new(hyper::Client::builder().build(hyper_rustls::Http
             # Cannot use fqan directly, as it might need remapping thanks to 'special case' resource.
             # see METHODS_RESOURCE for more information
             am = c.fqan_map[to_fqan(category, aresource, amethod)]
-            build_all_params(c, am)
-            aparams, arequest_value = build_all_params(c, am)
+            aparams, arequest_value = build_all_params(c, am, api.get('newtype_identifiers', False))
 
             if last_param_count is None or len(aparams) > last_param_count:
                 m, resource, method, params, request_value = am, aresource or category, amethod, aparams, arequest_value
@@ -331,7 +453,11 @@ You can read the full text at the repository's [license file][repo-license].
 /// Identifies the an OAuth2 authorization scope.
 /// A scope is needed when requesting an
 /// [authorization token](https://developers.google.com/youtube/v3/guides/authentication).
-#[derive(PartialEq, Eq, Hash)]
+///
+/// Scopes can be passed to the `add_scope()` and `add_scopes()` methods of all call builders, as
+/// well as be converted into their URL using `as_ref()` or `String::from()`. What each of them grants
+/// is told by `description()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Scope {
 % for url, scope in auth.oauth2.scopes.items():
     ${scope.description | rust_doc_sanitize(documentationLink), rust_doc_comment}
@@ -352,6 +478,30 @@ impl AsRef<str> for Scope {
     }
 }
 
+impl Scope {
+    /// What the scope grants access to, as described by the API, for instance to show it to the
+    /// users who are asked to authorize it.
+    pub fn description(&self) -> &'static str {
+        match *self {
+            % for url, scope in auth.oauth2.scopes.items():
+            ${scope_url_to_variant(name, url)} => ${rust_str_literal(scope.description)},
+            % endfor
+        }
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl From<Scope> for String {
+    fn from(scope: Scope) -> String {
+        scope.as_ref().to_string()
+    }
+}
+
 impl Default for Scope {
     fn default() -> Scope {
 <%
@@ -369,3 +519,64 @@ impl Default for Scope {
     }
 }
 </%def>
+
+## Newtypes for opaque identifier parameters, enabled by the 'newtype_identifiers' api option
+<%def name="identifier_newtypes(c)">\
+% if not api.get('newtype_identifiers', False):
+<% return '' %>\
+% endif
+% for tn in identifier_newtypes(c):
+/// An opaque identifier, as passed to all method parameters named *${tn[0].lower() + tn[1:]}*.
+///
+/// Having a type for each kind of identifier prevents passing one where another is expected.
+/// It can be created from any string, and serializes as such. As it compares and hashes like the
+/// string, it can key a `HashMap` or `BTreeMap`, which can be looked up with a `${'&'}str` as well.
+#[derive(Default, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct ${tn}(pub String);
+
+impl From<String> for ${tn} {
+    fn from(value: String) -> ${tn} {
+        ${tn}(value)
+    }
+}
+
+impl From<${'&'}str> for ${tn} {
+    fn from(value: &str) -> ${tn} {
+        ${tn}(value.to_string())
+    }
+}
+
+impl From<${'&'}String> for ${tn} {
+    fn from(value: &String) -> ${tn} {
+        ${tn}(value.clone())
+    }
+}
+
+impl From<${tn}> for String {
+    fn from(value: ${tn}) -> String {
+        value.0
+    }
+}
+
+impl AsRef<str> for ${tn} {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::borrow::Borrow<str> for ${tn} {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ${tn} {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+% endfor
+</%def>
@@ -5,14 +5,17 @@
                       split_camelcase_s, property, is_pod_property, TREF, IO_REQUEST,
                       schema_to_required_property, rust_copy_value_s, is_required_property,
                       hide_rust_doc_test, build_all_params, REQUEST_VALUE_PROPERTY_NAME, organize_params,
-                      indent_by, to_rust_type, rnd_arg_val_for_type, extract_parts, mb_type_params_s,
+                      indent_by, to_rust_type, rnd_arg_val_for_type, extract_parts, mb_type_params_s, mb_type_params_def_s,
                       hub_type_params_s, method_media_params, enclose_in, method_response,
                       CALL_BUILDER_MARKERT_TRAIT, pass_through, markdown_rust_block, parts_from_params,
                       DELEGATE_PROPERTY_NAME, struct_type_bounds_s, scope_url_to_variant,
                       re_find_replacements, ADD_PARAM_FN, ADD_PARAM_MEDIA_EXAMPLE, upload_action_fn, METHODS_RESOURCE,
                       method_name_to_variant, size_to_bytes, method_default_scope,
                       is_repeated_property, setter_fn_name, ADD_SCOPE_FN, ADD_SCOPES_FN, rust_doc_sanitize,
-                      CLEAR_SCOPES_FN, items, string_impl)
+                      CLEAR_SCOPES_FN, items, string_impl, USER_PROJECT_PARAM,
+                      DELEGATE_TYPE, DELEGATE_TYPE_PARAM, HUB_TYPE_PARAMETERS, FILTER_PARAM, filter_fields,
+                      method_const_name, rb_type_params_s, snapshot_type, constraint_checks, order_by_fields, rust_str_literal, has_error_format_param,
+                      ERROR_FORMAT_PARAM)
 
     SIMPLE = "simple"
     RESUMABLE = "resumable"
@@ -42,7 +45,7 @@
 ## Creates a method builder type
 ###############################################################################################
 ###############################################################################################
-<%def name="new(resource, method, c)">\
+<%def name="new(resource, method, c, validated)">\
 <%
     hub_type_name = hub_type(schemas,util.canonical_name())
     m = c.fqan_map[to_fqan(c.rtc_map[resource], resource, method)]
@@ -52,7 +55,7 @@
     mb_tparams = mb_type_params_s(m)
     ThisType = mb_type(resource, method) + mb_tparams
 
-    params, request_value = build_all_params(c, m)
+    params, request_value = build_all_params(c, m, api.get('newtype_identifiers', False))
     alt_param = None
     for p in params:
         if p.name == 'alt':
@@ -117,14 +120,16 @@ the *${m.scopes[0]}* scope to make a valid call.
 <%block filter="rust_doc_comment">\
 ${self.usage(resource, method, m, params, request_value, parts)}\
 </%block>
-pub struct ${ThisType}
+pub struct ${mb_type(resource, method)}${mb_type_params_def_s(m)}
     where ${struct_type_bounds_s()} {
 
     hub: &'a ${hub_type_name}${hub_type_params_s()},
 ## PROPERTIES ###############
 % for p in params:
     ${property(p.name)}:\
-    % if is_required_property(p):
+    % if p.name == DELEGATE_PROPERTY_NAME:
+ ${DELEGATE_TYPE_PARAM},
+    % elif is_required_property(p):
  ${activity_rust_type(schemas, p, allow_optionals=False)},
     % else:
  ${activity_rust_type(schemas, p)},
@@ -132,6 +137,12 @@ pub struct ${ThisType}
 % endfor
 ## A generic map for additinal parameters. Sometimes you can set some that are documented online only
     ${api.properties.params}: HashMap<String, String>,
+    ${api.properties.timeout}: Option<std::time::Duration>,
+    ${api.properties.cancellation}: Option<client::CancellationToken>,
+    ${api.properties.max_retries}: Option<u32>,
+    % if request_value:
+    ${api.properties.stream_request}: bool,
+    % endif
     % if method_default_scope(m):
 ## We need the scopes sorted, to not unnecessarily query new tokens
     ${api.properties.scopes}: BTreeSet<String>
@@ -146,6 +157,7 @@ where
     S::Response: hyper::client::connect::Connection + AsyncRead + AsyncWrite + Send + Unpin + 'static,
     S::Future: Send + Unpin + 'static,
     S::Error: Into<Box<dyn StdError + Send + Sync>>,
+    ${DELEGATE_TYPE_PARAM}: ${DELEGATE_TYPE},
 {
 % if api.get('no_upload_prefix') is not None and ThisType.startswith(api.no_upload_prefix):
 ${self._action_fn(c, resource, method, m, params, request_value, parts, doit_without_upload = True)}\
@@ -155,7 +167,11 @@ ${self._action_fn(c, resource, method, m, params, request_value, parts)}\
 
 ## SETTERS ###############
 % for p in params:
+% if p.name == DELEGATE_PROPERTY_NAME:
+${self._delegate_setter_fns(resource, method, m, p, params, c)}\
+% else:
 ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
+% endif
 % endfor
 
     /// Set any additional parameter of the query string used in the request.
@@ -172,12 +188,53 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
     /// * *${opn}* (${op.location}-${op.type}) - ${op.description}
     % endfor
     % endif
+    #[inline]
     pub fn ${ADD_PARAM_FN}<T>(mut self, name: T, value: T) -> ${ThisType}
                                                         where T: AsRef<str> {
         self.${api.properties.params}.insert(name.as_ref().to_string(), value.as_ref().to_string());
         self
     }
 
+    /// Set the time to wait for the response to each request of this call, instead of the default
+    /// timeout of the hub. A request which takes longer fails with `client::Error::Timeout`.
+    #[inline]
+    pub fn request_timeout(mut self, timeout: std::time::Duration) -> ${ThisType} {
+        self.${api.properties.timeout} = Some(timeout);
+        self
+    }
+
+    /// Set a token to cancel this call with. Once it is cancelled, the request in flight is dropped,
+    /// no further attempts are made, and the call fails with `client::Error::Cancelled`.
+    #[inline]
+    pub fn cancellation_token(mut self, token: client::CancellationToken) -> ${ThisType} {
+        self.${api.properties.cancellation} = Some(token);
+        self
+    }
+
+    /// Set how often a failed request of this call may be retried at most, instead of the maximum of
+    /// the hub, however often the delegate asks to retry. With 0, the call fails right with the first error.
+    #[inline]
+    pub fn max_retries(mut self, max_retries: u32) -> ${ThisType} {
+        self.${api.properties.max_retries} = Some(max_retries);
+        self
+    }
+
+    % if request_value:
+    /// Set whether to encode the request while it is sent, in chunks and without its length, instead of
+    /// in memory beforehand. This is worth it for requests of many megabytes. See `client::json_body`.
+    ///
+    % if method_media_params(m):
+    /// Uploads always encode the request in memory, as it is one part of the upload.
+    ///
+    % endif
+    /// Defaults to `false`.
+    #[inline]
+    pub fn stream_request(mut self, stream: bool) -> ${ThisType} {
+        self.${api.properties.stream_request} = stream;
+        self
+    }
+
+    % endif
     % if method_default_scope(m):
     /// Identifies the authorization scope for the method you are building.
     ///
@@ -190,6 +247,10 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
     /// Usually there is more than one suitable scope to authorize an operation, some of which may
     /// encompass more rights than others. For example, for listing resources, a *read-only* scope will be
     /// sufficient, a read-write scope will do as well.
+    ///
+    /// `validate()` reports scopes of which none is accepted by the method, like those of other APIs, as
+    /// such calls fail with `ACCESS_TOKEN_SCOPE_INSUFFICIENT`.
+    #[inline]
     pub fn ${ADD_SCOPE_FN}<St>(mut self, scope: St) -> ${ThisType}
                                                         where St: AsRef<str> {
         self.${api.properties.scopes}.insert(String::from(scope.as_ref()));
@@ -197,7 +258,11 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
     }
     /// Identifies the authorization scope(s) for the method you are building.
     ///
+    /// Accepts any collection of [`Scope`] variants or scope URLs, like
+    /// `${ADD_SCOPES_FN}([${scope_url_to_variant(name, method_default_scope(m), fully_qualified=True)}])`.
+    ///
     /// See [`Self::${ADD_SCOPE_FN}()`] for details.
+    #[inline]
     pub fn ${ADD_SCOPES_FN}<I, St>(mut self, scopes: I) -> ${ThisType}
                                                         where I: IntoIterator<Item = St>,
                                                          St: AsRef<str> {
@@ -209,12 +274,194 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
     /// Removes all scopes, and no default scope will be used either.
     /// In this case, you have to specify your API-key using the `key` parameter (see [`Self::${ADD_PARAM_FN}()`]
     /// for details).
+    #[inline]
     pub fn ${CLEAR_SCOPES_FN}(mut self) -> ${ThisType} {
         self.${api.properties.scopes}.clear();
         self
     }
     % endif
 }
+% if not method_media_params(m):
+
+impl${mb_tparams} client::service::ServiceRequest for ${ThisType}
+where
+    S: tower_service::Service<http::Uri> + Clone + Send + Sync + 'static,
+    S::Response: hyper::client::connect::Connection + AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    S::Future: Send + Unpin + 'static,
+    S::Error: Into<Box<dyn StdError + Send + Sync>>,
+    ${DELEGATE_TYPE_PARAM}: ${DELEGATE_TYPE} + 'a,
+{
+    % if response_schema:
+    type Response = client::CallResponse<${response_schema.id}>;
+    % else:
+    type Response = hyper::Response<hyper::body::Body>;
+    % endif
+    type Future = client::service::CallFuture<'a, Self::Response>;
+
+    fn into_call(self) -> Self::Future {
+        Box::pin(self.${api.terms.action}())
+    }
+}
+% endif
+<%
+    checks = list()
+    for p in params:
+        if p.name == DELEGATE_PROPERTY_NAME:
+            continue
+        if request_value and p.name == REQUEST_VALUE_PROPERTY_NAME:
+            if request_value.id in validated:
+                checks.append('client::Validate::validate_at(&self.%s, path, errors);' % property(p.name))
+            continue
+        checks += constraint_checks(p, '&client::validate::field_path(path, "%s")' % p.name,
+                                    '&self.%s' % property(p.name), 'errors')
+        if order_by_fields(p.name, p):
+            checks.append('client::validate::check_order_by(&client::validate::field_path(path, "%s"), &self.%s, &[%s], errors);'
+                          % (p.name, property(p.name), ', '.join(rust_str_literal(f) for f in order_by_fields(p.name, p))))
+    # end for each param
+    if method_default_scope(m):
+        checks.append('client::validate::check_scopes(&client::validate::field_path(path, "scope"), &self.%s, methods::%s.scopes, errors);'
+                      % (api.properties.scopes, method_const_name(m.id)))
+    unused = '' if checks else '_'
+%>
+/// Checks the parameters and the request against the constraints documented by the API.
+impl${mb_tparams} client::Validate for ${ThisType}
+    where ${struct_type_bounds_s()} {
+    fn validate_at(&self, ${unused}path: ${'&'}str, ${unused}errors: &mut Vec<client::ValidationError>) {
+    % for check in checks:
+        ${check}
+    % endfor
+    }
+}
+</%def>
+
+
+## Creates a builder fetching a group of reports at once, along with the type combining them
+###############################################################################################
+###############################################################################################
+<%def name="snapshot(resource, sub, activities, required, optional, c)">\
+<%
+    hub_type_name = hub_type(schemas, util.canonical_name())
+    SnapshotType = snapshot_type(resource, sub)
+    CallType = mb_type(resource, sub + '.snapshot')
+    rb_params = rb_type_params_s(resource, c)
+    ThisType = CallType + rb_params
+    fields = [(mangle_ident(a.split('.')[1]), a, m) for a, m in activities]
+    first_params = activities[0][1].get('parameters', dict())
+%>\
+/// The ${put_and(['*%s*' % split_camelcase_s(a.split('.')[1]) for a, _ in activities])} reports of a
+/// *${singular(resource)}*, as fetched at once through a [`${CallType}`].
+///
+/// Reports which are returned in pages only hold their first page.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
+pub struct ${SnapshotType} {
+% for field, a, m in fields:
+    /// The result of `${m.id}`.
+    pub ${field}: ${method_response(c, m).id},
+% endfor
+}
+
+/// A builder fetching the ${put_and(['*%s*' % split_camelcase_s(a.split('.')[1]) for a, _ in activities])} reports of a
+/// *${singular(resource)}* concurrently, which are combined into a single [`${SnapshotType}`].
+/// It is not used directly, but through a [`${rb_type(resource)}`] instance.
+///
+/// At most `concurrency()` calls are in flight at once, and their start can be spread out using `interval()`
+/// to stay within rate limits. The first failing call fails the whole snapshot.
+///
+/// # Example
+///
+/// Instantiate a resource method builder
+///
+<%block filter="rust_doc_test_norun, rust_doc_comment">\
+${util.test_prelude()}\
+
+<%block filter="rust_test_fn_invisible">\
+${lib.test_hub(hub_type_name, comments=False)}\
+let result = hub.${mangle_ident(resource)}().${mangle_ident(sub)}(${', '.join('"%s"' % pn for pn in required)})
+             .concurrency(2)
+             .interval(std::time::Duration::from_millis(100))
+             .doit().await;
+</%block>
+</%block>
+pub struct ${ThisType}
+    where ${struct_type_bounds_s()} {
+
+    hub: &'a ${hub_type_name}${hub_type_params_s()},
+% for pn in required:
+    ${property(pn)}: String,
+% endfor
+% for pn in optional:
+    ${property(pn)}: Option<String>,
+% endfor
+    _concurrency: usize,
+    _interval: std::time::Duration,
+}
+
+impl${rb_params} ${CALL_BUILDER_MARKERT_TRAIT} for ${ThisType} {}
+
+impl${rb_params} ${ThisType}
+where
+    S: tower_service::Service<http::Uri> + Clone + Send + Sync + 'static,
+    S::Response: hyper::client::connect::Connection + AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    S::Future: Send + Unpin + 'static,
+    S::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    /// Perform all calls, and combine their results once all of them succeeded.
+    pub async fn ${api.terms.action}(self) -> client::Result<${SnapshotType}> {
+    % for field, a, m in fields:
+        let mut ${field} = None;
+    % endfor
+        let start = tokio::time::Instant::now();
+        let calls: Vec<client::join::BoxedCall<'_, client::Error>> = vec![
+    % for i, (field, a, m) in enumerate(fields):
+            Box::pin(async {
+                tokio::time::sleep_until(start + self._interval * ${i}).await;
+                let mut call = self.hub.${mangle_ident(resource)}().${mangle_ident(a)}(${', '.join('&self.%s' % property(pn) for pn in required)});
+        % for pn in optional:
+                if let Some(value) = &self.${property(pn)} {
+                    call = call.${mangle_ident(pn)}(value);
+                }
+        % endfor
+                ${field} = Some(call.${api.terms.action}().await?.into_body());
+                Ok::<_, client::Error>(())
+            }),
+    % endfor
+        ];
+        client::join::try_join_bounded(calls, self._concurrency).await?;
+
+        Ok(${SnapshotType} {
+    % for field, a, m in fields:
+            ${field}: ${field}.unwrap_or_default(),
+    % endfor
+        })
+    }
+
+% for pn in optional:
+    ${first_params[pn].get('description', 'No description provided.') | rust_doc_sanitize(documentationLink), rust_doc_comment, indent_all_but_first_by(1)}
+    ///
+    /// It is passed to all calls.
+    #[inline]
+    pub fn ${mangle_ident(pn)}(mut self, new_value: ${'&'}str) -> ${ThisType} {
+        self.${property(pn)} = Some(new_value.to_string());
+        self
+    }
+% endfor
+
+    /// The maximum amount of calls in flight at once. It defaults to 4.
+    #[inline]
+    pub fn concurrency(mut self, new_value: usize) -> ${ThisType} {
+        self._concurrency = new_value;
+        self
+    }
+
+    /// The delay between the start of one call and the next. It defaults to none, which starts calls as soon as
+    /// fewer than `concurrency()` calls are in flight.
+    #[inline]
+    pub fn interval(mut self, new_value: std::time::Duration) -> ${ThisType} {
+        self._interval = new_value;
+        self
+    }
+}
 </%def>
 
 
@@ -270,10 +517,21 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
     /// Even though the property as already been set when instantiating this call,
     /// we provide this method for API completeness.
     % endif
+    % if p.name == FILTER_PARAM and filter_fields(schemas, m):
+    ///
+    /// The fields in `api::filters::${method_const_name(m.id).lower()}` build the filter with typed values
+    /// instead of writing the expression by hand.
+    % endif
+    % if p.name == USER_PROJECT_PARAM:
+    ///
+    /// This is the project billed for accessing the resource, not to be confused with the quota project
+    /// set on the hub, which is sent in the `X-Goog-User-Project` header. It is validated when the call is made.
+    % endif
     % if part_desc:
     ///
     ${part_desc | rust_doc_sanitize(documentationLink), rust_doc_comment, indent_all_but_first_by(1)}
     % endif
+    #[inline]
     pub fn ${mangle_ident(setter_fn_name(p))}(mut self, ${value_name}: ${InType}) -> ${ThisType} {
         % if p.get('repeated', False):
         self.${property(p.name)}.push(${new_value_copied});
@@ -282,6 +540,65 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
         % endif
         self
     }
+    % if not is_required_property(p):
+
+    /// Like [`Self::${mangle_ident(setter_fn_name(p))}()`], but only if the given value is `Some`, which allows to pass on
+    /// optional values without breaking the chain of calls. With `None`, the call is left unchanged.
+    #[inline]
+    pub fn ${mangle_ident('maybe_' + setter_fn_name(p))}(self, ${value_name}: Option<${InType}>) -> ${ThisType} {
+        match ${value_name} {
+            Some(${value_name}) => self.${mangle_ident(setter_fn_name(p))}(${value_name}),
+            None => self,
+        }
+    }
+    % endif
+</%def>
+
+
+## creates the setters of the delegate, which is a type parameter of the call builder
+###############################################################################################
+###############################################################################################
+<%def name="_delegate_setter_fns(resource, method, m, p, params, c)">\
+<%
+    OtherType = mb_type(resource, method) + "<'a, %s, %%s>" % ', '.join(HUB_TYPE_PARAMETERS)
+    dyn_delegate = "&'a mut dyn %s" % DELEGATE_TYPE
+    request_value = any(op.name == REQUEST_VALUE_PROPERTY_NAME for op in params)
+%>\
+    ${p.description | rust_doc_sanitize(documentationLink), rust_doc_comment, indent_all_but_first_by(1)}
+    ///
+    /// Sets the *delegate* property to the given value.
+    #[inline]
+    pub fn ${mangle_ident(setter_fn_name(p))}(self, new_value: ${dyn_delegate}) -> ${OtherType % dyn_delegate} {
+        self.with_delegate(new_value)
+    }
+
+    /// Sets the *delegate* property to the given value, which may be a delegate or a mutable borrow of it.
+    ///
+    /// Unlike with [`Self::${mangle_ident(setter_fn_name(p))}()`], the delegate is called without dynamic dispatch.
+    /// This allows its methods to be inlined, which can make a difference when making many small calls.
+    #[inline]
+    pub fn with_delegate<ND: ${DELEGATE_TYPE}>(self, new_value: ND) -> ${OtherType % 'ND'} {
+        ${mb_type(resource, method)} {
+            hub: self.hub,
+            % for op in params:
+            % if op.name == DELEGATE_PROPERTY_NAME:
+            ${property(op.name)}: new_value,
+            % else:
+            ${property(op.name)}: self.${property(op.name)},
+            % endif
+            % endfor
+            ${api.properties.params}: self.${api.properties.params},
+            ${api.properties.timeout}: self.${api.properties.timeout},
+            ${api.properties.cancellation}: self.${api.properties.cancellation},
+            ${api.properties.max_retries}: self.${api.properties.max_retries},
+            % if request_value:
+            ${api.properties.stream_request}: self.${api.properties.stream_request},
+            % endif
+            % if method_default_scope(m):
+            ${api.properties.scopes}: self.${api.properties.scopes},
+            % endif
+        }
+    }
 </%def>
 
 
@@ -314,7 +631,7 @@ ${self._setter_fn(resource, method, m, p, part_prop, ThisType, c)}\
             continue
         v = rnd_arg_val_for_type(activity_input_type(schemas, p))
         # we chose to replace random strings with their meaning, as indicated by the name !
-        if is_string_value(v):
+        if is_string_value(v) or p.get('newtype'):
             v = '"%s"' % p.name
         required_args.append(v)
     # end for each required property
@@ -412,7 +729,11 @@ match result {
         |Error::Failure(_)
         |Error::BadRequest(_)
         |Error::FieldClash(_)
-        |Error::JsonDecodeError(_, _) => println!("{}", e),
+        |Error::InvalidProject(_)
+        |Error::Timeout(_)
+        |Error::JsonDecodeError(_, _)
+        |Error::JsonEncodeError(_)
+        |Error::InvalidRequest(_) => println!("{}", e),
     },
     Ok(res) => println!("Success: {:?}", res),
 }
@@ -446,7 +767,7 @@ match result {
     if response_schema:
         if not supports_download:
             reserved_params = ['alt']
-        rtype = 'client::Result<(hyper::Response<hyper::body::Body>, %s)>' % (response_schema.id)
+        rtype = 'client::Result<client::CallResponse<%s>>' % (response_schema.id)
 
     mtype_param = 'RS'
 
@@ -479,6 +800,8 @@ match result {
     auth_call = 'self.hub.auth'
 
     default_scope = method_default_scope(m)
+    # APIs with the legacy 'dataWrapper' feature wrap requests and responses into {"data": ...}
+    data_wrapper = features is not UNDEFINED and 'dataWrapper' in features
 
     # s = '{foo}' -> ('{foo}', 'foo') -> (find_this, replace_with)
     seen = set()
@@ -531,8 +854,7 @@ match result {
         use client::{ToParts, url::Params};
         use std::borrow::Cow;
 
-        let mut dd = client::DefaultDelegate;
-        let mut dlg: &mut dyn client::Delegate = ${delegate}.unwrap_or(&mut dd);
+        let mut dlg = &mut ${delegate};
         dlg.begin(client::MethodInfo { id: "${m.id}",
                                http_method: ${method_name_to_variant(m.httpMethod)} });
 
@@ -545,6 +867,24 @@ match result {
             }
         }
 
+        % for p in field_params:
+        % if p.name == USER_PROJECT_PARAM:
+        % if is_required_property(p):
+        if !client::project::is_valid_project(&self.${property(p.name)}) {
+            ${delegate_finish}(false);
+            return Err(client::Error::InvalidProject(self.${property(p.name)}.clone()));
+        }
+        % else:
+        if let Some(project) = self.${property(p.name)}.as_ref() {
+            if !client::project::is_valid_project(project) {
+                ${delegate_finish}(false);
+                return Err(client::Error::InvalidProject(project.clone()));
+            }
+        }
+        % endif
+        % endif
+        % endfor
+
         let mut params = Params::with_capacity(${len(params) + len(reserved_params)} + ${paddfields}.len());
 <%
     if media_params and 'mediaUpload' in m:
@@ -595,6 +935,11 @@ match result {
         % endfor
 
         params.extend(${paddfields}.iter());
+        % if has_error_format_param(parameters):
+        if params.get("${ERROR_FORMAT_PARAM}").is_none() {
+            params.push("${ERROR_FORMAT_PARAM}", self.hub._error_format.as_str());
+        }
+        % endif
 
         % if response_schema:
         % if supports_download:
@@ -664,14 +1009,43 @@ else {
 
         let url = params.parse_with_url(&url);
 
-        % if request_value:
+        % if request_value and not media_params:
+        let json_mime_type = mime::APPLICATION_JSON;
+        % if data_wrapper:
+        let request_value = std::sync::Arc::new(client::serde::DataWrapper { data: self.${property(REQUEST_VALUE_PROPERTY_NAME)} });
+        % else:
+        let request_value = std::sync::Arc::new(self.${property(REQUEST_VALUE_PROPERTY_NAME)});
+        % endif
+        let request_value_bytes = if self.${api.properties.stream_request} {
+            None
+        } else {
+            let mut value = match json::value::to_value(&*request_value) {
+                Ok(value) => value,
+                Err(err) => return Err(client::Error::JsonEncodeError(err)),
+            };
+            client::remove_json_null_values(&mut value);
+            match json::to_vec(&value) {
+                Ok(bytes) => Some(hyper::body::Bytes::from(bytes)),
+                Err(err) => return Err(client::Error::JsonEncodeError(err)),
+            }
+        };
+        % elif request_value:
         let mut json_mime_type = mime::APPLICATION_JSON;
         let mut request_value_reader =
             {
-                let mut value = json::value::to_value(&self.${property(REQUEST_VALUE_PROPERTY_NAME)}).expect("serde to work");
+                % if data_wrapper:
+                let mut value = match json::value::to_value(&client::serde::DataWrapper { data: &self.${property(REQUEST_VALUE_PROPERTY_NAME)} }) {
+                % else:
+                let mut value = match json::value::to_value(&self.${property(REQUEST_VALUE_PROPERTY_NAME)}) {
+                % endif
+                    Ok(value) => value,
+                    Err(err) => return Err(client::Error::JsonEncodeError(err)),
+                };
                 client::remove_json_null_values(&mut value);
                 let mut dst = io::Cursor::new(Vec::with_capacity(128));
-                json::to_writer(&mut dst, &value).unwrap();
+                if let Err(err) = json::to_writer(&mut dst, &value) {
+                    return Err(client::Error::JsonEncodeError(err));
+                }
                 dst
             };
         let request_size = request_value_reader.seek(io::SeekFrom::End(0)).unwrap();
@@ -684,7 +1058,15 @@ else {
         let mut upload_url: Option<String> = None;
         % endif
 
+        let max_retries = self.${api.properties.max_retries}.or(self.hub._max_retries);
+        let retry_policy = self.hub._retry_policy.clone();
+        let mut attempt = 0u32;
         loop {
+            attempt += 1;
+            if self.${api.properties.cancellation}.as_ref().map_or(false, |token| token.is_cancelled()) {
+                ${delegate_finish}(false);
+                return Err(client::Error::Cancelled);
+            }
             % if default_scope:
             let token = match ${auth_call}.get_token(&self.${api.properties.scopes}.iter().map(String::as_str).collect::<Vec<_>>()[..]).await {
                 Ok(token) => token,
@@ -699,7 +1081,7 @@ else {
                 }
             };
             % endif
-            % if request_value:
+            % if request_value and media_params:
             request_value_reader.seek(io::SeekFrom::Start(0)).unwrap();
             % endif
             let mut req_result = {
@@ -729,12 +1111,23 @@ else {
                 };
             % endif
                 let client = &self.hub.client;
+                if let Some(limiter) = self.hub._rate_limiter.as_ref() {
+                    if client::cancel::cancellable(self.${api.properties.cancellation}.as_ref(), limiter.acquire()).await.is_none() {
+                        ${delegate_finish}(false);
+                        return Err(client::Error::Cancelled);
+                    }
+                }
                 dlg.pre_request();
+                dlg.request_url(url.as_str());
                 let mut req_builder = hyper::Request::builder()
                     .method(${method_name_to_variant(m.httpMethod)})
                     .uri(url.as_str())
                     .header(USER_AGENT, self.hub._user_agent.clone());
 
+                if let Some(project) = self.hub._quota_project.as_ref() {
+                    req_builder = req_builder.header(client::project::QUOTA_PROJECT_HEADER, project.as_str());
+                }
+
                 % if default_scope:
                 if let Some(token) = token.as_ref() {
                     req_builder = req_builder.header(AUTHORIZATION, format!("Bearer {}", token));
@@ -749,14 +1142,22 @@ else {
                 % endif
 
                 % if request_value:
-                    % if not simple_media_param:
+                    % if not media_params:
+                        let req_builder = req_builder.header(CONTENT_TYPE, json_mime_type.to_string());
+                        let request = match request_value_bytes.as_ref() {
+                            Some(bytes) => req_builder
+                                .header(CONTENT_LENGTH, bytes.len() as u64)
+                                .body(hyper::body::Body::from(bytes.clone())),
+                            None => req_builder.body(client::json_body::streamed(request_value.clone())),
+                        }\
+                    % elif not simple_media_param:
                         let request = req_builder
                         .header(CONTENT_TYPE, json_mime_type.to_string())
                         .header(CONTENT_LENGTH, request_size as u64)
                         .body(hyper::body::Body::from(request_value_reader.get_ref().clone()))\
                     % else:
                         let mut body_reader_bytes = vec![];
-                        body_reader.read_to_end(&mut body_reader_bytes).unwrap();
+                        body_reader.read_to_end(&mut body_reader_bytes)?;
                         let request = req_builder
                             .header(CONTENT_TYPE, content_type.to_string())
                             .body(hyper::body::Body::from(body_reader_bytes))\
@@ -780,7 +1181,31 @@ else {
                 % endif
 ;
 
-                client.request(request.unwrap()).await
+                let request = match request {
+                    Ok(request) => client.request(request),
+                    Err(err) => {
+                        ${delegate_finish}(false);
+                        return Err(client::Error::InvalidRequest(err));
+                    }
+                };
+                let request = client::cancel::cancellable(self.${api.properties.cancellation}.as_ref(), request);
+                let res = match self.${api.properties.timeout}.or(self.hub._default_timeout) {
+                    Some(timeout) => match tokio::time::timeout(timeout, request).await {
+                        Ok(res) => res,
+                        Err(_) => {
+                            ${delegate_finish}(false);
+                            return Err(client::Error::Timeout(timeout));
+                        }
+                    },
+                    None => request.await,
+                };
+                match res {
+                    Some(res) => res,
+                    None => {
+                        ${delegate_finish}(false);
+                        return Err(client::Error::Cancelled);
+                    }
+                }
 
 </%block>\
                 % if resumable_media_param:
@@ -790,14 +1215,32 @@ else {
 
             match req_result {
                 Err(err) => {
-                    if let client::Retry::After(d) = dlg.http_error(&err) {
-                        sleep(d).await;
+                    if self.hub._tracing {
+                        client::trace::http_error("${m.id}", attempt, &err);
+                    }
+                    if let client::Retry::After(d) = dlg.http_error(&err)
+                        .or_else(|| retry_policy.as_ref().map_or(client::Retry::Abort, |p| p.http_error(&${method_name_to_variant(m.httpMethod)}, attempt)))
+                        .within(attempt - 1, max_retries) {
+                        if let Some(hook) = self.hub._on_retry.as_ref() {
+                            hook(attempt, &client::retry::RetryReason::Http(&err), d);
+                        }
+                        if self.hub._tracing {
+                            client::trace::retry("${m.id}", attempt, d);
+                        }
+                        if client::cancel::cancellable(self.${api.properties.cancellation}.as_ref(), sleep(d)).await.is_none() {
+                            ${delegate_finish}(false);
+                            return Err(client::Error::Cancelled);
+                        }
                         continue;
                     }
                     ${delegate_finish}(false);
                     return Err(client::Error::HttpError(err))
                 }
                 Ok(mut res) => {
+                    dlg.http_response(&res);
+                    if self.hub._tracing {
+                        client::trace::response("${m.id}", attempt, res.status());
+                    }
                     if !res.status().is_success() {
                         let res_body_string = client::get_body_as_string(res.body_mut()).await;
                         let (parts, _) = res.into_parts();
@@ -806,8 +1249,25 @@ else {
 
                         let server_response = json::from_str::<serde_json::Value>(&res_body_string).ok();
 
-                        if let client::Retry::After(d) = dlg.http_failure(&restored_response, server_response.clone()) {
-                            sleep(d).await;
+                        if let client::Retry::After(d) = dlg.http_failure(&restored_response, server_response.clone())
+                            .or_else(|| retry_policy.as_ref().map_or(client::Retry::Abort, |p| {
+                                p.http_failure(&${method_name_to_variant(m.httpMethod)}, attempt, &restored_response, server_response.as_ref())
+                            }))
+                            .within(attempt - 1, max_retries) {
+                            if let Some(hook) = self.hub._on_retry.as_ref() {
+                                let reason = client::retry::RetryReason::Failure {
+                                    status: restored_response.status(),
+                                    error: server_response.as_ref(),
+                                };
+                                hook(attempt, &reason, d);
+                            }
+                            if self.hub._tracing {
+                                client::trace::retry("${m.id}", attempt, d);
+                            }
+                            if client::cancel::cancellable(self.${api.properties.cancellation}.as_ref(), sleep(d)).await.is_none() {
+                                ${delegate_finish}(false);
+                                return Err(client::Error::Cancelled);
+                            }
                             continue;
                         }
 
@@ -818,6 +1278,11 @@ else {
                             None => Err(client::Error::Failure(restored_response)),
                         }
                     }
+                    if let Some(max_age) = self.hub._revision_max_age {
+                        if let Some(drift) = client::revision::revision_drift(crate::API_REVISION, res.headers(), max_age) {
+                            dlg.api_revision_drift(&drift);
+                        }
+                    }
                     % if resumable_media_param:
                     if protocol == ${PROTOCOL_TYPE_MAP[resumable_media_param.protocol]} {
                         ${READER_SEEK | indent_all_but_first_by(6)}
@@ -838,7 +1303,8 @@ else {
                                 url: url_str,
                                 reader: &mut reader,
                                 media_type: reader_mime_type.clone(),
-                                content_length: size
+                                content_length: size,
+                                max_retries,
                             }.upload().await
                         };
                         match upload_result {
@@ -873,9 +1339,15 @@ if enable_resource_parsing \
                     % endif
 {
                         let res_body_string = client::get_body_as_string(res.body_mut()).await;
+                        dlg.response_body(&res_body_string);
 
+                    % if data_wrapper:
+                        match json::from_str::<client::serde::DataWrapper<_>>(&res_body_string) {
+                            Ok(decoded) => client::CallResponse::new(res, decoded.data),
+                    % else:
                         match json::from_str(&res_body_string) {
-                            Ok(decoded) => (res, decoded),
+                            Ok(decoded) => client::CallResponse::new(res, decoded),
+                    % endif
                             Err(err) => {
                                 dlg.response_json_decode_error(&res_body_string, &err);
                                 return Err(client::Error::JsonDecodeError(res_body_string, err));
@@ -883,7 +1355,7 @@ if enable_resource_parsing \
                         }
                     }\
                     % if supports_download:
- else { (res, Default::default()) }\
+ else { client::CallResponse::new(res, Default::default()) }\
                     % endif
 ;
                 % else:
@@ -4,9 +4,10 @@
                       to_fqan, indent_all_but_first_by, is_repeated_property, is_required_property,
                       activity_input_type, TREF, IO_REQUEST, schema_to_required_property, 
                       rust_copy_value_s, organize_params, REQUEST_VALUE_PROPERTY_NAME,
-                      build_all_params, rb_type_params_s, hub_type_params_s, mb_type_params_s, mb_additional_type_params, 
+                      build_all_params, rb_type_params_s, hub_type_params_s, mb_additional_type_params, 
                       struct_type_bounds_s, METHODS_RESOURCE, SPACES_PER_TAB, prefix_all_but_first_with,
-                      METHODS_BUILDER_MARKER_TRAIT, remove_empty_lines, method_default_scope, rust_doc_sanitize)
+                      METHODS_BUILDER_MARKER_TRAIT, remove_empty_lines, method_default_scope, rust_doc_sanitize,
+                      snapshot_groups, snapshot_type)
 %>\
 <%namespace name="util" file="../../../lib/util.mako"/>\
 <%namespace name="lib" file="lib.mako"/>\
@@ -60,17 +61,18 @@ impl${rb_params} ${ThisType} {
 
     # skip part if we have a request resource. Only resources can have parts
     # that we can easily deduce
-    params, request_value = build_all_params(c, m)
+    params, request_value = build_all_params(c, m, api.get('newtype_identifiers', False))
     required_props, optional_props, part_prop = organize_params(params, request_value)
 
     method_args = ''
     if required_props:
         method_args = ', ' + ', '.join('%s: %s' % (mangle_ident(p.name), activity_input_type(schemas, p)) for p in required_props)
 
-    mb_tparams = mb_type_params_s(m)
+    # the method builder uses the default delegate until another one is set
+    mb_tparams = rb_type_params_s(resource, c)
     # we would could have information about data requirements for each property in it's dict.
     # for now, we just hardcode it, and treat the entries as way to easily change param names
-    assert len(api.properties) == 2, "Hardcoded for now, thanks to scope requirements"
+    assert len(api.properties) == 6, "Hardcoded for now, thanks to scope and request requirements"
 
     type_params = ''
     if mb_additional_type_params(m):
@@ -121,6 +123,9 @@ impl${rb_params} ${ThisType} {
             % endfor
 % for prop_key, custom_name in api.properties.items():
             % if prop_key == 'scopes' and not method_default_scope(m):
+<% continue %>\
+            % endif
+            % if prop_key == 'stream_request' and not request_value:
 <% continue %>\
             % endif
             ${custom_name}: Default::default(),
@@ -128,5 +133,23 @@ impl${rb_params} ${ThisType} {
         }
     }
     % endfor ## for each activity
+    % for sub, activities, required, optional in snapshot_groups(c, resource):
+
+    /// Create a builder which fetches the ${put_and(sorted('`%s()`' % mangle_ident(a) for a, _ in activities))}
+    /// reports concurrently, and combines them into a single [`${snapshot_type(resource, sub)}`].
+    pub fn ${mangle_ident(sub)}(&self${''.join(', %s: &str' % mangle_ident(pn) for pn in required)}) -> ${mb_type(resource, sub + '.snapshot')}${rb_params} {
+        ${mb_type(resource, sub + '.snapshot')} {
+            hub: self.hub,
+            % for pn in required:
+            ${property(pn)}: ${mangle_ident(pn)}.to_string(),
+            % endfor
+            % for pn in optional:
+            ${property(pn)}: Default::default(),
+            % endfor
+            _concurrency: 4,
+            _interval: Default::default(),
+        }
+    }
+    % endfor ## for each snapshot group
 }
 </%def>
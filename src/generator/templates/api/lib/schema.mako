@@ -1,10 +1,13 @@
 <%!
-    from generator.lib.util import (schema_markers, rust_doc_comment, mangle_ident, to_serde_type, to_rust_type, put_and,
+    from generator.lib.util import (schema_markers, rust_doc_comment, mangle_ident, to_serde_type, to_json_schema_type, to_rust_type, put_and,
                       IO_TYPES, activity_split, enclose_in, REQUEST_MARKER_TRAIT, mb_type, indent_all_but_first_by,
                       NESTED_TYPE_SUFFIX, RESPONSE_MARKER_TRAIT, split_camelcase_s, METHODS_RESOURCE,
                       PART_MARKER_TRAIT, canonical_type_name, TO_PARTS_MARKER, UNUSED_TYPE_MARKER, is_schema_with_optionals,
-                      rust_doc_sanitize, items)
+                      rust_doc_sanitize, items, paged_report_items_prop, is_date_schema, has_next_page_token,
+                      schema_struct_properties, constraint_checks, validated_property_schema,
+                      extra_properties_field, to_extern_crate_name)
 %>\
+<%namespace name="util" file="../../../lib/util.mako"/>\
 ## Build a schema which must be an object
 ###################################################################################################################
 ###################################################################################################################
@@ -20,12 +23,22 @@ ${struct} {
     <%
         rust_ty = to_rust_type(schemas, s.id, pn, p, allow_optionals=allow_optionals)
         serde_ty, use_custom_serde = to_serde_type(schemas, s.id, pn, p, allow_optionals=allow_optionals)
+        json_schema_ty, use_custom_json_schema = to_json_schema_type(schemas, s.id, pn, p, allow_optionals=allow_optionals)
     %>
     % if use_custom_serde:
     #[serde_as(as = "${serde_ty}")]
     % endif
+    % if use_custom_json_schema:
+    #[cfg_attr(feature = "jsonschema", schemars(with = "${json_schema_ty}"))]
+    % endif
     pub ${mangle_ident(pn)}: ${rust_ty},
 % endfor
+% if extra_properties_field(s):
+    /// The fields not listed above, like those with dynamic names, which are kept as they are so they
+    /// survive reading and writing the object again.
+    #[serde(flatten)]
+    pub ${extra_properties_field(s)}: HashMap<String, json::Value>,
+% endif
 }
 % elif 'additionalProperties' in s:
 ${struct}(pub ${to_rust_type(schemas, s.id, NESTED_TYPE_SUFFIX, s, allow_optionals=allow_optionals)});
@@ -59,7 +72,7 @@ ${struct} { _never_set: Option<bool> }
 ## 's' contains the schema structure from json to build
 ###################################################################################################################
 ###################################################################################################################
-<%def name="new(s, c)">\
+<%def name="new(s, c, validated)">\
 <% 
     markers = schema_markers(s, c, transitive=True)
     # We always need Serialization support, as others might want to serialize the response, even though we will 
@@ -85,6 +98,7 @@ ${doc(s, c)}\
 </%block>
 #[serde_with::serde_as(crate = "::client::serde_with")]
 #[derive(${', '.join(traits)})]
+#[cfg_attr(feature = "jsonschema", derive(schemars::JsonSchema))]
 % if s.type == 'object':
 ${_new_object(s, s.get('properties'), c, allow_optionals)}\
 % elif s.type == 'array':
@@ -112,6 +126,12 @@ impl ${marker_trait} for ${s_type} {}
 % endif
 % endfor
 
+% if s.type == 'object' and s.get('properties') and 'variant' not in s:
+impl client::FieldNames for ${s_type} {
+    const FIELD_NAMES: &'static [&'static str] = &[${', '.join('"%s"' % pn for pn, _ in items(s.properties))}];
+}
+
+% endif
 % if TO_PARTS_MARKER in nt_markers and allow_optionals:
 impl ${TO_PARTS_MARKER} for ${s_type} {
     /// Return a comma separated list of members that are currently set, i.e. for which `self.member.is_some()`.
@@ -135,6 +155,173 @@ impl ${TO_PARTS_MARKER} for ${s_type} {
     }
 }
 % endif
+<%
+    items_pn = s.type == 'object' and paged_report_items_prop(s)
+    if items_pn:
+        items_rt = to_rust_type(schemas, s.id, items_pn, s.properties[items_pn], allow_optionals=allow_optionals)
+        items_rt = items_rt[len('Option<'):-1] if allow_optionals else items_rt
+        assert items_rt.startswith('Vec<')
+%>\
+% if items_pn:
+
+impl client::PagedResponse for ${s_type} {
+    type Item = ${items_rt[len('Vec<'):-1]};
+
+    fn next_page_token(&self) -> Option<${'&'}str> {
+    % if allow_optionals:
+        self.next_page_token.as_deref()
+    % else:
+        Some(self.next_page_token.as_str())
+    % endif
+    }
+
+    fn total_size(&self) -> Option<u64> {
+    % if allow_optionals:
+        self.total_size.and_then(|n| std::convert::TryFrom::try_from(n).ok())
+    % else:
+        std::convert::TryFrom::try_from(self.total_size).ok()
+    % endif
+    }
+
+    fn into_items(self) -> ${items_rt} {
+    % if allow_optionals:
+        self.${mangle_ident(items_pn)}.unwrap_or_default()
+    % else:
+        self.${mangle_ident(items_pn)}
+    % endif
+    }
+}
+% endif
+% if s.type == 'object' and has_next_page_token(s):
+
+impl ${s_type} {
+    /// The token to fetch the next page with, unless this is the last page. An empty token counts
+    /// as none.
+    pub fn next_page_token_ref(&self) -> Option<${'&'}str> {
+    % if allow_optionals:
+        self.next_page_token.as_deref().filter(|t| !t.is_empty())
+    % else:
+        Some(self.next_page_token.as_str()).filter(|t| !t.is_empty())
+    % endif
+    }
+
+    /// Returns true if there are more pages to fetch after this one.
+    pub fn has_more_pages(&self) -> bool {
+        self.next_page_token_ref().is_some()
+    }
+
+<%
+    list_pns = [pn for pn, p in items(s.properties)
+                if to_rust_type(schemas, s.id, pn, p, allow_optionals=allow_optionals).startswith(
+                    allow_optionals and 'Option<Vec<' or 'Vec<')]
+    some = allow_optionals and (lambda v: 'Some(%s)' % v) or (lambda v: v)
+%>\
+    /// Merge `other`, the page following this one, into this page. The items of all lists and maps
+    /// are appended, while other fields are replaced by those set in `other`. The token of the next
+    /// page is always taken from `other`, so the merged pages have none once the last page was merged.
+    % if list_pns:
+<% ln = mangle_ident(list_pns[0]) %>\
+    ///
+    /// ```
+    /// # extern crate ${to_extern_crate_name(util.crate_name())} as ${util.library_name()};
+    /// use ${util.library_name()}::api::${s_type};
+    ///
+    /// let mut pages = ${s_type} {
+    ///     ${ln}: ${some('vec![Default::default()]')},
+    ///     next_page_token: ${some('"1".to_string()')},
+    ///     ..Default::default()
+    /// };
+    /// pages.merge(${s_type} {
+    ///     ${ln}: ${some('vec![Default::default(), Default::default()]')},
+    ///     next_page_token: ${some('"2".to_string()')},
+    ///     ..Default::default()
+    /// });
+    % if allow_optionals:
+    /// assert_eq!(pages.${ln}.map(|items| items.len()), Some(3));
+    /// assert_eq!(pages.next_page_token.as_deref(), Some("2"));
+    % else:
+    /// assert_eq!(pages.${ln}.len(), 3);
+    /// assert_eq!(pages.next_page_token, "2");
+    % endif
+    /// ```
+    % endif
+    pub fn merge(&mut self, other: ${s_type}) {
+    % for pn, p in items(s.properties):
+<%
+        mn = mangle_ident(pn)
+        rt = to_rust_type(schemas, s.id, pn, p, allow_optionals=allow_optionals)
+        inner_rt = rt[len('Option<'):-1] if allow_optionals and rt.startswith('Option<') else rt
+        is_collection = inner_rt.startswith('Vec<') or inner_rt.startswith('HashMap<')
+%>\
+        % if pn == 'nextPageToken' or not allow_optionals and not is_collection:
+        self.${mn} = other.${mn};
+        % elif not allow_optionals:
+        self.${mn}.extend(other.${mn});
+        % elif is_collection:
+        match (&mut self.${mn}, other.${mn}) {
+            (Some(items), Some(more)) => items.extend(more),
+            (items, more) => if more.is_some() { *items = more },
+        }
+        % else:
+        if other.${mn}.is_some() {
+            self.${mn} = other.${mn};
+        }
+        % endif
+    % endfor
+    % if extra_properties_field(s):
+        self.${extra_properties_field(s)}.extend(other.${extra_properties_field(s)});
+    % endif
+    }
+}
+% endif
+% if s.id in validated:
+<%
+    checks = list()
+    for pn, p in items(schema_struct_properties(s)):
+        path = '&client::validate::field_path(path, "%s")' % pn
+        value = '&self.%s' % mangle_ident(pn)
+        checks += constraint_checks(p, path, value, 'errors')
+        if validated_property_schema(schemas, s, pn, p, validated):
+            checks.append('client::Validate::validate_at(%s, %s, errors);' % (value, path))
+%>
+
+impl client::Validate for ${s_type} {
+    fn validate_at(&self, path: ${'&'}str, errors: &mut Vec<client::ValidationError>) {
+    % for check in checks:
+        ${check}
+    % endfor
+    }
+}
+% endif
+% if s.type == 'object' and is_date_schema(s):
+<%
+    opt = (lambda v: 'Some(%s)' % v) if allow_optionals else (lambda v: v)
+    field = (lambda v: v) if allow_optionals else (lambda v: 'Some(%s)' % v)
+%>
+impl ${s_type} {
+    /// Returns the date as `chrono::NaiveDate`, or `None` unless year, month and day are all set
+    /// and form a valid date. Partial dates, like a year alone or a month and day without a year,
+    /// are `None` as well, as a zero in any of the fields means it is unset.
+    pub fn to_naive_date(&self) -> Option<client::chrono::NaiveDate> {
+        match (${field('self.year')}, ${field('self.month')}, ${field('self.day')}) {
+            (Some(year), Some(month), Some(day)) if year != 0 && month > 0 && day > 0 => {
+                client::chrono::NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+            }
+            _ => None,
+        }
+    }
+
+    /// Creates a full date from the given `chrono::NaiveDate`.
+    pub fn from_naive_date(date: client::chrono::NaiveDate) -> ${s_type} {
+        use client::chrono::Datelike;
+        ${s_type} {
+            year: ${opt('date.year()')},
+            month: ${opt('date.month() as i32')},
+            day: ${opt('date.day() as i32')},
+        }
+    }
+}
+% endif
 </%def>
 
 #########################################################################################################
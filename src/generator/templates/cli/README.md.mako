@@ -1,6 +1,16 @@
 <%
-    from generator.lib.util import (markdown_comment, new_context)
-    from generator.lib.cli import (CONFIG_DIR, CONFIG_DIR_FLAG, SCOPE_FLAG, application_secret_path, DEBUG_FLAG)
+    from generator.lib.util import (markdown_comment, new_context, has_error_format_param)
+    from generator.lib.cli import (CONFIG_DIR, CONFIG_DIR_FLAG, CONFIG_FILE_FLAG, SCOPE_FLAG, application_secret_path, DEBUG_FLAG, RAW_ERRORS_FLAG, DRY_RUN_FLAG,
+                                   VERBOSE_FLAG, VERBOSE_SHORT_FLAG, has_paged_methods, ALL_PAGES_FLAG,
+                                   STATE_FILE_FLAG, RESUME_FLAG, CONTINUE_FROM_FLAG, OUT_ARG, OUTPUT_FLAG,
+                                   PROFILE_FLAG, SELECT_PROFILE_FLAG, has_fan_out_methods, CONCURRENCY_FLAG,
+                                   DEFAULT_CONCURRENCY, PARAM_FLAG,
+                                   has_report_methods, REPORT_FORMAT_FLAG, ON_ERROR_FLAG, HUMAN_FLAG,
+                                   STREAM_OUTPUT_FLAG, MAX_PAGES_FLAG, ONLY_FIELDS_FLAG,
+                                   STRIP_FIELDS_FLAG, STRICT_FLAG, TIMING_FLAG, VALIDATE_ONLY_FLAG,
+                                   SPLIT_PAGES_FLAG, has_destructive_methods, ASSUME_YES_FLAG, ASSUME_YES_SHORT_FLAG,
+                                   RECORD_FLAG, SORT_BY_FLAG, WHERE_FLAG, REPLAY_FLAG, SERVE_FLAG, SERVE_ALLOW_REMOTE_FLAG, INTERACTIVE_FLAG, STRUCT_FLAG, OUTPUT_SCHEMA_FLAG, MAX_RETRIES_FLAG, ADDRESS_FAMILY_FLAG, HAPPY_EYEBALLS_FLAG,
+                                   OUTPUT_FORMAT_FLAG, ERROR_FORMAT_FLAG)
 
     c = new_context(schemas, resources)
 %>\
@@ -39,10 +49,29 @@ This documentation was generated from the *${util.canonical_name()}* API at revi
 ${argparse.grammar(c)}
 ```
 
+Parameters taking a single value which are given more than once with `-${PARAM_FLAG}` only use their last value, and a warning
+says so. With `--${STRICT_FLAG}`, this is an error instead.
+
+Methods taking a request structure can ask for its fields one after the other with `--${INTERACTIVE_FLAG}`, instead of setting
+them with `-${STRUCT_FLAG}`. Each field is shown with its type and the values it allows, and an empty answer skips it.
+% if has_destructive_methods(c):
+
+Methods which destroy data, like deletes, ask for confirmation before the call is made if the CLI runs in a terminal.
+Their help says so. To skip the question, for instance in scripts started from a terminal, pass
+`-${ASSUME_YES_SHORT_FLAG}`/`--${ASSUME_YES_FLAG}`. Without a terminal, no question is asked.
+% endif
+
 # Configuration
 
 The program will store all persistent data in the `${CONFIG_DIR}` directory in *JSON* files prefixed with `${util.program_name()}-`.  You can change the directory used to store configuration with the `--${CONFIG_DIR_FLAG}` flag on a per-invocation basis.
 
+Defaults for the global flags can be kept in a *TOML* file, which is `${util.program_name()}-config.toml` in the configuration directory, or the one given with `--${CONFIG_FILE_FLAG}`. It has one key per flag, named like its long form, and flags given on the command line take precedence.
+
+```toml
+${'#'} use the same account each time
+profile = "work"
+```
+
 More information about the various kinds of persistent data are given in the following paragraphs.
 
 # Authentication
@@ -54,6 +83,8 @@ If not set, the system will automatically select the smallest feasible scope, e.
 method that is read-only, it will ask only for a read-only scope. 
 You may use the `--${SCOPE_FLAG}` flag to specify a scope directly. 
 All applicable scopes are documented in the respective method's CLI documentation.
+A method which accepts none of the given scopes is reported before making the call, and scopes it doesn't
+accept along with others are warned about.
 
 The first time a scope is used, the user is asked for permission. Follow the instructions given 
 by the CLI to grant permissions, or to decline.
@@ -64,6 +95,12 @@ is necessary.
 
 To revoke granted authentication, please refer to the [official documentation][revoke-access].
 
+${'##'} Profiles
+
+To use multiple accounts side by side, pass `--${PROFILE_FLAG} <name>` to keep the tokens of each account in a profile
+of its own, whose name may only contain letters, digits, `_` and `-`. Without it, the `default` profile is used. If there is more than one profile, `--${SELECT_PROFILE_FLAG}`
+lists them and lets you pick one when running in a terminal, and falls back to the `default` profile otherwise.
+
 # Application Secrets
 
 In order to allow any application to use Google services, it will need to be registered using the 
@@ -81,6 +118,96 @@ was enabled for it. Such a secret file can be downloaded in the *Google Develope
 
 Learn more about how to setup Google projects and enable APIs using the [official documentation][google-project-new].
 
+% if has_paged_methods(c):
+
+# Paging
+
+Methods returning results in pages fetch only the first page by default. With `--${ALL_PAGES_FLAG}`, all pages are
+fetched one after another, and merged into a single result once the last page arrived. To need as few requests as
+possible, each page is requested with the maximum size documented for the method, unless a page size is given
+explicitly with `-${PARAM_FLAG} page-size=<size>`. A page size above the documented maximum would silently be lowered by
+the API, so a warning says so, or an error with `--${STRICT_FLAG}`.
+
+Merging needs all pages in memory. To pass the results on while they arrive instead, add `--${STREAM_OUTPUT_FLAG}`, which
+writes each item of a page as a single line of JSON as soon as the page was fetched.
+
+To see exactly what each page returned, for instance when looking into paging issues, `--${SPLIT_PAGES_FLAG} <dir>`
+writes each page as is to a file of its own in the given directory, named `page-0001.json`, `page-0002.json` and so on.
+The pages keep their `nextPageToken` and are not merged.
+
+The API doesn't always return items in the same order, which makes it hard to compare exports of different runs.
+`--${SORT_BY_FLAG} <field>` sorts the items of the merged pages by the given field, like `deviceId` or a dotted path like
+`device.id`, so the same items always produce the same output. Items without the field come last, in the order they
+arrived. As the pages need to be merged for that, it can't be combined with `--${STATE_FILE_FLAG}`, `--${STREAM_OUTPUT_FLAG}` or
+`--${SPLIT_PAGES_FLAG}`.
+
+Server-side filters aren't available for every method, and can't express every condition. `--${WHERE_FLAG} <expression>`
+keeps only the items of the results a small expression holds for, like `--${WHERE_FLAG} 'cpuInfo[0].maxClockSpeed > 2000000'`.
+Fields of an item are referred to by dotted paths, with `[n]` picking the element of an array, and are compared with `==`,
+`!=`, `<`, `<=`, `>` and `>=` to strings in quotes, numbers, `true`, `false`, `null` or other fields. Comparisons are
+combined with `&&`, `||`, `!` and parentheses, and a field on its own holds if it is set and neither `null` nor `false`.
+Strings holding numbers, like 64 bit integers, are compared to numbers by their value. Missing fields are `null`.
+All items are still fetched from the API, but only the matching ones are written or merged.
+
+To fetch pages one call at a time, like in a script fetching what's new every so often, write each response to a file and
+pass it to the next call with `--${CONTINUE_FROM_FLAG} <file>`. The call then fetches the page following that response,
+using its `nextPageToken`, so the token doesn't need to be copied by hand. It fails once the response was the last page,
+and when a page token is given as parameter as well.
+
+To explore large collections without fetching all of them, `--${MAX_PAGES_FLAG} <count>` stops after the given amount of
+pages. The results fetched until then are written as usual, and a note on standard error tells that they were truncated.
+With `--${STATE_FILE_FLAG}`, the state is kept in that case, so `--${RESUME_FLAG}` continues with the next page.
+
+Very large exports can be made resumable by adding `--${STATE_FILE_FLAG} <path>`. Each page is then written as a single
+line of JSON as soon as it arrived, and the token of the next page is kept in the state file. Should the export be
+interrupted, running the same command again with `--${RESUME_FLAG}` continues with the page recorded in the state file,
+and appends to the output file given with `-${OUTPUT_FLAG} <${OUT_ARG}>`. The state file is removed once all pages are done.
+
+As no page can be fetched without the one before it, a failed page always ends the export. With `--${ON_ERROR_FLAG} continue`,
+the default, the pages merged until then are written before exiting with a non-zero status, while `--${ON_ERROR_FLAG} stop`
+discards them. Pages already written with `--${STATE_FILE_FLAG}` are kept either way.
+% endif
+% if has_fan_out_methods(c):
+
+# Fan-out
+
+Get-style methods taking a single name can be called for many names at once, by passing `@<file>` instead of the name.
+The file is expected to contain one name per line, and `@-` reads the names from standard input. Up to
+`--${CONCURRENCY_FLAG} <limit>` calls are made at once, ${DEFAULT_CONCURRENCY} by default, and each result is written as a single line of
+JSON, in the order of the names. Failed calls are reported on standard error as well as in the output, as a line of the
+form `{"name": "<name>", "error": "<message>"}`, without stopping the remaining ones. With `--${ON_ERROR_FLAG} stop`, no further
+calls are made after the first failure instead. Either way, the exit status is non-zero if any call failed.
+
+For a quick look at a resource, `--${HUMAN_FLAG}` writes a readable summary instead of its JSON. Each field at the top
+level of the resource gets a line of its own, starting with fields like its name and display name, while nested objects
+and lists are summarised by their size.
+% endif
+% if has_report_methods(c):
+
+# Reports
+
+The responses of count methods can be written in the [Prometheus text exposition format][prometheus-format] with
+`--${REPORT_FORMAT_FLAG} prometheus`, which allows to use the CLI as a one-shot exporter. Each count becomes a gauge named
+after its field. Counts in a list become samples of a gauge named after the list, labelled with the remaining fields of
+their list entry. With `--${STATE_FILE_FLAG}`, pages are still written as JSON.
+
+[prometheus-format]: https://prometheus.io/docs/instrumenting/exposition_formats/#text-based-format
+% endif
+
+# Output
+
+Responses can be pruned before they are written, without an extra step through tools like `jq`. `--${ONLY_FIELDS_FLAG} <paths>`
+keeps only the given comma separated paths, like `items.name,nextPageToken`, and `--${STRIP_FIELDS_FLAG} <paths>` drops
+them, for instance to leave out large lists nobody reads. Lists are looked through, so `items.name` is the name of each
+item. Unlike the `fields` parameter some methods have, this works with any method, but the whole response is transferred.
+
+To know which paths there are, add `--${OUTPUT_SCHEMA_FLAG}` to a call. Instead of making it, the structure of its response is
+written, with the name and type of each field, and nested fields indented below theirs. Neither the arguments of the method
+nor authentication are needed for that, like in `${util.program_name()} --${OUTPUT_SCHEMA_FLAG} <resource> <method>`.
+
+For readers consuming the output while it is written, like `tail -f` or a monitoring agent, `--${OUTPUT_FORMAT_FLAG} ndjson-stream`
+writes each item as a line of compact JSON and flushes the output right after. The items of a list are written as each page
+arrives, with or without `--${ALL_PAGES_FLAG}`, and any other response is a single line.
 
 # Debugging
 
@@ -88,10 +215,64 @@ Even though the CLI does its best to provide usable error messages, sometimes it
 what exactly led to a particular issue. This is done by allowing all client-server communication to be 
 output to standard error *as-is*.
 
-The `--${DEBUG_FLAG}` flag will print errors using the `Debug` representation to standard error.
+Errors returned by the API are printed as `ERROR <code> (<status>): <message>`, followed by their details, like the reason
+and metadata of an `ErrorInfo` or the localized message meant for users, indented below. `--${RAW_ERRORS_FLAG}` prints them as
+the JSON they came as instead, and the `--${DEBUG_FLAG}` flag will print errors using the `Debug` representation to standard error.
+% if has_error_format_param(parameters):
+Errors are requested in the format of a `google.rpc.Status`, which holds such details. `--${ERROR_FORMAT_FLAG} v1` requests the
+legacy format instead, as does `-${PARAM_FLAG} $-xgafv=1` for a single call.
+% endif
+
+To check a command without making the call, add `--${DRY_RUN_FLAG}`. All arguments are validated, including the
+conversion of the request body into its typed representation, and any issue is reported as usual.
+`--${VALIDATE_ONLY_FLAG}` goes one step further, and also checks the parameters and the request body against the
+constraints documented by the API, like the pattern of a resource name, the values an enumeration allows or the fields
+an `order-by` parameter may name, suggesting the closest one for a typo.
 
 You may consider redirecting standard error into a file for ease of use, e.g. `${util.program_name()} --${DEBUG_FLAG} <resource> <method> [options] 2>debug.txt`.
 
+To follow what happens on the wire, use `-${VERBOSE_SHORT_FLAG}`/`--${VERBOSE_FLAG}`, which can be repeated to increase the level of detail:
+
+* `-${VERBOSE_SHORT_FLAG}` logs each request attempt along with its status code and latency, as well as the outcome of the call.
+* `-${VERBOSE_SHORT_FLAG * 2}` additionally logs the response headers.
+* `-${VERBOSE_SHORT_FLAG * 3}` additionally logs the request body and the body of error responses.
+
+To find out where the time of a call goes, use `--${TIMING_FLAG}`. Once the call finished, it writes how long it took
+until the name was resolved, the connection was established and secured, the first byte of the response arrived, and the
+call was done, all measured from the start of the request, once the token was obtained. Phases which were skipped, like connecting when a connection could be reused,
+are left out.
+
+To turn real calls into fixtures for tests, use `--${RECORD_FLAG} <dir>`. Each call which got a response is saved into a file
+of its own in the given directory, like `0001-<method>.json`, with the HTTP method and URL of the request, its body, the
+status code and the body of the response. API keys are left out of the URL. Recording into a directory which already holds
+fixtures numbers the new ones after them.
+
+With `--${REPLAY_FLAG} <dir>`, calls are answered with these fixtures instead of being sent to the API, which needs neither
+network access nor authentication. This allows to demonstrate or test the CLI offline. A call is answered with the first
+fixture recorded for the same HTTP method and URL which wasn't used yet, or the last one if all were used. Calls without
+such a fixture fail with a `404 Not Found` error.
+
+To point tools which know nothing about authentication at the API, like `curl`, run `${util.program_name()} --${SERVE_FLAG} 127.0.0.1:8080`
+instead of a method. The CLI then forwards each request received on that address to the API, authorized with its own token,
+and logs it along with the status it was answered with to standard error. Only `GET` and `HEAD` requests are forwarded, any
+other request fails with `405 Method Not Allowed`. As anyone who can reach the address acts with the token, only loopback
+addresses are accepted, unless `--${SERVE_ALLOW_REMOTE_FLAG}` is given as well. Requests whose `Host` isn't the loopback address
+are answered with `403 Forbidden`, so web pages can't reach the proxy through a domain resolving to it. The token is
+requested for the scopes given with `--${SCOPE_FLAG}`, or else for the read-only scopes of the API.
+
+If connecting takes seconds in a network where IPv6 or IPv4 is configured but doesn't work, choose the addresses to connect
+to with `--${ADDRESS_FAMILY_FLAG} <family>`. `ipv4` and `ipv6` only use the addresses of that family, while `prefer-ipv4` and
+`prefer-ipv6` try them first and fall back to the others. If a connection to the first address family isn't established
+within 300 milliseconds, the other family is tried alongside it. `--${HAPPY_EYEBALLS_FLAG} <milliseconds>` changes that
+delay, and `0` tries all addresses one after the other instead.
+
+Failed requests aren't retried by default. With `--${MAX_RETRIES_FLAG} <retries>`, requests failing with a connection error or a
+transient status, like `503 Service Unavailable` or `429 Too Many Requests`, are retried up to the given amount of times. Each
+retry waits as long as the API asks to, or up to twice as long as the one before, starting at a second. Only `GET`, `HEAD`,
+`PUT` and `DELETE` requests are retried that way, as other requests, like creating a resource, may have been carried out
+already. They are only retried after `429 Too Many Requests` or `503 Service Unavailable` with a `Retry-After` header.
+`--${MAX_RETRIES_FLAG} 0` keeps calls failing right with the first error, for instance when the latency matters more.
+
 
 [scopes]: https://developers.google.com/+/api/oauth#scopes
 [revoke-access]: http://webapps.stackexchange.com/a/30849
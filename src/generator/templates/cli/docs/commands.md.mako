@@ -5,8 +5,8 @@
     from generator.lib.cli import (subcommand_md_filename, new_method_context, SPLIT_START, SPLIT_END, pretty, SCOPE_FLAG,
                      mangle_subcommand, is_request_value_property, FIELD_SEP, PARAM_FLAG, UPLOAD_FLAG, docopt_mode,
                      FILE_ARG, MIME_ARG, OUT_ARG, OUTPUT_FLAG, to_cli_schema, cli_schema_to_yaml, SchemaEntry,
-                     STRUCT_FLAG, field_to_value, CTYPE_ARRAY, CTYPE_MAP, to_docopt_arg, FILE_FLAG, MIME_FLAG, 
-                     DEFAULT_MIME)
+                     STRUCT_FLAG, field_to_value, CTYPE_ARRAY, CTYPE_MAP, to_docopt_arg, FILE_FLAG, MIME_FLAG, MIME_LONG_FLAG,
+                     DEFAULT_MIME, is_destructive_method, ASSUME_YES_FLAG, INTERACTIVE_FLAG)
 
     from copy import deepcopy
 
@@ -43,6 +43,12 @@ the *${mc.m.scopes[0]}* scope to make a valid call.
 If unset, the scope for this method defaults to *${method_default_scope(mc.m)}*.
 You can set the scope for this method like this: `${util.program_name()} --${SCOPE_FLAG} <scope> ${mangle_subcommand(resource)} ${mangle_subcommand(method)} ...`
 % endif # have method scopes
+% if is_destructive_method(mc):
+# Confirmation
+
+This method destroys data, so you will be asked for confirmation before the call is made if running in a terminal.
+Skip the question like this: `${util.program_name()} --${ASSUME_YES_FLAG} ${mangle_subcommand(resource)} ${mangle_subcommand(method)} ...`
+% endif # destructive method
 <%
     rprops = [p for p in mc.required_props if not is_request_value_property(mc, p)]
     oprops = [p for p in mc.optional_props if not p.get('skip_example', False)]
@@ -86,6 +92,18 @@ The cursor position is key to comfortably set complex nested structures. The fol
 * You can also set nested fields without setting the cursor explicitly. For example, to set a value relative to the current cursor position, you would specify `-${STRUCT_FLAG} struct${FIELD_SEP}sub_struct=bar`.
 * You can move the cursor one level up by using `${FIELD_SEP}${FIELD_SEP}`. Each additional `${FIELD_SEP}` moves it up one additional level. E.g. `${FIELD_SEP}${FIELD_SEP}${FIELD_SEP}` would go three levels up.
 
+${'###'} Environment Variables
+
+Values may refer to environment variables, which is handy in scripts. `${'$'}{NAME}` is replaced with the value of the variable `NAME`, and it is an error if it is not set. `${'$'}{NAME:-default}` uses `default` instead if the variable is unset or empty. Write `$$` for a literal `$`, like in `-${STRUCT_FLAG} name=${'$'}{TEAM}-$$5`.
+
+${'###'} Dates and Durations
+
+A date, made of `year`, `month` and `day` fields, may be set at once like `-${STRUCT_FLAG} start-date=2024-01-15`. Durations are sent as a number of seconds like `5400s`, but may also be given as `1h30m`, combining hours (`h`), minutes (`m`), seconds (`s`) and milliseconds (`ms`).
+
+${'###'} Interactively
+
+With `--${INTERACTIVE_FLAG}`, each field not set with `-${STRUCT_FLAG}` is asked for in turn, along with its type and the values it allows. An empty answer skips the field, while lists and maps take an entry per answer until an empty one.
+
 % endif # have request value
 % if mc.media_params:
 <%
@@ -100,12 +118,13 @@ This method supports the upload of data, which *requires* all of the following f
     - **${mp.protocol}** - ${mp.get('description', NO_DESC).split('\n')[0] | xml_escape}
 % endfor # each media param
 * **-${FILE_FLAG} ${escape_html(FILE_ARG)}**
-    - Path to file to upload. It must be seekable.
+    - Path to file to upload, or `-` to read it from standard input, which is spooled to a temporary file first.
 
 The following flag *may* be set: 
 
-* **-${MIME_FLAG} ${escape_html(MIME_ARG)}**
-    - the mime type, like '${DEFAULT_MIME}', which is the default
+* **-${MIME_FLAG}, --${MIME_LONG_FLAG} ${escape_html(MIME_ARG)}**
+    - the mime type, like '${DEFAULT_MIME}'. If unset, it is guessed from the file extension, falling back to
+      '${DEFAULT_MIME}'.
 
 % endif # have upload capabilities
 % if mc.response_schema or smd:
@@ -2,11 +2,26 @@
 <%!
     import os
 
-    from generator.lib.util import (put_and, supports_scopes, api_index, indent_by, enclose_in, put_and, escape_rust_string)
+    from generator.lib.util import (put_and, supports_scopes, api_index, indent_by, enclose_in, put_and, escape_rust_string,
+                                    has_error_format_param)
     from generator.lib.cli import (mangle_subcommand, new_method_context, PARAM_FLAG, STRUCT_FLAG, UPLOAD_FLAG, OUTPUT_FLAG, VALUE_ARG,
                      CONFIG_DIR, SCOPE_FLAG, is_request_value_property, FIELD_SEP, docopt_mode, FILE_ARG, MIME_ARG, OUT_ARG,
-                     CONFIG_DIR_FLAG, KEY_VALUE_ARG, to_docopt_arg, DEBUG_FLAG, MODE_ARG, SCOPE_ARG,
-                     CONFIG_DIR_ARG, FILE_FLAG, MIME_FLAG, subcommand_md_filename)
+                     CONFIG_DIR_FLAG, KEY_VALUE_ARG, to_docopt_arg, DEBUG_FLAG, RAW_ERRORS_FLAG, MODE_ARG, SCOPE_ARG,
+                     CONFIG_DIR_ARG, FILE_FLAG, MIME_FLAG, MIME_LONG_FLAG, subcommand_md_filename, DRY_RUN_FLAG, VERBOSE_FLAG, VERBOSE_SHORT_FLAG,
+                     has_paged_methods, ALL_PAGES_FLAG, STATE_FILE_FLAG, STATE_FILE_ARG, RESUME_FLAG, CONTINUE_FROM_FLAG,
+                     CONTINUE_FROM_ARG,
+                     PROFILE_FLAG, PROFILE_ARG, SELECT_PROFILE_FLAG, has_fan_out_methods, CONCURRENCY_FLAG,
+                     CONCURRENCY_ARG, DEFAULT_CONCURRENCY, has_report_methods, REPORT_FORMAT_FLAG,
+                     REPORT_FORMAT_ARG, has_multi_call_methods, ON_ERROR_FLAG, ON_ERROR_ARG,
+                     HUMAN_FLAG, HUMAN_SHORT_FLAG, STREAM_OUTPUT_FLAG, CONFIG_FILE_FLAG, CONFIG_FILE_ARG,
+                     MAX_PAGES_FLAG, MAX_PAGES_ARG, ONLY_FIELDS_FLAG, ONLY_FIELDS_ARG, STRIP_FIELDS_FLAG,
+                     STRIP_FIELDS_ARG, STRICT_FLAG, TIMING_FLAG, VALIDATE_ONLY_FLAG,
+                     SPLIT_PAGES_FLAG, SPLIT_PAGES_ARG, has_destructive_methods, is_destructive_method,
+                     ASSUME_YES_FLAG, ASSUME_YES_SHORT_FLAG, RECORD_FLAG, RECORD_ARG, REPLAY_FLAG, REPLAY_ARG,
+                     SORT_BY_FLAG, SORT_BY_ARG, ADDRESS_FAMILY_FLAG, ADDRESS_FAMILY_ARG, HAPPY_EYEBALLS_FLAG,
+                     HAPPY_EYEBALLS_ARG, WHERE_FLAG, WHERE_ARG, OUTPUT_FORMAT_FLAG, OUTPUT_FORMAT_ARG,
+                     SERVE_FLAG, SERVE_ARG, SERVE_ALLOW_REMOTE_FLAG, ERROR_FORMAT_FLAG, ERROR_FORMAT_ARG, INTERACTIVE_FLAG,
+                     OUTPUT_SCHEMA_FLAG, MAX_RETRIES_FLAG, MAX_RETRIES_ARG)
 
     def rust_boolean(v):
         return v and 'true' or 'false'
@@ -38,7 +53,7 @@ ${util.program_name()} [options]
     # end for each required property
 
     if mc.request_value:
-        args.append('(-%s %s)...' % (STRUCT_FLAG, '<%s>' % KEY_VALUE_ARG))
+        args.append('((-%s %s)... | --%s)' % (STRUCT_FLAG, '<%s>' % KEY_VALUE_ARG, INTERACTIVE_FLAG))
     # end request_value
 
     if mc.media_params:
@@ -58,6 +73,7 @@ ${util.program_name()} [options]
                 ${mangle_subcommand(method)} ${' '.join(args)}
     % endfor # each method
 % endfor # end for each resource
+  ${util.program_name()} [options] --${SERVE_FLAG} <${SERVE_ARG}>
   ${util.program_name()} --help
 
 Configuration:
@@ -71,6 +87,143 @@ Configuration:
             A directory into which we will store our persistent data. Defaults to
             a user-writable directory that we will create during the first invocation.
             [default: ${CONFIG_DIR}]
+  --${CONFIG_FILE_FLAG} <${CONFIG_FILE_ARG}>
+            A TOML file setting defaults for the global flags, one key per flag named
+            like its long form. Flags given on the command line take precedence.
+            [default: ${util.program_name()}-config.toml in the --${CONFIG_DIR_FLAG}, if it exists]
+  --${PROFILE_FLAG} <${PROFILE_ARG}>
+            The profile to keep the tokens of the authenticated account in, which
+            allows to use multiple accounts side by side. [default: default]
+  --${SELECT_PROFILE_FLAG}
+            If no profile is given, pick one of the known profiles interactively.
+            Has no effect unless running in a terminal.
+  -${VERBOSE_SHORT_FLAG}, --${VERBOSE_FLAG}...
+            Log each request attempt with its status and latency to standard error.
+            Repeat to add response headers (-vv) and bodies (-vvv).
+  --${TIMING_FLAG}
+            Write how long resolving the name, connecting, securing the connection,
+            receiving the first byte and the whole call took to standard error.
+  --${RECORD_FLAG} <${RECORD_ARG}>
+            Save each call, with its request and response, as a fixture file in the
+            given directory.
+  --${REPLAY_FLAG} <${REPLAY_ARG}>
+            Answer all calls with the fixtures in the given directory, as saved with
+            --${RECORD_FLAG}, without network access or authentication.
+  --${SERVE_FLAG} <${SERVE_ARG}>
+            Instead of making a call, listen on the given address, like 127.0.0.1:8080,
+            and forward the read requests received there to the API, authorized with
+            the token of the CLI. Each request is logged to standard error.
+  --${SERVE_ALLOW_REMOTE_FLAG}
+            Allow --${SERVE_FLAG} to listen on an address other than a loopback address,
+            which lets anyone who can reach it act with the token.
+  --${ADDRESS_FAMILY_FLAG} <${ADDRESS_FAMILY_ARG}>
+            Either any, ipv4, ipv6, prefer-ipv4 or prefer-ipv6. Connect only to the
+            addresses of the given family, or try those of the preferred family first.
+            [default: any]
+  --${HAPPY_EYEBALLS_FLAG} <${HAPPY_EYEBALLS_ARG}>
+            How long to wait for a connection to the first address family before
+            trying the other one alongside it, or 0 to try all addresses one after
+            the other. [default: 300]
+  --${MAX_RETRIES_FLAG} <${MAX_RETRIES_ARG}>
+            Retry requests failing with a connection error or a transient status,
+            like 503, up to the given amount of times, waiting as long as the API
+            asks to. With 0, a call fails right with the first error. [default: 0]
+  --${DRY_RUN_FLAG}
+            Validate all arguments, including the assembled request body, and exit
+            without making the call.
+  --${VALIDATE_ONLY_FLAG}
+            Like --${DRY_RUN_FLAG}, but also check the parameters and the request body
+            against the constraints documented by the API, like patterns and the
+            values an enumeration allows.
+  --${STRICT_FLAG}
+            Fail if a parameter taking a single value is given more than once
+            with -${PARAM_FLAG}, instead of warning that only its last value is used.
+% if has_error_format_param(parameters):
+  --${ERROR_FORMAT_FLAG} <${ERROR_FORMAT_ARG}>
+            Either v1 or v2. The format errors are returned in by the API, where v1
+            is the legacy format lacking the details of v2. [default: v2]
+% endif
+  --${RAW_ERRORS_FLAG}
+            Write errors returned by the API as the JSON they came as, instead of
+            their code, status and message followed by their details.
+% if has_destructive_methods(c):
+  -${ASSUME_YES_SHORT_FLAG}, --${ASSUME_YES_FLAG}
+            Make destructive calls, like deletes, without asking for confirmation
+            first. Only needed when running in a terminal.
+% endif
+% if has_multi_call_methods(c):
+  --${ON_ERROR_FLAG} <${ON_ERROR_ARG}>
+            Either continue or stop. With continue, a failed call of many is recorded
+            in the output and the remaining calls are made, while stop makes no
+            further calls. Either way, the exit status is non-zero if any call failed.
+            [default: continue]
+% endif
+
+Output:
+  --${ONLY_FIELDS_FLAG} <${ONLY_FIELDS_ARG}>
+            Keep only the given comma separated paths of the response, like
+            items.name, and drop everything else before it is written.
+  --${STRIP_FIELDS_FLAG} <${STRIP_FIELDS_ARG}>
+            Drop the given comma separated paths of the response, like
+            items.sample, before it is written.
+  --${OUTPUT_FORMAT_FLAG} <${OUTPUT_FORMAT_ARG}>
+            Either json or ndjson-stream. With ndjson-stream, each item is written
+            as a line of compact JSON, and the output is flushed right after, for
+            readers tailing it. [default: json]
+  --${OUTPUT_SCHEMA_FLAG}
+            Instead of making the call, write the structure of its response, with
+            the name and type of each field, and nested fields indented below theirs.
+% if has_paged_methods(c):
+
+Paging:
+  --${ALL_PAGES_FLAG}
+            Fetch all pages of methods returning paged results, and merge them
+            into a single result.
+  --${STATE_FILE_FLAG} <${STATE_FILE_ARG}>
+            Write each page as a line of JSON as soon as it was fetched, and keep
+            the token of the next page in the given file until all pages are done.
+            Requires --${ALL_PAGES_FLAG}.
+  --${RESUME_FLAG}
+            Continue an interrupted export from the page recorded in the state file,
+            appending to the output file. Requires --${STATE_FILE_FLAG}.
+  --${CONTINUE_FROM_FLAG} <${CONTINUE_FROM_ARG}>
+            Fetch the page following the response an earlier call wrote to the
+            given file, using its nextPageToken as the page token.
+  --${STREAM_OUTPUT_FLAG}
+            Write the items of each page as lines of JSON as soon as the page was
+            fetched, instead of merging all pages. Requires --${ALL_PAGES_FLAG}.
+  --${MAX_PAGES_FLAG} <${MAX_PAGES_ARG}>
+            Fetch no more than the given amount of pages, and write the results
+            fetched until then. Requires --${ALL_PAGES_FLAG}.
+  --${SPLIT_PAGES_FLAG} <${SPLIT_PAGES_ARG}>
+            Write each page as returned by the API to a file of its own in the given
+            directory, like page-0001.json, instead of merging all pages.
+            Requires --${ALL_PAGES_FLAG}.
+  --${SORT_BY_FLAG} <${SORT_BY_ARG}>
+            Sort the items of the merged pages by the given field, like deviceId, so
+            the output is the same across runs. Requires --${ALL_PAGES_FLAG}.
+  --${WHERE_FLAG} <${WHERE_ARG}>
+            Keep only the items of the results the given expression holds for, like
+            'cpuInfo[0].maxClockSpeed > 2000000'.
+% endif
+% if has_fan_out_methods(c):
+
+Fan-out:
+  --${CONCURRENCY_FLAG} <${CONCURRENCY_ARG}>
+            The amount of calls in flight at once when the name given to a get-style
+            method is of the form @<file>, which makes the call for each name in the
+            file, one per line, or on standard input if it is @-. [default: ${DEFAULT_CONCURRENCY}]
+  -${HUMAN_SHORT_FLAG}, --${HUMAN_FLAG}
+            Write a readable summary of the resources returned by get-style methods,
+            with one labelled line per field, instead of their JSON.
+% endif
+% if has_report_methods(c):
+
+Reports:
+  --${REPORT_FORMAT_FLAG} <${REPORT_FORMAT_ARG}>
+            The format to write the responses of count methods in, either json or
+            prometheus for the Prometheus text exposition format. [default: json]
+% endif
 </%def>
 
 
@@ -86,6 +239,7 @@ Configuration:
     # (1) = description
     # (2) = argument name, no argument if no argument
     # (3) = multiple
+    # (4) = short flag, or None
     global_args = list()
     if supports_scopes(auth):
         global_args.append((
@@ -94,7 +248,8 @@ Configuration:
             "requires the user to grant this application permission to use it."
             "If unset, it defaults to the shortest scope url for a particular method.",
             SCOPE_ARG,
-            True
+            True,
+            None,
         ))
     # end add scope arg
     global_args.append((
@@ -104,14 +259,341 @@ Configuration:
         "[default: %s" % CONFIG_DIR,
         CONFIG_DIR_ARG,
         False,
+        None,
+    ))
+
+    global_args.append((
+        CONFIG_FILE_FLAG,
+        "A TOML file setting defaults for the global flags, one key per flag named like its long form. "
+        "Flags given on the command line take precedence. [default: %s-config.toml in the directory "
+        "given with --%s, if it exists]" % (util.program_name(), CONFIG_DIR_FLAG),
+        CONFIG_FILE_ARG,
+        False,
+        None,
     ))
 
+    global_args.append((
+        PROFILE_FLAG,
+        "The profile to keep the tokens of the authenticated account in, which allows to use "
+        "multiple accounts side by side. [default: default]",
+        PROFILE_ARG,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        SELECT_PROFILE_FLAG,
+        "If no profile is given, pick one of the known profiles interactively. "
+        "Has no effect unless running in a terminal",
+        None,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        DRY_RUN_FLAG,
+        "Validate all arguments, including the assembled request body, and exit without making the call",
+        None,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        VALIDATE_ONLY_FLAG,
+        "Like --%s, but also check the parameters and the request body against the constraints documented "
+        "by the API, like patterns and the values an enumeration allows" % DRY_RUN_FLAG,
+        None,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        STRICT_FLAG,
+        "Fail if a parameter taking a single value is given more than once with -%s, instead of "
+        "warning that only its last value is used" % PARAM_FLAG,
+        None,
+        False,
+        None,
+    ))
+
+    if has_error_format_param(parameters):
+        global_args.append((
+            ERROR_FORMAT_FLAG,
+            "Either v1 or v2. The format errors are returned in by the API, where v1 is the legacy format "
+            "lacking the details of v2. [default: v2]",
+            ERROR_FORMAT_ARG,
+            False,
+            None,
+        ))
+
+    if has_destructive_methods(c):
+        global_args.append((
+            ASSUME_YES_FLAG,
+            "Make destructive calls, like deletes, without asking for confirmation first. "
+            "Only needed when running in a terminal",
+            None,
+            False,
+            ASSUME_YES_SHORT_FLAG,
+        ))
+
     global_args.append((
         DEBUG_FLAG,
         "Debug print all errors",
         None,
         False,
+        None,
+    ))
+
+    global_args.append((
+        RAW_ERRORS_FLAG,
+        "Write errors returned by the API as the JSON they came as, instead of their code, status and message "
+        "followed by their details",
+        None,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        VERBOSE_FLAG,
+        "Log each request attempt with its status and latency to standard error. "
+        "Repeat to add response headers (-vv) and bodies (-vvv)",
+        None,
+        True,
+        VERBOSE_SHORT_FLAG,
+    ))
+
+    global_args.append((
+        TIMING_FLAG,
+        "Write how long resolving the name, connecting, securing the connection, receiving the first "
+        "byte and the whole call took to standard error",
+        None,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        RECORD_FLAG,
+        "Save each call, with its request and response, as a fixture file in the given directory",
+        RECORD_ARG,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        REPLAY_FLAG,
+        "Answer all calls with the fixtures in the given directory, as saved with --%s, without network "
+        "access or authentication" % RECORD_FLAG,
+        REPLAY_ARG,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        SERVE_FLAG,
+        "Instead of making a call, listen on the given address, like 127.0.0.1:8080, and forward the read "
+        "requests received there to the API, authorized with the token of the CLI. Each request is logged "
+        "to standard error",
+        SERVE_ARG,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        SERVE_ALLOW_REMOTE_FLAG,
+        "Allow --%s to listen on an address other than a loopback address, which lets anyone who can "
+        "reach it act with the token" % SERVE_FLAG,
+        None,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        ADDRESS_FAMILY_FLAG,
+        "Either any, ipv4, ipv6, prefer-ipv4 or prefer-ipv6. Connect only to the addresses of the given "
+        "family, or try those of the preferred family first. [default: any]",
+        ADDRESS_FAMILY_ARG,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        HAPPY_EYEBALLS_FLAG,
+        "How long to wait for a connection to the first address family before trying the other one "
+        "alongside it, or 0 to try all addresses one after the other. [default: 300]",
+        HAPPY_EYEBALLS_ARG,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        MAX_RETRIES_FLAG,
+        "Retry requests failing with a connection error or a transient status, like 503, up to the given "
+        "amount of times, waiting as long as the API asks to. With 0, a call fails right with the first "
+        "error. [default: 0]",
+        MAX_RETRIES_ARG,
+        False,
+        None,
+    ))
+
+    if has_multi_call_methods(c):
+        global_args.append((
+            ON_ERROR_FLAG,
+            "Either continue or stop. With continue, a failed call of many is recorded in the output "
+            "and the remaining calls are made, while stop makes no further calls. Either way, the exit "
+            "status is non-zero if any call failed. [default: continue]",
+            ON_ERROR_ARG,
+            False,
+            None,
+        ))
+
+    global_args.append((
+        ONLY_FIELDS_FLAG,
+        "Keep only the given comma separated paths of the response, like items.name, and drop "
+        "everything else before it is written",
+        ONLY_FIELDS_ARG,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        STRIP_FIELDS_FLAG,
+        "Drop the given comma separated paths of the response, like items.sample, before it is written",
+        STRIP_FIELDS_ARG,
+        False,
+        None,
     ))
+
+    global_args.append((
+        OUTPUT_FORMAT_FLAG,
+        "Either json or ndjson-stream. With ndjson-stream, each item is written as a line of compact "
+        "JSON, and the output is flushed right after, for readers tailing it. [default: json]",
+        OUTPUT_FORMAT_ARG,
+        False,
+        None,
+    ))
+
+    global_args.append((
+        OUTPUT_SCHEMA_FLAG,
+        "Instead of making the call, write the structure of its response, with the name and type of each "
+        "field, and nested fields indented below theirs",
+        None,
+        False,
+        None,
+    ))
+
+    if has_paged_methods(c):
+        global_args.append((
+            ALL_PAGES_FLAG,
+            "Fetch all pages of methods returning paged results, and merge them into a single result",
+            None,
+            False,
+            None,
+        ))
+
+        global_args.append((
+            STATE_FILE_FLAG,
+            "Write each page as a line of JSON as soon as it was fetched, and keep the token of the "
+            "next page in the given file until all pages are done. Requires --%s" % ALL_PAGES_FLAG,
+            STATE_FILE_ARG,
+            False,
+            None,
+        ))
+
+        global_args.append((
+            RESUME_FLAG,
+            "Continue an interrupted export from the page recorded in the state file, "
+            "appending to the output file. Requires --%s" % STATE_FILE_FLAG,
+            None,
+            False,
+            None,
+        ))
+
+        global_args.append((
+            CONTINUE_FROM_FLAG,
+            "Fetch the page following the response an earlier call wrote to the given file, using its "
+            "nextPageToken as the page token",
+            CONTINUE_FROM_ARG,
+            False,
+            None,
+        ))
+
+        global_args.append((
+            STREAM_OUTPUT_FLAG,
+            "Write the items of each page as lines of JSON as soon as the page was fetched, instead of "
+            "merging all pages. Requires --%s" % ALL_PAGES_FLAG,
+            None,
+            False,
+            None,
+        ))
+
+        global_args.append((
+            MAX_PAGES_FLAG,
+            "Fetch no more than the given amount of pages, and write the results fetched until then. "
+            "Requires --%s" % ALL_PAGES_FLAG,
+            MAX_PAGES_ARG,
+            False,
+            None,
+        ))
+
+        global_args.append((
+            SPLIT_PAGES_FLAG,
+            "Write each page as returned by the API to a file of its own in the given directory, like "
+            "page-0001.json, instead of merging all pages. Requires --%s" % ALL_PAGES_FLAG,
+            SPLIT_PAGES_ARG,
+            False,
+            None,
+        ))
+
+        global_args.append((
+            SORT_BY_FLAG,
+            "Sort the items of the merged pages by the given field, like deviceId, so the output is the "
+            "same across runs. Requires --%s" % ALL_PAGES_FLAG,
+            SORT_BY_ARG,
+            False,
+            None,
+        ))
+
+        global_args.append((
+            WHERE_FLAG,
+            "Keep only the items of the results the given expression holds for, like "
+            "'cpuInfo[0].maxClockSpeed > 2000000'",
+            WHERE_ARG,
+            False,
+            None,
+        ))
+    # end add paging args
+
+    if has_fan_out_methods(c):
+        global_args.append((
+            CONCURRENCY_FLAG,
+            "The amount of calls in flight at once when the name given to a get-style method is of the "
+            "form @<file>, which makes the call for each name in the file, one per line, or on standard "
+            "input if it is @-. [default: %i]" % DEFAULT_CONCURRENCY,
+            CONCURRENCY_ARG,
+            False,
+            None,
+        ))
+
+        global_args.append((
+            HUMAN_FLAG,
+            "Write a readable summary of the resources returned by get-style methods, with one labelled "
+            "line per field, instead of their JSON",
+            None,
+            False,
+            HUMAN_SHORT_FLAG,
+        ))
+    # end add fan-out args
+
+    if has_report_methods(c):
+        global_args.append((
+            REPORT_FORMAT_FLAG,
+            "The format to write the responses of count methods in, either json or prometheus for the "
+            "Prometheus text exposition format. [default: json]",
+            REPORT_FORMAT_ARG,
+            False,
+            None,
+        ))
+    # end add report args
 %>\
 <%
     have_media_params = False
@@ -171,7 +653,7 @@ let arg_data = [
     if mc.media_params:
         args.append((
                 UPLOAD_FLAG,
-                "Specify the upload protocol (%s) and the file to upload" % '|'.join(mp.protocol for mp in mc.media_params),
+                "Specify the upload protocol (%s) and the file to upload, or - for standard input" % '|'.join(mp.protocol for mp in mc.media_params),
                 MODE_ARG,
                 True,
                 True,
@@ -200,7 +682,7 @@ let arg_data = [
 %>\
     ("${mangle_subcommand(method)}",
             ${rust_optional(mc.m.get('description'))},
-            "Details at ${doc_base_url}/${os.path.splitext(subcommand_md_filename(resource, method))[0]}",
+            "${is_destructive_method(mc) and 'Asks for confirmation unless --%s is given. ' % ASSUME_YES_FLAG or ''}Details at ${doc_base_url}/${os.path.splitext(subcommand_md_filename(resource, method))[0]}",
           vec![
             % for flag, desc, arg_name, required, multi in args:
             (${rust_optional(arg_name)},
@@ -227,9 +709,12 @@ let mut app = App::new("${util.program_name()}")
 .about("${escape_rust_string(description)}")
 % endif
 .after_help("${url_info}")
-% for flag, desc, arg_name, multiple in global_args:
+% for flag, desc, arg_name, multiple, short_flag in global_args:
 .arg(Arg::with_name("${arg_name or flag}")
         .long("${flag}")
+    % if short_flag:
+        .short("${short_flag}")
+    % endif
         .help("${desc}")
         .multiple(${rust_boolean(multiple)})
         .takes_value(${rust_boolean(arg_name)}))\
@@ -240,6 +725,12 @@ let mut app = App::new("${util.program_name()}")
 % endif
 % endfor
 
+let config_flags: &[client::config_file::ConfigFlag] = &[
+% for flag, desc, arg_name, multiple, short_flag in global_args:
+    ("${flag}", "${arg_name or flag}", ${rust_boolean(arg_name)}, ${short_flag and "Some('%s')" % short_flag or 'None'}),
+% endfor
+];
+
 for &(main_command_name, about, ref subcommands) in arg_data.iter() {
     let mut mcmd = SubCommand::with_name(main_command_name).about(about);
 
@@ -281,12 +772,21 @@ for &(main_command_name, about, ref subcommands) in arg_data.iter() {
 
                 scmd = scmd.arg(Arg::with_name("${MIME_ARG}")
                                     .short("${MIME_FLAG}")
+                                    .long("${MIME_LONG_FLAG}")
                                     .requires("${MODE_ARG}")
                                     .required(false)
-                                    .help("The file's mime time, like 'application/octet-stream'")
+                                    .help("The file's mime type, like 'application/octet-stream'. It is guessed from the file extension if unset")
                                     .takes_value(true));
             }
             % endif
+            if arg_name_str == "${KEY_VALUE_ARG}" {
+                arg = arg.required_unless("${INTERACTIVE_FLAG}");
+
+                scmd = scmd.arg(Arg::with_name("${INTERACTIVE_FLAG}")
+                                    .long("${INTERACTIVE_FLAG}")
+                                    .help("Ask for each field of the request structure not set with -${STRUCT_FLAG}, along with its type and the values it allows")
+                                    .takes_value(false));
+            }
             scmd = scmd.arg(arg);
         }
         mcmd = mcmd.subcommand(scmd);
@@ -2,14 +2,24 @@
 <%!
     from generator.lib.util import (hub_type, mangle_ident, indent_all_but_first_by, activity_rust_type, setter_fn_name, ADD_PARAM_FN,
                       upload_action_fn, is_schema_with_optionals, schema_markers, indent_by, method_default_scope,
-                      ADD_SCOPE_FN, TREF, enclose_in)
+                      ADD_SCOPE_FN, TREF, enclose_in, supports_scopes, has_error_format_param, escape_rust_string,
+                      is_date_schema, page_items_prop, has_next_page_token)
     from generator.lib.cli import (mangle_subcommand, new_method_context, PARAM_FLAG, STRUCT_FLAG, OUTPUT_FLAG, VALUE_ARG,
                      CONFIG_DIR, SCOPE_FLAG, is_request_value_property, FIELD_SEP, docopt_mode, FILE_ARG, MIME_ARG, OUT_ARG,
                      call_method_ident, POD_TYPES, opt_value, ident,
                      KEY_VALUE_ARG, to_cli_schema, SchemaEntry, CTYPE_POD, actual_json_type, CTYPE_MAP, CTYPE_ARRAY,
                      application_secret_path, CONFIG_DIR_FLAG, req_value, MODE_ARG,
-                     opt_values, SCOPE_ARG, CONFIG_DIR_ARG, DEFAULT_MIME, field_vec, comma_sep_fields, JSON_TYPE_TO_ENUM_MAP,
-                     CTYPE_TO_ENUM_MAP)
+                     opt_values, SCOPE_ARG, CONFIG_DIR_ARG, comma_sep_fields, JSON_TYPE_TO_ENUM_MAP,
+                     CTYPE_TO_ENUM_MAP, VERBOSE_FLAG, page_token_param, page_size_param, ALL_PAGES_FLAG, STATE_FILE_ARG, RESUME_FLAG, CONTINUE_FROM_ARG,
+                     PROFILE_ARG, SELECT_PROFILE_FLAG, fan_out_param, CONCURRENCY_FLAG, CONCURRENCY_ARG,
+                     DEFAULT_CONCURRENCY, is_report_method, metric_prefix, REPORT_FORMAT_FLAG, REPORT_FORMAT_ARG,
+                     ON_ERROR_FLAG, ON_ERROR_ARG, HUMAN_FLAG, STREAM_OUTPUT_FLAG,
+                     MAX_PAGES_FLAG, MAX_PAGES_ARG, ONLY_FIELDS_ARG, STRIP_FIELDS_ARG,
+                     STRICT_FLAG, TIMING_FLAG, VALIDATE_ONLY_FLAG, SPLIT_PAGES_ARG, is_destructive_method,
+                     ASSUME_YES_FLAG, RECORD_ARG, SORT_BY_ARG, WHERE_ARG,
+                     REPLAY_ARG, OUTPUT_FORMAT_FLAG, OUTPUT_FORMAT_ARG, SERVE_FLAG, SERVE_ARG, SERVE_ALLOW_REMOTE_FLAG,
+                     ERROR_FORMAT_FLAG, ERROR_FORMAT_ARG, INTERACTIVE_FLAG,
+                     OUTPUT_SCHEMA_FLAG, schema_outline, MAX_RETRIES_FLAG, MAX_RETRIES_ARG, json_type_enum)
     from generator.lib.types import JSON_TO_RUST_DEFAULT
     v_arg = '<%s>' % VALUE_ARG
     SOPT = 'self.opt'
@@ -33,11 +43,12 @@
 %>\
 use client::{InvalidOptionsError, CLIError, arg_from_str, writer_from_opts, parse_kv_arg,
           input_file_from_opts, input_mime_from_opts, FieldCursor, FieldError, CallType, UploadProtocol,
-          calltype_from_str, remove_json_null_values, ComplexType, JsonType, JsonTypeInfo};
+          calltype_from_str, to_output_value, expand_env_vars, ComplexType, JsonType, JsonTypeInfo};
 
 use std::default::Default;
 use std::error::Error as StdError;
 use std::str::FromStr;
+use std::sync::Mutex;
 
 use serde_json as json;
 use clap::ArgMatches;
@@ -48,14 +59,47 @@ use tower_service;
 
 enum DoitError {
     IoError(String, io::Error),
+    OutputError(String, io::Error),
     ApiError(Error),
+    FanOutError(usize, usize, usize),
+    Aborted,
+    ServeError(String, hyper::Error),
+}
+
+<% gpm = gen_global_parameter_names(parameters) %>\
+// The parameters every method accepts, as pairs of their name on the command-line and in the API.
+const GLOBAL_PARAMS: &[(&str, &str)] = &[
+    % for pn in gpm:
+    ("${mangle_subcommand(pn)}", "${pn}"),
+    % endfor # each global parameter
+];
+
+// The structure of the response of each method, as written with --${OUTPUT_SCHEMA_FLAG}, by the names of
+// its resource and method on the command-line.
+fn output_schema(resource: ${'&'}str, method: ${'&'}str) -> Option<${'&'}'static str> {
+    match (resource, method) {
+% for resource in sorted(c.rta_map.keys()):
+    % for method in sorted(c.rta_map[resource]):
+<%
+    mc = new_method_context(resource, method, c)
+    if mc.response_schema:
+        outline = r'\n'.join(schema_outline(c, mc.response_schema)) + r'\n'
+    else:
+        outline = r'The call returns no data.\n'
+%>\
+        ("${mangle_subcommand(resource)}", "${mangle_subcommand(method)}") => Some("${escape_rust_string(outline)}"),
+    % endfor # each method
+% endfor # each resource
+        _ => None,
+    }
 }
 
 struct Engine<'n, S> {
     opt: ArgMatches<'n>,
     hub: ${hub_type_name}<S>,
-    gp: ${"Vec<&'static str>"},
-    gpm: Vec<(&'static str, &'static str)>,
+    timing: client::timing::Timing,
+    // the answers given with --${INTERACTIVE_FLAG} while validating, to be used by the call
+    answers: Mutex<Vec<String>>,
 }
 
 
@@ -75,6 +119,43 @@ where
 
     % endfor # each method
 % endfor
+    async fn _serve(&self, dry_run: bool, err: &mut InvalidOptionsError) -> Result<(), DoitError> {
+        let addr = ${SOPT}.value_of("${SERVE_ARG}").unwrap_or("");
+        let addr = match addr.parse::<std::net::SocketAddr>() {
+            Ok(addr) => addr,
+            Err(parse_err) => {
+                err.issues.push(CLIError::ParseError("--${SERVE_FLAG}".to_string(), "socket address".to_string(),
+                                                     addr.to_string(), parse_err.to_string()));
+                return Ok(());
+            }
+        };
+        if !addr.ip().is_loopback() && !${SOPT}.is_present("${SERVE_ALLOW_REMOTE_FLAG}") {
+            err.issues.push(CLIError::ParseError("--${SERVE_FLAG}".to_string(), "loopback address".to_string(), addr.to_string(),
+                                                 "anyone who can reach other addresses acts with the token, which needs --${SERVE_ALLOW_REMOTE_FLAG}".to_string()));
+        }
+        % if supports_scopes(auth):
+<%
+    readonly_scopes = sorted(url for url in auth.oauth2.scopes.keys() if 'readonly' in url)
+%>\
+        let mut scopes: Vec<${'&'}str> = ${opt_values(SCOPE_ARG, opt=SOPT)}.copied().collect();
+        if scopes.is_empty() {
+            scopes = vec![${', '.join(enclose_in('"', readonly_scopes))}];
+        }
+        % if not readonly_scopes:
+        if scopes.is_empty() {
+            err.issues.push(CLIError::OptionRequires("${SERVE_FLAG}".to_string(), "${SCOPE_FLAG}".to_string()));
+        }
+        % endif
+        % else:
+        let scopes: Vec<${'&'}str> = Vec::new();
+        % endif
+        if dry_run {
+            return Ok(());
+        }
+        let proxy = client::serve::Proxy::new("${rootUrl}", self.hub.client.clone(), self.hub.auth.clone(), &scopes);
+        proxy.serve(addr).await.map_err(|serve_err| DoitError::ServeError(addr.to_string(), serve_err))
+    }
+
     async fn _doit(&self, dry_run: bool) -> Result<Result<(), DoitError>, Option<InvalidOptionsError>> {
         let mut err = InvalidOptionsError::new();
         let mut call_result: Result<(), DoitError> = Ok(());
@@ -96,6 +177,9 @@ where
                 }
             },
 % endfor # each resource
+            _ if ${SOPT}.is_present("${SERVE_ARG}") => {
+                call_result = self._serve(dry_run, &mut err).await;
+            },
             _ => {
                 err.issues.push(CLIError::MissingCommandError);
                 writeln!(io::stderr(), "{}\n", ${SOPT}.usage()).ok();
@@ -113,7 +197,12 @@ where
     }
 
     // Please note that this call will fail if any part of the opt can't be handled
-    async fn new(opt: ArgMatches<'n>, connector: S) -> Result<Engine<'n, S>, InvalidOptionsError> {
+    async fn new(opt: ArgMatches<'n>, connector: S, timing: client::timing::Timing) -> Result<Engine<'n, S>, InvalidOptionsError> {
+        if opt.is_present("${REPLAY_ARG}") {
+            // calls are answered from fixtures, which need no authentication
+            let client = hyper::Client::builder().build(connector);
+            return Engine::with_auth(opt, client, Box::new(NoToken), timing).await;
+        }
         let (config_dir, secret) = {
             let config_dir = match client::assure_config_dir_exists(opt.value_of("${CONFIG_DIR_ARG}").unwrap_or("${CONFIG_DIR}")) {
                 Err(e) => return Err(InvalidOptionsError::single(e, 3)),
@@ -127,24 +216,49 @@ where
             }
         };
 
+        let profile = client::profile::resolve_profile(&config_dir, "${util.program_name()}", opt.value_of("${PROFILE_ARG}"),
+                                                       opt.is_present("${SELECT_PROFILE_FLAG}"));
+        let token_path = match client::profile::token_storage_path(&config_dir, "${util.program_name()}", profile.as_deref()) {
+            Ok(path) => path,
+            Err(e) => return Err(InvalidOptionsError::single(e, 1)),
+        };
         let client = hyper::Client::builder().build(connector);
 
         let auth = oauth2::InstalledFlowAuthenticator::with_client(
             secret,
             oauth2::InstalledFlowReturnMethod::HTTPRedirect,
             client.clone(),
-        ).persist_tokens_to_disk(format!("{}/${util.program_name()}", config_dir)).build().await.unwrap();
+        ).persist_tokens_to_disk(&token_path).build().await;
+        let auth = match auth {
+            Ok(auth) => auth,
+            Err(e) => return Err(InvalidOptionsError::single(
+                CLIError::Configuration(client::ConfigurationError::Io((token_path, e))), 3)),
+        };
 
-<% gpm = gen_global_parameter_names(parameters) %>\
+        Engine::with_auth(opt, client, Box::new(auth), timing).await
+    }
+
+    // Like `new()`, but uses the given authenticator instead of deriving one from the configuration
+    // directory, which allows it to be shared with other engines.
+    async fn with_auth(opt: ArgMatches<'n>, client: hyper::Client<S, hyper::body::Body>, auth: Box<dyn GetToken>,
+                       timing: client::timing::Timing) -> Result<Engine<'n, S>, InvalidOptionsError> {
+        let mut hub = ${hub_type_name}::with_auth(client, auth);
+        let mut err = InvalidOptionsError::new();
+        % if has_error_format_param(parameters):
+        hub.error_format(arg_from_str(opt.value_of("${ERROR_FORMAT_ARG}").unwrap_or("v2"), &mut err,
+                                      "--${ERROR_FORMAT_FLAG}", "error format"));
+        % endif
+        // the delegates decide whether to retry, the hub makes sure the maximum holds for any of them
+        hub.max_retries(Some(arg_from_str(opt.value_of("${MAX_RETRIES_ARG}").unwrap_or("0"), &mut err,
+                                          "--${MAX_RETRIES_FLAG}", "integer")));
+        if !err.issues.is_empty() {
+            return Err(err);
+        }
         let engine = Engine {
             opt: opt,
-            hub: ${hub_type_name}::new(client, auth),
-            gp: ${field_vec(gpm)},
-            gpm: vec![
-                % for pn in list(pn for pn in gpm if mangle_subcommand(pn) != pn):
-                    ("${mangle_subcommand(pn)}", "${pn}"),
-                % endfor # each global parameter
-                ]
+            hub: hub,
+            timing: timing,
+            answers: Mutex::new(Vec::new()),
         };
 
         match engine._doit(true).await {
@@ -180,7 +294,61 @@ where
 
     request_prop_type = None
     global_parameter_names = gen_global_parameter_names(parameters)
+    page_token = page_token_param(mc)
+    items_field = page_token and page_items_prop(mc.response_schema)
+    page_size, max_page_size = page_size_param(mc)
+    fan_out = fan_out_param(mc)
+    report = is_report_method(mc)
+    destructive = is_destructive_method(mc)
 %>\
+// checked along with the hub already
+let max_retries: u32 = ${SOPT}.value_of("${MAX_RETRIES_ARG}").and_then(|v| v.parse().ok()).unwrap_or(0);
+% if report:
+let report = client::report::ReportWriter::new(
+    arg_from_str(${SOPT}.value_of("${REPORT_FORMAT_ARG}").unwrap_or("json"), err, "--${REPORT_FORMAT_FLAG}", "report format"),
+    "${metric_prefix(util.canonical_name())}");
+% endif
+% if mc.response_schema:
+let projection = client::projection::Projection::new(${SOPT}.value_of("${ONLY_FIELDS_ARG}"),
+                                                     ${SOPT}.value_of("${STRIP_FIELDS_ARG}"), err);
+let output_format: client::output_format::OutputFormat = arg_from_str(${SOPT}.value_of("${OUTPUT_FORMAT_ARG}").unwrap_or("json"), err,
+                                                                      "--${OUTPUT_FORMAT_FLAG}", "output format");
+% endif
+% if page_token or fan_out:
+let on_error: client::fan_out::OnError = arg_from_str(${SOPT}.value_of("${ON_ERROR_ARG}").unwrap_or("continue"), err,
+                                                     "--${ON_ERROR_FLAG}", "error policy");
+% endif
+% if fan_out:
+let fan_out_names = client::fan_out::names_from_arg(${opt_value(fan_out.name)}, dry_run, err);
+let names = fan_out_names.clone().unwrap_or_else(|| vec![${opt_value(fan_out.name)}.to_string()]);
+let human = ${SOPT}.is_present("${HUMAN_FLAG}");
+if human && output_format == client::output_format::OutputFormat::NdjsonStream {
+    err.issues.push(CLIError::OptionConflicts("${HUMAN_FLAG}".to_string(), "${OUTPUT_FORMAT_FLAG}".to_string()));
+}
+let concurrency: usize = arg_from_str(${SOPT}.value_of("${CONCURRENCY_ARG}").unwrap_or("${DEFAULT_CONCURRENCY}"), err,
+                                      "--${CONCURRENCY_FLAG}", "integer");
+let recorder = ${SOPT}.value_of("${RECORD_ARG}").filter(|_| !dry_run).map(client::record::Recorder::new);
+let mut dlgs: Vec<_> = names.iter()
+    .map(|_| {
+        let dlg = client::VerboseDelegate::new(if dry_run { 0 } else { ${SOPT}.occurrences_of("${VERBOSE_FLAG}") })
+            .with_max_retries(max_retries);
+        match &recorder {
+            Some(recorder) => dlg.with_recorder(recorder.clone()),
+            None => dlg,
+        }
+    })
+    .collect();
+let mut calls = Vec::new();
+% else:
+let mut dlg = client::VerboseDelegate::new(if dry_run { 0 } else { ${SOPT}.occurrences_of("${VERBOSE_FLAG}") })
+    .with_max_retries(max_retries);
+if ${SOPT}.is_present("${TIMING_FLAG}") {
+    dlg = dlg.with_timing(self.timing.clone());
+}
+if let Some(dir) = ${SOPT}.value_of("${RECORD_ARG}").filter(|_| !dry_run) {
+    dlg = dlg.with_recorder(client::record::Recorder::new(dir));
+}
+% endif
     ## REQUIRED PARAMETERS
 % for p in mc.required_props:
 <%
@@ -208,7 +376,12 @@ let ${prop_name}: ${prop_type} = arg_from_str(&${opt_value(p.name)}, err, "<${ma
         # if type is not available, we know it's the request value, which should also be borrowed
         borrow = borrow_prefix(p)
         arg_name = mangle_ident(p.name)
-        if p.get('type', '') == 'string':
+        if page_token and is_request_value_property(mc, p):
+            # each page is fetched with the same request
+            arg_name += '.clone()'
+        if p is fan_out:
+            arg_name = 'name'
+        elif p.get('type', '') == 'string':
             if p.get('repeated', False):
                 arg_name = opt_values(p.name) + '.map(|&v| v.to_string()).collect::<Vec<String>>()'
             else:
@@ -216,11 +389,59 @@ let ${prop_name}: ${prop_type} = arg_from_str(&${opt_value(p.name)}, err, "<${ma
         call_args.append(borrow + arg_name)
     # end for each required prop
 %>\
+% if method_default_scope(mc.m):
+if dry_run {
+    client::check_scopes(&${opt_values(SCOPE_ARG, opt=SOPT)}.copied().collect::<Vec<_>>(),
+                         &[${', '.join(enclose_in('"', mc.m.scopes))}], err);
+}
+% endif
 % if track_download_flag:
 let mut download_mode = false;
 % endif
-let mut call = self.hub.${mangle_ident(resource)}().${mangle_ident(method)}(${', '.join(call_args)});
+% if page_token:
+let mut pager = client::Pager::new(${SOPT}.is_present("${ALL_PAGES_FLAG}"), ${SOPT}.value_of("${STATE_FILE_ARG}"),
+                                   ${SOPT}.is_present("${RESUME_FLAG}"), err)
+                  .with_continue_from(${SOPT}.value_of("${CONTINUE_FROM_ARG}"),
+                                      ${opt_values(VALUE_ARG)}.any(|v| v.split('=').next() == Some("${mangle_subcommand(page_token.name)}")),
+                                      err)
+                  .with_items_field(${'Some("%s")' % items_field if items_field else 'None'})
+% if has_next_page_token(mc.response_schema):
+                  .with_merge(|merged, page| client::paging::merge_as(merged, page, api::${mc.response_schema.id}::merge))
+% endif
+                  .with_stream_output(${SOPT}.is_present("${STREAM_OUTPUT_FLAG}"), err)
+                  .with_output_format(output_format)
+                  .with_max_pages(${SOPT}.value_of("${MAX_PAGES_ARG}"), err)
+                  .with_pages_dir(${SOPT}.value_of("${SPLIT_PAGES_ARG}"), err)
+                  .with_sort_key(${SOPT}.value_of("${SORT_BY_ARG}"), err)
+                  .with_filter(${SOPT}.value_of("${WHERE_ARG}"), err)
+                  .with_projection(projection)\
+% if report:
+
+                  .with_report(report)\
+% endif
+;
+let mut ostream = if dry_run {
+    Box::new(io::sink()) as Box<dyn Write>
+} else {
+    match pager.writer(opt.value_of("${(OUT_ARG)}")) {
+        Ok(f) => f,
+        Err(io_err) => return Err(DoitError::IoError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err)),
+    }
+};
+loop {
+% elif fan_out:
+for (name, dlg) in names.iter().zip(dlgs.iter_mut()) {
+% endif
+let mut call = self.hub.${mangle_ident(resource)}().${mangle_ident(method)}(${', '.join(call_args)})
+                        .delegate(${fan_out and 'dlg' or '&mut dlg'});
+% if page_size:
+if ${SOPT}.is_present("${ALL_PAGES_FLAG}") {
+    // use the documented maximum to need as few requests as possible, unless a page size is given below
+    call = call.${mangle_ident(setter_fn_name(page_size))}(${max_page_size});
+}
+% endif
 % if handle_props:
+let mut params = client::DuplicateParams::new(${SOPT}.is_present("${STRICT_FLAG}"), dry_run);
 for parg in ${opt_values(VALUE_ARG)} {
     let (key, value) = parse_kv_arg(&*parg, err, false);
     match key {
@@ -231,6 +452,9 @@ for parg in ${opt_values(VALUE_ARG)} {
     value_unwrap = f"value.unwrap_or({default_value})"
 %>\
         "${mangle_subcommand(p.name)}" => {
+        % if not p.get('repeated', False):
+            params.check(key, err);
+        % endif
         % if p.name == 'alt':
             if ${value_unwrap} == "media" {
                 download_mode = true;
@@ -243,33 +467,31 @@ for parg in ${opt_values(VALUE_ARG)} {
 ${value_unwrap}\
         % endif # handle conversion
 );
+        % if page_size and p.name == page_size.name:
+            if let Some(size) = value.and_then(|v| v.parse::<i64>().ok()) {
+                client::paging::check_page_size(key, size, ${max_page_size}, ${SOPT}.is_present("${STRICT_FLAG}"), dry_run, err);
+            }
+        % endif
         },
 % endfor # each property
         _ => {
 <%
     value_unwrap = 'value.unwrap_or("unset")'
 %>\
-            let mut found = false;
-            for param in &self.gp {
-                if key == *param {
+            match GLOBAL_PARAMS.iter().find(|t| t.0 == key) {
+                Some(&(_, param)) => {
+                    params.check(key, err);
                     % if track_download_flag and 'alt' in global_parameter_names:
                     if key == "alt" && ${value_unwrap} == "media" {
                         download_mode = true;
                     }
                     % endif
-                    found = true;
-                    call = call.${ADD_PARAM_FN}(self.gpm.iter().find(|t| t.0 == key).unwrap_or(&("", key)).1, ${value_unwrap});
-                    break;
+                    call = call.${ADD_PARAM_FN}(param, ${value_unwrap});
+                }
+                None => {
+                    const KNOWN_PARAMS: &[&str] = &[${comma_sep_fields(set(global_parameter_names) | optional_prop_names)}];
+                    err.issues.push(CLIError::UnknownParameter(key.to_string(), KNOWN_PARAMS.to_vec()));
                 }
-            }
-            if !found {
-                err.issues.push(CLIError::UnknownParameter(key.to_string(),
-                                                          {let mut v = Vec::new();
-                                                                   v.extend(self.gp.iter().map(|v|*v));
-% if comma_sep_fields(optional_prop_names):
-                                                                   v.extend([${comma_sep_fields(optional_prop_names)}].iter().map(|v|*v));
-% endif
-                                                                   v } ));
             }
         }
     }
@@ -278,15 +500,152 @@ ${value_unwrap}\
 % if mc.media_params:
 let vals = opt.values_of("${MODE_ARG}").unwrap().collect::<Vec<${'&'}str>>();
 let protocol = calltype_from_str(vals[0], [${', '.join('"%s"' % mp.protocol for mp in mc.media_params)}].iter().map(|&v| v.to_string()).collect(), err);
-let mut input_file = input_file_from_opts(vals[1], err);
-let mime_type = input_mime_from_opts(${opt_value(MIME_ARG, default=DEFAULT_MIME)}, err);
-% else:
+let mut input_file = input_file_from_opts(vals[1], dry_run, err);
+let mime_type = input_mime_from_opts(opt.value_of("${mangle_subcommand(MIME_ARG)}"), vals[1], err);
+% elif not (page_token or fan_out):
 let protocol = CallType::Standard;
 % endif # support upload
+% if fan_out:
+    % if method_default_scope(mc.m):
+for scope in ${opt_values(SCOPE_ARG, opt=SOPT)} {
+    call = call.${ADD_SCOPE_FN}(scope);
+}
+    % endif
+calls.push(call);
+}
+if dry_run {
+    if ${SOPT}.is_present("${VALIDATE_ONLY_FLAG}") {
+        for call in &calls {
+            client::check_constraints(call, err);
+        }
+    }
+    return Ok(());
+}
+assert!(err.issues.len() == 0);
+let mut ostream = match writer_from_opts(opt.value_of("${(OUT_ARG)}")) {
+    Ok(f) => f,
+    Err(io_err) => return Err(DoitError::IoError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err)),
+};
+let calls = calls.into_iter().zip(names.iter())
+    .map(|(call, name)| async move { (name, call.${api.terms.action}().await) })
+    .collect();
+let mut first_err = None;
+let mut output_err = None;
+let mut failed = 0;
+let mut done = 0;
+client::fan_out::for_each_buffered(calls, concurrency, |(name, result)| {
+    done += 1;
+    let written = match result {
+        Err(api_err) => {
+            let mut written = Ok(());
+            if fan_out_names.is_some() {
+                writeln!(io::stderr(), "{}: {}", name, api_err).ok();
+                if on_error == client::fan_out::OnError::Continue {
+                    written = json::to_writer(&mut ostream, &client::fan_out::error_line(name, &api_err))
+                        .map_err(io::Error::from)
+                        .and_then(|()| writeln!(ostream))
+                        .and_then(|()| ostream.flush());
+                }
+            }
+            failed += 1;
+            first_err.get_or_insert(api_err);
+            if on_error == client::fan_out::OnError::Stop {
+                return false;
+            }
+            written
+        },
+        Ok(response) => to_output_value(response.body()).and_then(|mut value| {
+            projection.apply(&mut value);
+            if human {
+                if fan_out_names.is_some() && done > 1 {
+                    writeln!(ostream)?;
+                }
+                client::human::write_summary(&value, &mut ostream)?;
+            } else if fan_out_names.is_some() || output_format == client::output_format::OutputFormat::NdjsonStream {
+                json::to_writer(&mut ostream, &value)?;
+                writeln!(ostream)?;
+            } else {
+                json::to_writer_pretty(&mut ostream, &value)?;
+            }
+            ostream.flush()
+        }),
+    };
+    match written {
+        Ok(()) => true,
+        Err(io_err) => {
+            // there is no point in making further calls if their results can't be written
+            output_err = Some(io_err);
+            false
+        }
+    }
+}).await;
+if let Some(io_err) = output_err {
+    return Err(DoitError::OutputError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err));
+}
+match (first_err, fan_out_names) {
+    (None, _) => Ok(()),
+    (Some(api_err), None) => Err(DoitError::ApiError(api_err)),
+    (Some(_), Some(_)) => Err(DoitError::FanOutError(failed, done, names.len())),
+}\
+% elif page_token:
+if let Some(token) = pager.page_token() {
+    call = call.${mangle_ident(setter_fn_name(page_token))}(token);
+}
+if dry_run {
+    if ${SOPT}.is_present("${VALIDATE_ONLY_FLAG}") {
+        client::check_constraints(&call, err);
+    }
+    return Ok(());
+}
+assert!(err.issues.len() == 0);
+    % if method_default_scope(mc.m):
+for scope in ${opt_values(SCOPE_ARG, opt=SOPT)} {
+    call = call.${ADD_SCOPE_FN}(scope);
+}
+    % endif
+match call.${api.terms.action}().await {
+    Err(api_err) => {
+        if on_error == client::fan_out::OnError::Continue {
+            if let Err(io_err) = pager.finish_partial(&mut ostream) {
+                return Err(DoitError::IoError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err));
+            }
+        }
+        return Err(DoitError::ApiError(api_err));
+    },
+    Ok(response) => {
+        let value = match to_output_value(response.body()) {
+            Ok(value) => value,
+            Err(io_err) => return Err(DoitError::OutputError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err)),
+        };
+        match pager.add_page(value, &mut ostream) {
+            Ok(true) => {},
+            Ok(false) => break,
+            Err(io_err) => return Err(DoitError::IoError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err)),
+        }
+    }
+}
+}
+if pager.truncated() {
+    writeln!(io::stderr(), "The results were truncated after {} pages, as --${MAX_PAGES_FLAG} was reached.", pager.pages()).ok();
+}
+match pager.finish(&mut ostream) {
+    Ok(()) => Ok(()),
+    Err(io_err) => Err(DoitError::IoError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err)),
+}\
+% else:
 if dry_run {
+    if ${SOPT}.is_present("${VALIDATE_ONLY_FLAG}") {
+        client::check_constraints(&call, err);
+    }
     Ok(())
 } else {
     assert!(err.issues.len() == 0);
+    % if destructive:
+    if !client::confirm::confirm("Do you really want to call ${mangle_subcommand(resource)} ${mangle_subcommand(method)}? It can't be undone.",
+                                 ${SOPT}.is_present("${ASSUME_YES_FLAG}")) {
+        return Err(DoitError::Aborted);
+    }
+    % endif
     % if method_default_scope(mc.m):
     for scope in ${opt_values(SCOPE_ARG, opt=SOPT)} {
         call = call.${ADD_SCOPE_FN}(scope);
@@ -312,7 +671,8 @@ if dry_run {
     } {
         Err(api_err) => Err(DoitError::ApiError(api_err)),
         % if mc.response_schema:
-        Ok((mut response, output_schema)) => {
+        Ok(response) => {
+            let (mut response, output_schema) = response.into_parts();
         % else:
         Ok(mut response) => {
         % endif # handle output structure
@@ -323,19 +683,30 @@ if dry_run {
             if !download_mode {
             % endif
             % if mc.response_schema:
-            let mut value = json::value::to_value(&output_schema).expect("serde to work");
-            remove_json_null_values(&mut value);
-            json::to_writer_pretty(&mut ostream, &value).unwrap();
-            ostream.flush().unwrap();
+            let written = to_output_value(&output_schema).and_then(|mut value| {
+                projection.apply(&mut value);
+            % if report:
+                report.write(&value, &mut ostream)
+            % else:
+                output_format.write(&value, &mut ostream)
+            % endif
+            });
+            if let Err(io_err) = written {
+                return Err(DoitError::OutputError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err));
+            }
             % endif
             % if track_download_flag:
             } else {
             % endif
             % if supports_media_download:
             ## Download is the only option - nothing else matters
-            let bytes = hyper::body::to_bytes(response.into_body()).await.expect("a string as API currently is inefficient").to_vec();
-            ostream.write_all(&bytes).expect("write to be complete");
-            ostream.flush().expect("io to never fail which should really be fixed one day");
+            let bytes = match hyper::body::to_bytes(response.into_body()).await {
+                Ok(bytes) => bytes,
+                Err(err) => return Err(DoitError::ApiError(Error::HttpError(err))),
+            };
+            if let Err(io_err) = ostream.write_all(&bytes).and_then(|()| ostream.flush()) {
+                return Err(DoitError::OutputError(${opt_value(OUT_ARG, default='-')}.to_string(), io_err));
+            }
             % endif
             % if track_download_flag:
             }
@@ -344,13 +715,14 @@ if dry_run {
         }
     }
 }\
+% endif # handle paging
 </%def>
 
 <%def name="_request_value_impl(c, request_cli_schema, request_prop_name, request_prop_type)">
 <%
     allow_optionals_fn = lambda s: is_schema_with_optionals(schema_markers(s, c, transitive=False))
 
-    def flatten_schema_fields(schema, res, fields, cur=list()):
+    def flatten_schema_fields(schema, res, fields, dates, cur=list()):
         if len(cur) == 0:
             cur = list()
 
@@ -365,21 +737,43 @@ if dry_run {
                 cur[-1][0] = mangle_ident(fn)
                 res.append((schema, f, list(cur)))
             else:
-                flatten_schema_fields(f, res, fields, cur)
+                if is_date_schema(f):
+                    dates.append(list(cur))
+                flatten_schema_fields(f, res, fields, dates, cur)
             cur.pop()
         # endfor
     # end utility
 
     schema_fields = list()
     fields = set()
-    flatten_schema_fields(request_cli_schema, schema_fields, fields)
+    date_fields = list()
+    flatten_schema_fields(request_cli_schema, schema_fields, fields, date_fields)
 %>\
 let mut field_cursor = FieldCursor::default();
 let mut object = json::value::Value::Object(Default::default());
 
-for kvarg in ${opt_values(KEY_VALUE_ARG)} {
+let mut kvargs: Vec<String> = ${opt_values(KEY_VALUE_ARG)}.map(|v| v.to_string()).collect();
+if opt.is_present("${INTERACTIVE_FLAG}") {
+    let mut answers = self.answers.lock().unwrap();
+    if dry_run {
+        let fields: &[client::interactive::Field] = &[
+    % for schema, fe, f in schema_fields:
+<%
+    pname = FIELD_SEP.join(mangle_subcommand(t[1]) for t in f)
+    choices = fe.actual_property.get('enum', [])
+%>\
+            client::interactive::Field { path: "${pname}", jtype: JsonType::${json_type_enum(f[-1][1], fe)}, ctype: ComplexType::${CTYPE_TO_ENUM_MAP[fe.container_type]}, choices: &[${', '.join(enclose_in('"', choices))}] },
+    % endfor # each nested field
+        ];
+        *answers = client::interactive::ask_fields(fields, &kvargs, err);
+    }
+    kvargs.extend(answers.iter().cloned());
+}
+
+for kvarg in kvargs.iter() {
     let last_errc = err.issues.len();
     let (key, value) = parse_kv_arg(&*kvarg, err, false);
+    let value = value.map(|v| expand_env_vars(v, &kvarg, err));
     let mut temp_cursor = field_cursor.clone();
     if let Err(field_err) = temp_cursor.set(&*key) {
         err.issues.push(field_err);
@@ -400,21 +794,30 @@ for kvarg in ${opt_values(KEY_VALUE_ARG)} {
 <%
     pname = FIELD_SEP.join(mangle_subcommand(t[1]) for t in f)
     sname = FIELD_SEP.join(t[1] for t in f)
-    ptype = actual_json_type(f[-1][1], fe.actual_property.type)
-    jtype = 'JsonType::' + JSON_TYPE_TO_ENUM_MAP[ptype]
+    jtype = 'JsonType::' + json_type_enum(f[-1][1], fe)
     ctype = 'ComplexType::' + CTYPE_TO_ENUM_MAP[fe.container_type]
 %>\
             "${pname}" => Some(("${sname}", JsonTypeInfo { jtype: ${jtype}, ctype: ${ctype} })),
             % endfor # each nested field
+            % for f in date_fields:
+            "${FIELD_SEP.join(mangle_subcommand(t[1]) for t in f)}" => Some(("${FIELD_SEP.join(t[1] for t in f)}", JsonTypeInfo { jtype: JsonType::Date, ctype: ComplexType::Pod })),
+            % endfor # each date field
             _ => {
-                let suggestion = FieldCursor::did_you_mean(key, &${field_vec(sorted(fields))});
-                err.issues.push(CLIError::Field(FieldError::Unknown(temp_cursor.to_string(), suggestion, value.map(|v| v.to_string()))));
+                let suggestion = FieldCursor::did_you_mean(key, &[${comma_sep_fields(fields)}]);
+                err.issues.push(CLIError::Field(FieldError::Unknown(temp_cursor.to_string(), suggestion, value.as_ref().map(|v| v.to_string()))));
                 None
             }
         };
     if let Some((field_cursor_str, type_info)) = type_info {
-        FieldCursor::from(field_cursor_str).set_json_value(&mut object, value.unwrap(), type_info, err, &temp_cursor);
+        FieldCursor::from(field_cursor_str).set_json_value(&mut object, &value.unwrap(), type_info, err, &temp_cursor);
     }
 }
-let mut ${request_prop_name}: api::${request_prop_type} = json::value::from_value(object).unwrap();
+dlg.request_body(&object);
+let mut ${request_prop_name}: api::${request_prop_type} = match json::value::from_value(object) {
+    Ok(value) => value,
+    Err(json_err) => {
+        err.issues.push(CLIError::RequestValue(json_err.to_string()));
+        return Ok(());
+    }
+};
 </%def>
@@ -4,7 +4,9 @@
 <%  
     from generator.lib.util import (new_context, rust_comment, to_extern_crate_name, library_to_crate_name, library_name,
                       indent_all_but_first_by)
-    from generator.lib.cli import OUT_ARG, DEBUG_FLAG, opt_value
+    from generator.lib.cli import (OUT_ARG, DEBUG_FLAG, RAW_ERRORS_FLAG, DRY_RUN_FLAG, opt_value, CONFIG_DIR, CONFIG_DIR_ARG,
+                                   CONFIG_DIR_FLAG, CONFIG_FILE_FLAG, OUTPUT_SCHEMA_FLAG, TIMING_FLAG, VALIDATE_ONLY_FLAG, REPLAY_ARG,
+                                   ADDRESS_FAMILY_FLAG, ADDRESS_FAMILY_ARG, HAPPY_EYEBALLS_FLAG, HAPPY_EYEBALLS_ARG)
 
     c = new_context(schemas, resources)
     default_user_agent = "google-cli-rust-client/" + cargo.build_version
@@ -21,7 +23,7 @@ use std::env;
 use std::io::{self, Write};
 use clap::{App, SubCommand, Arg};
 
-use ${to_extern_crate_name(library_to_crate_name(library_name(name, version), make.depends_on_suffix))}::{api, Error, oauth2, client::chrono, FieldMask};
+use ${to_extern_crate_name(library_to_crate_name(library_name(name, version), make.depends_on_suffix))}::{api, Error, oauth2, client::chrono, client::GetToken, client::NoToken, FieldMask};
 
 
 use google_clis_common as client;
@@ -32,19 +34,85 @@ ${engine.new(c)}\
 async fn main() {
     let mut exit_status = 0i32;
     ${argparse.new(c) | indent_all_but_first_by(1)}\
-    let matches = app.get_matches();
+    // the configuration file is merged into the arguments before they are parsed
+    let args: Vec<std::ffi::OsString> = env::args_os().collect();
+    let given = client::config_file::GivenFlags::scan(&args, config_flags);
+    let default_config_file = client::assure_config_dir_exists(given.value("${CONFIG_DIR_FLAG}").unwrap_or("${CONFIG_DIR}"))
+        .ok()
+        .map(|dir| client::config_file::default_config_file_path(&dir, "${util.program_name()}"));
+    let args = match client::config_file::args_with_config_file(args, &given, given.value("${CONFIG_FILE_FLAG}"),
+                                                                default_config_file, "${CONFIG_FILE_FLAG}", config_flags) {
+        Ok(args) => args,
+        Err(err) => {
+            writeln!(io::stderr(), "{}", err).ok();
+            std::process::exit(1);
+        }
+    };
+    // the outline of the response is written before the arguments of the method are checked and
+    // authentication is set up, as it needs neither
+    if given.contains("${OUTPUT_SCHEMA_FLAG}") {
+        if let [resource, method, ..] = given.command() {
+            if let Some(outline) = output_schema(resource, method) {
+                io::stdout().write_all(outline.as_bytes()).ok();
+                std::process::exit(0);
+            }
+        }
+    }
+    let matches = app.get_matches_from(args);
 
-    let debug = matches.is_present("a${DEBUG_FLAG}");
+    let debug = matches.is_present("${DEBUG_FLAG}");
+    let raw_errors = matches.is_present("${RAW_ERRORS_FLAG}");
+    let dry_run = matches.is_present("${DRY_RUN_FLAG}") || matches.is_present("${VALIDATE_ONLY_FLAG}");
+    // Each layer of the connector marks the phase it completes, for --${TIMING_FLAG}
+    let timing = client::timing::Timing::default();
+    let address_family = matches.value_of("${ADDRESS_FAMILY_ARG}").unwrap_or("any");
+    let address_family = match address_family.parse::<client::dns::AddressFamily>() {
+        Ok(family) => family,
+        Err(err) => {
+            write!(io::stderr(), "{}", client::CLIError::ParseError("--${ADDRESS_FAMILY_FLAG}".to_string(),
+                                                                  "address family".to_string(),
+                                                                  address_family.to_string(), err)).ok();
+            std::process::exit(1);
+        }
+    };
+    let resolver = client::dns::Resolver::new(hyper::client::connect::dns::GaiResolver::new(), address_family);
+    let resolver = timing.wrap(resolver, client::timing::Phase::Resolved);
+    let mut http = hyper::client::HttpConnector::new_with_resolver(resolver);
+    http.enforce_http(false);
+    if let Some(timeout) = matches.value_of("${HAPPY_EYEBALLS_ARG}") {
+        match timeout.parse::<u64>() {
+            Ok(0) => http.set_happy_eyeballs_timeout(None),
+            Ok(ms) => http.set_happy_eyeballs_timeout(Some(std::time::Duration::from_millis(ms))),
+            Err(err) => {
+                write!(io::stderr(), "{}", client::CLIError::ParseError("--${HAPPY_EYEBALLS_FLAG}".to_string(),
+                                                                      "integer".to_string(),
+                                                                      timeout.to_string(), err.to_string())).ok();
+                std::process::exit(1);
+            }
+        }
+    }
     let connector = hyper_rustls::HttpsConnectorBuilder::new().with_native_roots()
         .https_or_http()
         .enable_http1()
-        .build();
+        .wrap_connector(timing.wrap(http, client::timing::Phase::Connected));
+    let connector = timing.wrap(connector, client::timing::Phase::Secured);
+    let connector = match matches.value_of("${REPLAY_ARG}") {
+        Some(dir) => match client::replay::Replayer::from_dir(dir) {
+            Ok(replayer) => client::replay::Connector::Fixtures(replayer),
+            Err(err) => {
+                writeln!(io::stderr(), "Failed to read the fixtures in '{}': {}", dir, err).ok();
+                std::process::exit(1);
+            }
+        },
+        None => client::replay::Connector::Network(connector),
+    };
 
-    match Engine::new(matches, connector).await {
+    match Engine::new(matches, connector, timing).await {
         Err(err) => {
             exit_status = err.exit_code;
             writeln!(io::stderr(), "{}", err).ok();
         },
+        Ok(_) if dry_run => {},
         Ok(engine) => {
             if let Err(doit_err) = engine.doit().await {
                 exit_status = 1;
@@ -52,9 +120,27 @@ async fn main() {
                     DoitError::IoError(path, err) => {
                         writeln!(io::stderr(), "Failed to open output file '{}': {}", path, err).ok();
                     },
+                    DoitError::OutputError(path, err) => {
+                        writeln!(io::stderr(), "Failed to write the output to '{}': {}", path, err).ok();
+                    },
+                    DoitError::FanOutError(failed, done, total) => {
+                        if done < total {
+                            writeln!(io::stderr(), "{} of {} calls failed, {} were skipped", failed, total, total - done).ok();
+                        } else {
+                            writeln!(io::stderr(), "{} of {} calls failed", failed, total).ok();
+                        }
+                    },
+                    DoitError::Aborted => {
+                        writeln!(io::stderr(), "Aborted.").ok();
+                    },
+                    DoitError::ServeError(addr, err) => {
+                        writeln!(io::stderr(), "Failed to serve on '{}': {}", addr, err).ok();
+                    },
                     DoitError::ApiError(err) => {
                         if debug {
                             writeln!(io::stderr(), "{:#?}", err).ok();
+                        } else if let Some(pretty) = err.pretty_status().filter(|_| !raw_errors) {
+                            writeln!(io::stderr(), "{}", pretty).ok();
                         } else {
                             writeln!(io::stderr(), "{}", err).ok();
                         }